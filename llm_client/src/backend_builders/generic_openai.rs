@@ -0,0 +1,91 @@
+use crate::LlmClient;
+use llm_devices::logging::{LoggingConfig, LoggingConfigTrait};
+use llm_interface::llms::{
+    api::{
+        config::{ApiConfig, LlmApiConfigTrait},
+        generic_openai::{GenericApiBackend, GenericApiConfig},
+    },
+    LlmBackend,
+};
+use llm_utils::models::api_model::{openai::OpenAiModelTrait, ApiLlmModel};
+
+// Everything here can be implemented for any struct.
+/// A builder for OpenAI compatible servers and proxies, like vLLM, LM Studio and LiteLLM.
+pub struct GenericApiBackendBuilder {
+    pub config: GenericApiConfig,
+    pub model: ApiLlmModel,
+}
+
+impl Default for GenericApiBackendBuilder {
+    fn default() -> Self {
+        let mut config = GenericApiConfig::default();
+        config.api_config.host = "localhost".to_string();
+        config.api_config.api_key_env_var = "GENERIC_API_KEY".to_string();
+        Self {
+            config,
+            model: ApiLlmModel::gpt_4_o_mini(),
+        }
+    }
+}
+
+impl GenericApiBackendBuilder {
+    pub fn init(self) -> crate::Result<LlmClient> {
+        Ok(LlmClient::new(std::sync::Arc::new(LlmBackend::GenericApi(
+            GenericApiBackend::new(self.config, self.model)?,
+        ))))
+    }
+
+    /// The model id the server expects in requests. Token counts still use the tokenizer of the selected OpenAI model.
+    pub fn model_id<S: Into<String>>(mut self, model_id: S) -> Self {
+        self.model.model_base.model_id = model_id.into();
+        self
+    }
+
+    pub fn completion_path<S: Into<String>>(mut self, path: S) -> Self {
+        self.config.completion_path = path.into();
+        self
+    }
+
+    /// The path used by [`crate::raw_completion::RawCompletion`]. Defaults to `/completions`.
+    pub fn raw_completion_path<S: Into<String>>(mut self, path: S) -> Self {
+        self.config.raw_completion_path = path.into();
+        self
+    }
+
+    pub fn use_https(mut self, use_https: bool) -> Self {
+        self.config.use_https = use_https;
+        self
+    }
+
+    pub fn base_path<S: Into<String>>(mut self, base_path: S) -> Self {
+        self.config.base_path = Some(base_path.into());
+        self
+    }
+
+    pub fn header<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.config.custom_headers.insert(name.into(), value.into());
+        self
+    }
+}
+
+impl OpenAiModelTrait for GenericApiBackendBuilder {
+    fn model(&mut self) -> &mut ApiLlmModel {
+        &mut self.model
+    }
+}
+
+impl LlmApiConfigTrait for GenericApiBackendBuilder {
+    fn api_base_config_mut(&mut self) -> &mut ApiConfig {
+        &mut self.config.api_config
+    }
+
+    fn api_config(&self) -> &ApiConfig {
+        &self.config.api_config
+    }
+}
+
+impl LoggingConfigTrait for GenericApiBackendBuilder {
+    fn logging_config_mut(&mut self) -> &mut LoggingConfig {
+        &mut self.config.logging_config
+    }
+}
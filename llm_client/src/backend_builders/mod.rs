@@ -1,7 +1,9 @@
 pub mod anthropic;
+pub mod generic_openai;
 #[cfg(feature = "llama_cpp_backend")]
 pub mod llama_cpp;
 #[cfg(feature = "mistral_rs_backend")]
 pub mod mistral_rs;
 pub mod openai;
 pub mod perplexity;
+pub mod router;
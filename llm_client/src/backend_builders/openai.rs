@@ -30,6 +30,19 @@ impl OpenAiBackendBuilder {
             OpenAiBackend::new(self.config, self.model)?,
         ))))
     }
+
+    /// Use an Azure OpenAI deployment. Set the resource endpoint, e.g. `my-resource.openai.azure.com`, with `with_api_host`.
+    /// The API key is loaded from `AZURE_OPENAI_API_KEY` unless another env var is set.
+    pub fn azure_deployment<S: Into<String>>(mut self, deployment: S) -> Self {
+        self.config.set_azure_deployment(deployment);
+        self
+    }
+
+    /// Non default Azure OpenAI `api-version`.
+    pub fn azure_api_version<S: Into<String>>(mut self, api_version: S) -> Self {
+        self.config.set_azure_api_version(api_version);
+        self
+    }
 }
 
 impl LlmApiConfigTrait for OpenAiBackendBuilder {
@@ -0,0 +1,33 @@
+use crate::LlmClient;
+use llm_interface::llms::router::{
+    builder::{RouterBackendBuilder as InterfaceRouterBuilder, RouterBackendTrait},
+    RoutingStrategy,
+};
+
+// Everything here can be implemented for any struct.
+#[derive(Default)]
+pub struct RouterBackendBuilder {
+    pub builder: InterfaceRouterBuilder,
+}
+
+impl RouterBackendBuilder {
+    pub fn init(self) -> crate::Result<LlmClient> {
+        Ok(LlmClient::new(self.builder.init()?))
+    }
+
+    /// Adds the backend of an already initialized client. The first backend added is the primary.
+    pub fn add_client(self, client: &LlmClient) -> Self {
+        self.add_backend(std::sync::Arc::clone(&client.backend))
+    }
+
+    pub fn strategy(mut self, strategy: RoutingStrategy) -> Self {
+        self.builder.strategy = strategy;
+        self
+    }
+}
+
+impl RouterBackendTrait for RouterBackendBuilder {
+    fn router_builder(&mut self) -> &mut InterfaceRouterBuilder {
+        &mut self.builder
+    }
+}
@@ -24,31 +24,47 @@ impl BasicCompletion {
         &mut self.base_req.prompt
     }
 
+    /// Renders the prompt as it would be sent to the backend, without sending it.
+    /// Useful for checking the chat template of a local model.
+    pub fn dry_run(&self) -> crate::Result<String> {
+        self.base_req.render_prompt()
+    }
+
     pub async fn run(&mut self) -> crate::Result<CompletionResponse> {
         let mut res = self.base_req.request().await?;
 
-        match *self.base_req.backend {
+        let backend = match (&*self.base_req.backend, &res.served_by) {
+            (LlmBackend::Router(router), Some(served_by)) => {
+                &router.backends[served_by.backend_index].backend
+            }
+            _ => &self.base_req.backend,
+        };
+        match **backend {
             #[cfg(feature = "llama_cpp_backend")]
             LlmBackend::LlamaCpp(_) => {
-                res.content = self.parse_response(&res.content)?;
+                res.content = Self::parse_response(backend, &res.content)?;
             }
             #[cfg(feature = "mistral_rs_backend")]
             LlmBackend::MistralRs(_) => {
-                res.content = self.parse_response(&res.content)?;
+                res.content = Self::parse_response(backend, &res.content)?;
             }
             _ => (),
         }
         Ok(res)
     }
 
-    fn parse_response(&self, content: &str) -> crate::Result<String> {
+    fn parse_response(backend: &LlmBackend, content: &str) -> crate::Result<String> {
         if content.is_empty() {
             return Err(anyhow::format_err!(
                 "parse_response error: content.is_empty()"
             ));
         }
-        let bos = self.base_req.backend.bos_token();
-        let eos = self.base_req.backend.eos_token();
+        let bos = backend
+            .bos_token()
+            .ok_or_else(|| anyhow::format_err!("parse_response error: backend has no bos_token"))?;
+        let eos = backend
+            .eos_token()
+            .ok_or_else(|| anyhow::format_err!("parse_response error: backend has no eos_token"))?;
         let content = content
             .strip_prefix(&format!("{}\n\n", bos))
             .or_else(|| content.strip_prefix(&format!("{}\n", bos)))
@@ -64,7 +80,7 @@ impl BasicCompletion {
         let content = content
             .strip_suffix(&format!("{}\n\n", eos))
             .or_else(|| content.strip_suffix(&format!("{}\n", eos)))
-            .or_else(|| content.strip_suffix(&eos))
+            .or_else(|| content.strip_suffix(eos))
             .unwrap_or(content)
             .to_string();
 
@@ -0,0 +1,217 @@
+use super::step::InferenceStep;
+use anyhow::Result;
+use llm_interface::requests::{
+    completion::{CompletionFinishReason, CompletionRequest},
+    constraints::stop_sequence::StoppingSequence,
+};
+use serde::{Deserialize, Serialize};
+
+/// Stands in for native grammars on backends that don't support them, like OpenAI and Anthropic.
+/// The grammar is described in the prompt, the response is validated against it, and the LLM is re-prompted with the validation error until it passes or the retries run out.
+/// When the retries run out, the step fails with [`GrammarEmulationError::RetriesExhausted`].
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GrammarEmulation {
+    /// When false, the response is validated once and `llm_content` is left empty if it fails.
+    ///
+    /// Defaults to true.
+    pub enabled: bool,
+    /// Adds the grammar to the prompt as instructions before the first attempt.
+    ///
+    /// Defaults to true.
+    pub instructions: bool,
+    /// The number of times to re-prompt with the validation error after the first attempt fails.
+    ///
+    /// Defaults to 2.
+    pub max_retries: u8,
+}
+
+impl Default for GrammarEmulation {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            instructions: true,
+            max_retries: 2,
+        }
+    }
+}
+
+pub(crate) async fn emulated_grammar_request(
+    base_req: &CompletionRequest,
+    step: &mut InferenceStep,
+) -> Result<()> {
+    let emulation = step.step_config.grammar_emulation.clone();
+    // Retries are sent on a copy, so the failed attempts don't end up in the cascade's prompt.
+    let mut req = base_req.clone();
+    if emulation.instructions {
+        req.prompt
+            .add_user_message()?
+            .set_content(grammar_instructions(
+                &step.step_config.grammar.grammar_string(),
+            ));
+    }
+
+    let mut attempts = EmulationAttempts::new(emulation.max_retries);
+    loop {
+        let res = req.request().await?;
+        step.logprob = super::mean_logprob(&res);
+        let no_result = matches!(
+            res.finish_reason,
+            CompletionFinishReason::MatchingStoppingSequence(StoppingSequence::NoResult(_))
+        );
+        let validation = if no_result {
+            None
+        } else {
+            Some(step.step_config.grammar.validate_clean(&res.content))
+        };
+        match attempts.next(&res.content, validation)? {
+            AttemptOutcome::Done(content) => {
+                step.llm_content = content;
+                return Ok(());
+            }
+            AttemptOutcome::Retry(instructions) => {
+                req.prompt
+                    .add_assistant_message()?
+                    .set_content(&res.content);
+                req.prompt.add_user_message()?.set_content(instructions);
+            }
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GrammarEmulationError {
+    #[error("Response failed emulated grammar validation after {attempts} attempts: {error}")]
+    RetriesExhausted {
+        attempts: u16,
+        /// The last response.
+        content: String,
+        error: String,
+    },
+}
+
+/// Counts the attempts of an emulated grammar request, and decides what follows each response.
+struct EmulationAttempts {
+    max_retries: u8,
+    attempt: u16,
+}
+
+#[derive(Debug, PartialEq)]
+enum AttemptOutcome {
+    /// The validated content, or `None` when the model gave the no result stop word.
+    Done(Option<String>),
+    /// Re-prompt with these instructions.
+    Retry(String),
+}
+
+impl EmulationAttempts {
+    fn new(max_retries: u8) -> Self {
+        Self {
+            max_retries,
+            attempt: 0,
+        }
+    }
+
+    /// `validation` is `None` when the model gave the no result stop word.
+    fn next<E: std::fmt::Display>(
+        &mut self,
+        content: &str,
+        validation: Option<std::result::Result<String, E>>,
+    ) -> std::result::Result<AttemptOutcome, GrammarEmulationError> {
+        self.attempt += 1;
+        match validation {
+            None => Ok(AttemptOutcome::Done(None)),
+            Some(Ok(content)) => Ok(AttemptOutcome::Done(Some(content))),
+            Some(Err(e)) => {
+                crate::info!(
+                    attempt = self.attempt,
+                    error = %e,
+                    "Response failed emulated grammar validation"
+                );
+                if self.attempt > u16::from(self.max_retries) {
+                    return Err(GrammarEmulationError::RetriesExhausted {
+                        attempts: self.attempt,
+                        content: content.to_owned(),
+                        error: e.to_string(),
+                    });
+                }
+                Ok(AttemptOutcome::Retry(retry_instructions(
+                    content,
+                    &e.to_string(),
+                )))
+            }
+        }
+    }
+}
+
+fn grammar_instructions(grammar_string: &str) -> String {
+    format!(
+        "Your response must be valid for the following GBNF grammar. Respond with only the text the grammar allows.\n```\n{grammar_string}\n```"
+    )
+}
+
+fn retry_instructions(content: &str, error: &str) -> String {
+    format!(
+        "Your response '{content}' did not match the required format: {error}\nRespond again, following the required format exactly."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attempts_retry_until_exhausted() {
+        let mut attempts = EmulationAttempts::new(2);
+        for _ in 0..2 {
+            assert_eq!(
+                attempts
+                    .next("maybe", Some(Err::<String, _>("not a boolean")))
+                    .unwrap(),
+                AttemptOutcome::Retry(retry_instructions("maybe", "not a boolean"))
+            );
+        }
+        match attempts.next("maybe", Some(Err::<String, _>("not a boolean"))) {
+            Err(GrammarEmulationError::RetriesExhausted {
+                attempts,
+                content,
+                error,
+            }) => {
+                assert_eq!(attempts, 3);
+                assert_eq!(content, "maybe");
+                assert_eq!(error, "not a boolean");
+            }
+            res => panic!("expected RetriesExhausted, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_attempts_done() {
+        let mut attempts = EmulationAttempts::new(0);
+        assert_eq!(
+            attempts
+                .next::<&str>(" true ", Some(Ok("true".to_owned())))
+                .unwrap(),
+            AttemptOutcome::Done(Some("true".to_owned()))
+        );
+        let mut attempts = EmulationAttempts::new(1);
+        assert!(matches!(
+            attempts.next("maybe", Some(Err("not a boolean"))),
+            Ok(AttemptOutcome::Retry(_))
+        ));
+        assert_eq!(
+            attempts.next::<&str>("None", None).unwrap(),
+            AttemptOutcome::Done(None)
+        );
+    }
+
+    #[test]
+    fn test_instructions() {
+        let instructions = grammar_instructions("root ::= \"true\" | \"false\"");
+        assert!(instructions.contains("GBNF grammar"));
+        assert!(instructions.ends_with("```\nroot ::= \"true\" | \"false\"\n```"));
+        assert_eq!(
+            retry_instructions("maybe", "expected a boolean"),
+            "Your response 'maybe' did not match the required format: expected a boolean\nRespond again, following the required format exactly."
+        );
+    }
+}
@@ -1,21 +1,27 @@
+pub mod grammar_emulation;
+pub mod replay;
 pub mod round;
 pub mod step;
 
 use anyhow::{anyhow, Result};
 use core::panic;
 use llm_interface::requests::{
-    completion::{CompletionFinishReason, CompletionRequest},
+    completion::{CompletionFinishReason, CompletionRequest, CompletionResponse},
     constraints::stop_sequence::StoppingSequence,
 };
 pub use round::CascadeRound;
+use serde::{Deserialize, Serialize};
 use step::InferenceStep;
 
-#[derive(Clone)]
+/// Serializes with serde, with every round's task, steps, step configs, and each inference step's raw `llm_content`,
+/// timing and request config, so a flow can be saved as an audit trail and replayed with [`CascadeFlow::replay`].
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CascadeFlow {
     pub cascade_name: String,
     pub duration: std::time::Duration,
     pub result_can_be_none: bool,
     pub rounds: Vec<CascadeRound>,
+    #[serde(skip, default = "std::time::Instant::now")]
     pub start_time: std::time::Instant,
 }
 
@@ -39,7 +45,18 @@ impl CascadeFlow {
     pub async fn run_all_rounds(&mut self, base_req: &mut CompletionRequest) -> Result<()> {
         self.start_time = std::time::Instant::now();
 
-        for round in self.rounds.iter_mut() {
+        for i in 0..self.rounds.len() {
+            let (previous_rounds, rounds) = self.rounds.split_at_mut(i);
+            let round = &mut rounds[0];
+            if round.skipped {
+                continue;
+            }
+            if let Some(condition) = &round.condition {
+                if !condition(previous_rounds) {
+                    round.skip();
+                    continue;
+                }
+            }
             round.run_all_steps(base_req).await?;
         }
 
@@ -63,11 +80,24 @@ impl CascadeFlow {
         Ok(())
     }
 
+    /// The result of the last round that wasn't skipped.
     pub fn primitive_result(&self) -> Option<String> {
-        match self.rounds.last() {
-            Some(round) => round.primitive_result(),
-            None => panic!("No rounds in cascade"),
+        if self.rounds.is_empty() {
+            panic!("No rounds in cascade")
         }
+        self.last_run_round()
+            .and_then(|round| round.primitive_result())
+    }
+
+    /// The mean token logprob of the step that produced `primitive_result`, if the backend returned token probabilities.
+    pub fn primitive_logprob(&self) -> Option<f32> {
+        self.last_run_round()
+            .and_then(|round| round.resolved_steps.back())
+            .and_then(|step| step.logprob())
+    }
+
+    fn last_run_round(&self) -> Option<&CascadeRound> {
+        self.rounds.iter().rev().find(|round| !round.skipped)
     }
 }
 
@@ -75,7 +105,11 @@ pub(crate) async fn cascade_request(
     base_req: &mut CompletionRequest,
     step: &mut InferenceStep,
 ) -> Result<()> {
+    if step.step_config.grammar_emulation.enabled && !base_req.backend.capabilities().grammar {
+        return grammar_emulation::emulated_grammar_request(base_req, step).await;
+    }
     let res = base_req.request().await?;
+    step.logprob = mean_logprob(&res);
     if matches!(
         res.finish_reason,
         CompletionFinishReason::MatchingStoppingSequence(StoppingSequence::NoResult(_))
@@ -95,6 +129,28 @@ pub(crate) async fn cascade_request(
     Ok(())
 }
 
+/// The mean logprob of the tokens the model selected, if the backend returned token probabilities.
+fn mean_logprob(res: &CompletionResponse) -> Option<f32> {
+    let logprobs: Vec<f32> = res
+        .completion_probabilities
+        .as_ref()?
+        .iter()
+        .filter_map(|probabilities| {
+            let content = probabilities.content.as_ref()?;
+            probabilities
+                .top_probs
+                .iter()
+                .find(|top| &top.token == content)
+                .map(|top| top.prob)
+        })
+        .collect();
+    if logprobs.is_empty() {
+        None
+    } else {
+        Some(logprobs.iter().sum::<f32>() / logprobs.len() as f32)
+    }
+}
+
 impl std::fmt::Display for CascadeFlow {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f)?;
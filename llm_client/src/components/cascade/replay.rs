@@ -0,0 +1,150 @@
+use super::{step::CascadeStep, CascadeFlow};
+use llm_interface::{llms::LlmBackend, requests::completion::CompletionRequest};
+use std::sync::Arc;
+
+/// A saved flow run again, with the inference steps whose outcomes changed.
+#[derive(Clone)]
+pub struct CascadeReplay {
+    pub original: CascadeFlow,
+    pub replayed: CascadeFlow,
+    pub diffs: Vec<StepDiff>,
+}
+
+impl CascadeReplay {
+    /// Whether the replayed flow gave a different final result.
+    pub fn primitive_result_changed(&self) -> bool {
+        self.original.primitive_result() != self.replayed.primitive_result()
+    }
+}
+
+/// An inference step with a different outcome in the replay. `None` when the step had no outcome.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepDiff {
+    pub round: usize,
+    pub step: usize,
+    pub original: Option<String>,
+    pub replayed: Option<String>,
+}
+
+impl CascadeFlow {
+    /// A copy of the flow with every step unresolved and every inference step's results cleared, ready to run again.
+    /// Guidance steps and dynamic suffixes are kept as they are.
+    ///
+    /// Conditions, loops and early exits are removed, so the copy runs the steps the original ran, and skips the rounds it skipped.
+    pub fn to_unresolved(&self) -> CascadeFlow {
+        let mut flow = self.clone();
+        flow.duration = std::time::Duration::default();
+        for round in &mut flow.rounds {
+            round.condition = None;
+            round.repeat = None;
+            round.exit_when = None;
+            round.skipped_steps.clear();
+            let mut steps = std::mem::take(&mut round.resolved_steps);
+            steps.append(&mut round.unresolved_steps);
+            for step in &mut steps {
+                step.step_config_mut().condition = None;
+                if let CascadeStep::Inference(step) = step {
+                    step.llm_content = None;
+                    step.logprob = None;
+                    step.duration = None;
+                    step.config = None;
+                    *step.outcome.get_mut() = None;
+                }
+            }
+            round.unresolved_steps = steps;
+        }
+        flow
+    }
+
+    /// Runs the flow again on `backend`, with the request config recorded by its first inference step,
+    /// and compares each inference step's outcome with the original.
+    ///
+    /// The workflow that built the flow isn't run again, so the tasks, guidance steps and dynamic suffixes are the original ones,
+    /// even where the workflow would have built them from the new outcomes.
+    pub async fn replay(&self, backend: Arc<LlmBackend>) -> crate::Result<CascadeReplay> {
+        let mut base_req = CompletionRequest::new(backend);
+        let recorded_config = self.rounds.iter().find_map(|round| {
+            round.resolved_steps.iter().find_map(|step| match step {
+                CascadeStep::Inference(step) => step.config.clone(),
+                CascadeStep::Guidance(_) => None,
+            })
+        });
+        if let Some(config) = recorded_config {
+            base_req.config = config.with_ctx_size_of(&base_req.config);
+        }
+
+        let mut replayed = self.to_unresolved();
+        replayed.run_all_rounds(&mut base_req).await?;
+
+        let mut diffs = Vec::new();
+        for (round, (original, replayed)) in self.rounds.iter().zip(&replayed.rounds).enumerate() {
+            for (step, (original, replayed)) in original
+                .resolved_steps
+                .iter()
+                .zip(&replayed.resolved_steps)
+                .enumerate()
+            {
+                if matches!(original, CascadeStep::Guidance(_)) {
+                    continue;
+                }
+                let original = original.display_step_outcome().ok();
+                let replayed = replayed.display_step_outcome().ok();
+                if original != replayed {
+                    diffs.push(StepDiff {
+                        round,
+                        step,
+                        original,
+                        replayed,
+                    });
+                }
+            }
+        }
+        Ok(CascadeReplay {
+            original: self.clone(),
+            replayed,
+            diffs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::cascade::step::StepConfig;
+
+    #[test]
+    fn test_transcript_round_trip() {
+        let mut flow = CascadeFlow::new("Transcript");
+        let round = flow.new_round("Is the sky blue?");
+        round.add_guidance_step(&StepConfig::default(), "Thinking it over.");
+        round.add_inference_step(&StepConfig {
+            step_prefix: Some("The answer is:".to_string()),
+            ..StepConfig::default()
+        });
+        let round = flow.last_round().unwrap();
+        while let Some(mut step) = round.unresolved_steps.pop_front() {
+            if let CascadeStep::Inference(step) = &mut step {
+                step.llm_content = Some("true".to_string());
+                step.duration = Some(std::time::Duration::from_millis(5));
+            }
+            round.resolved_steps.push_back(step);
+        }
+
+        let json = serde_json::to_string(&flow).unwrap();
+        let saved: CascadeFlow = serde_json::from_str(&json).unwrap();
+        assert_eq!(saved.primitive_result().as_deref(), Some("true"));
+        assert_eq!(
+            saved.rounds[0].resolved_steps[1]
+                .display_step_outcome()
+                .unwrap(),
+            "The answer is: true"
+        );
+
+        let unresolved = saved.to_unresolved();
+        let round = &unresolved.rounds[0];
+        assert!(round.resolved_steps.is_empty());
+        assert_eq!(round.unresolved_steps.len(), 2);
+        assert!(round.unresolved_steps[1].display_step_outcome().is_err());
+        assert!(round.unresolved_steps[0].display_step_outcome().is_ok());
+    }
+}
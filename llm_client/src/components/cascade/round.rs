@@ -1,13 +1,39 @@
 use super::step::{CascadeStep, StepConfig};
 use llm_interface::requests::completion::CompletionRequest;
-use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, sync::Arc};
 
+/// Decides from the rounds before it whether a round runs. See [`CascadeRound::run_if`].
+pub type RoundCondition = Arc<dyn Fn(&[CascadeRound]) -> bool + Send + Sync>;
+/// Checks a round's resolved steps. See [`CascadeRound::repeat_until`] and [`CascadeRound::exit_when`].
+pub type RoundPredicate = Arc<dyn Fn(&CascadeRound) -> bool + Send + Sync>;
+
+/// Runs the round's steps again until `until` returns true, up to `max_iterations` times in total.
 #[derive(Clone)]
+pub struct RoundRepeat {
+    pub max_iterations: u8,
+    pub until: RoundPredicate,
+}
+
+/// The conditions, loops and early exits are closures, so they aren't serialized.
+/// A saved flow keeps the steps that ran, the steps whose condition was false, and `skipped` for rounds whose condition was false.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CascadeRound {
     pub task: String,
     pub unresolved_steps: VecDeque<CascadeStep>,
     pub resolved_steps: VecDeque<CascadeStep>,
+    /// Steps whose condition was false, kept so a failed iteration can restore them.
+    #[serde(default)]
+    pub skipped_steps: VecDeque<CascadeStep>,
     pub step_separator: Option<char>,
+    /// Set when the round's condition was false, and the round was not run.
+    pub skipped: bool,
+    #[serde(skip)]
+    pub condition: Option<RoundCondition>,
+    #[serde(skip)]
+    pub repeat: Option<RoundRepeat>,
+    #[serde(skip)]
+    pub exit_when: Option<RoundPredicate>,
 }
 
 impl CascadeRound {
@@ -16,10 +42,78 @@ impl CascadeRound {
             task: task.into(),
             unresolved_steps: VecDeque::new(),
             resolved_steps: VecDeque::new(),
+            skipped_steps: VecDeque::new(),
             step_separator: Some(' '),
+            skipped: false,
+            condition: None,
+            repeat: None,
+            exit_when: None,
+        }
+    }
+
+    /// Only runs the round, when the flow is run with `CascadeFlow::run_all_rounds`, if `condition` returns true for the rounds before it.
+    /// For example, a clarification round that runs when the previous round's answer was none.
+    pub fn run_if<F>(&mut self, condition: F) -> &mut Self
+    where
+        F: Fn(&[CascadeRound]) -> bool + Send + Sync + 'static,
+    {
+        self.condition = Some(Arc::new(condition));
+        self
+    }
+
+    /// Runs the round's steps again, in the same assistant message, until `until` returns true for the resolved steps,
+    /// up to `max_iterations` times in total.
+    pub fn repeat_until<F>(&mut self, max_iterations: u8, until: F) -> &mut Self
+    where
+        F: Fn(&CascadeRound) -> bool + Send + Sync + 'static,
+    {
+        self.repeat = Some(RoundRepeat {
+            max_iterations: max_iterations.max(1),
+            until: Arc::new(until),
+        });
+        self
+    }
+
+    /// Stops the round, skipping its remaining steps and iterations, once `exit` returns true after a step.
+    /// For example, when the model says it's done.
+    pub fn exit_when<F>(&mut self, exit: F) -> &mut Self
+    where
+        F: Fn(&CascadeRound) -> bool + Send + Sync + 'static,
+    {
+        self.exit_when = Some(Arc::new(exit));
+        self
+    }
+
+    /// Adds an inference step that only runs if `condition` returns true for the round's resolved steps.
+    pub fn add_inference_step_if<F>(
+        &mut self,
+        step_config: &StepConfig,
+        condition: F,
+    ) -> &mut CascadeStep
+    where
+        F: Fn(&CascadeRound) -> bool + Send + Sync + 'static,
+    {
+        let step_config = StepConfig {
+            condition: Some(Arc::new(condition)),
+            ..step_config.clone()
+        };
+        self.add_inference_step(&step_config)
+    }
+
+    /// The `llm_content` of the resolved step at `index`. `None` for guidance steps, unresolved steps, and steps that returned no result.
+    pub fn step_result(&self, index: usize) -> Option<String> {
+        match self.resolved_steps.get(index)? {
+            CascadeStep::Inference(step) => step.llm_content.clone(),
+            CascadeStep::Guidance(_) => None,
         }
     }
 
+    /// Marks the round as skipped and drops its steps.
+    pub fn skip(&mut self) {
+        self.skipped = true;
+        self.unresolved_steps.clear();
+    }
+
     pub fn add_inference_step(&mut self, step_config: &StepConfig) -> &mut CascadeStep {
         self.unresolved_steps
             .push_back(CascadeStep::new_inference_step(
@@ -91,15 +185,28 @@ impl CascadeRound {
 
     pub async fn run_all_steps(&mut self, base_req: &mut CompletionRequest) -> crate::Result<()> {
         base_req.prompt.add_user_message()?.set_content(&self.task);
-        while !self.unresolved_steps.is_empty() {
-            match self.run_next_step(base_req).await {
-                Ok(_) => {}
-                Err(e) => {
-                    let mut resolved = std::mem::take(&mut self.resolved_steps);
-                    resolved.append(&mut self.unresolved_steps);
-                    self.unresolved_steps = resolved;
+        let iteration_steps = self.repeat.as_ref().map(|_| self.unresolved_steps.clone());
+        let mut iteration = 1;
+        'iterations: loop {
+            let iteration_start = (self.resolved_steps.len(), self.skipped_steps.len());
+            while !self.unresolved_steps.is_empty() {
+                if let Err(e) = self.run_next_step(base_req).await {
+                    self.restore_iteration(iteration_start);
                     return Err(e);
                 }
+                if self.exit_when.as_ref().is_some_and(|exit| exit(self)) {
+                    self.unresolved_steps.clear();
+                    break 'iterations;
+                }
+            }
+            match (&self.repeat, &iteration_steps) {
+                (Some(repeat), Some(iteration_steps))
+                    if iteration < repeat.max_iterations && !(repeat.until)(self) =>
+                {
+                    iteration += 1;
+                    self.unresolved_steps = steps_for_iteration(iteration_steps, iteration);
+                }
+                _ => break,
             }
         }
 
@@ -111,8 +218,29 @@ impl CascadeRound {
         Ok(())
     }
 
+    /// Moves the steps resolved and skipped since `iteration_start`, the lengths of `resolved_steps` and `skipped_steps`
+    /// when the iteration started, back in front of the unresolved steps in order, so a failed iteration can run again.
+    /// The steps of earlier iterations stay resolved or skipped.
+    fn restore_iteration(&mut self, iteration_start: (usize, usize)) {
+        let (resolved_start, skipped_start) = iteration_start;
+        let mut steps = self.resolved_steps.split_off(resolved_start);
+        steps.append(&mut self.skipped_steps.split_off(skipped_start));
+        steps
+            .make_contiguous()
+            .sort_by_key(|step| step.step_counter());
+        steps.append(&mut self.unresolved_steps);
+        self.unresolved_steps = steps;
+    }
+
+    /// Runs the next unresolved step, or moves it to `skipped_steps` if its condition returns false.
     pub async fn run_next_step(&mut self, base_req: &mut CompletionRequest) -> crate::Result<()> {
         let mut current_step = self.unresolved_steps.pop_front().unwrap();
+        if let Some(condition) = &current_step.step_config().condition {
+            if !condition(self) {
+                self.skipped_steps.push_back(current_step);
+                return Ok(());
+            }
+        }
         let generation_prefix = self.generation_prefix(&current_step)?;
         match current_step
             .run_step(generation_prefix.as_deref(), base_req)
@@ -182,6 +310,19 @@ impl CascadeRound {
     }
 }
 
+/// A copy of a repeated round's steps for an iteration, numbered on from the steps of the iterations before it.
+fn steps_for_iteration(steps: &VecDeque<CascadeStep>, iteration: u8) -> VecDeque<CascadeStep> {
+    let offset = steps.len() * usize::from(iteration.saturating_sub(1));
+    steps
+        .iter()
+        .cloned()
+        .map(|mut step| {
+            step.set_step_counter(step.step_counter() + offset);
+            step
+        })
+        .collect()
+}
+
 impl std::fmt::Display for CascadeRound {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         fn print_step(
@@ -252,3 +393,139 @@ static STEP_GRADIENT: std::sync::LazyLock<Vec<&'static str>> = std::sync::LazyLo
         "\x1B[38;2;219;0;113m",
     ]
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::cascade::CascadeFlow;
+    use llm_interface::llms::{
+        api::generic_openai::{GenericApiBackend, GenericApiConfig},
+        LlmBackend,
+    };
+    use llm_utils::models::api_model::ApiLlmModel;
+
+    /// Guidance steps send nothing to API backends, so rounds of guidance steps run without a model.
+    fn request() -> CompletionRequest {
+        let backend =
+            GenericApiBackend::new(GenericApiConfig::default(), ApiLlmModel::gpt_4_o_mini())
+                .unwrap();
+        CompletionRequest::new(Arc::new(LlmBackend::GenericApi(backend)))
+    }
+
+    fn add_guidance(round: &mut CascadeRound, llm_content: &str) {
+        let step_config = StepConfig {
+            use_counter: true,
+            ..StepConfig::default()
+        };
+        round.add_guidance_step(&step_config, llm_content);
+    }
+
+    fn add_guidance_if<F>(round: &mut CascadeRound, llm_content: &str, condition: F)
+    where
+        F: Fn(&CascadeRound) -> bool + Send + Sync + 'static,
+    {
+        let step_config = StepConfig {
+            use_counter: true,
+            condition: Some(Arc::new(condition)),
+            ..StepConfig::default()
+        };
+        round.add_guidance_step(&step_config, llm_content);
+    }
+
+    fn outcomes(steps: &VecDeque<CascadeStep>) -> Vec<String> {
+        steps
+            .iter()
+            .map(|step| step.display_step_outcome().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_round_condition() {
+        let mut flow = CascadeFlow::new("Round condition");
+        add_guidance(flow.new_round("First"), "a");
+        add_guidance(
+            flow.new_round("Second")
+                .run_if(|previous| previous[0].resolved_steps.is_empty()),
+            "b",
+        );
+        add_guidance(
+            flow.new_round("Third")
+                .run_if(|previous| previous[1].skipped),
+            "c",
+        );
+        flow.run_all_rounds(&mut request()).await.unwrap();
+
+        assert!(flow.rounds[1].skipped);
+        assert!(flow.rounds[1].unresolved_steps.is_empty());
+        assert!(flow.rounds[1].resolved_steps.is_empty());
+        assert!(!flow.rounds[2].skipped);
+        assert_eq!(outcomes(&flow.rounds[2].resolved_steps), vec!["1 c"]);
+    }
+
+    #[tokio::test]
+    async fn test_step_condition() {
+        let mut round = CascadeRound::new("Step condition");
+        add_guidance(&mut round, "a");
+        add_guidance_if(&mut round, "b", |round| round.resolved_steps.len() > 1);
+        add_guidance_if(&mut round, "c", |round| round.resolved_steps.len() == 1);
+        round.run_all_steps(&mut request()).await.unwrap();
+
+        assert_eq!(outcomes(&round.resolved_steps), vec!["1 a", "3 c"]);
+        assert_eq!(outcomes(&round.skipped_steps), vec!["2 b"]);
+        assert!(round.unresolved_steps.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_exit_when() {
+        let mut round = CascadeRound::new("Exit");
+        add_guidance(&mut round, "a");
+        add_guidance(&mut round, "b");
+        add_guidance(&mut round, "c");
+        round
+            .repeat_until(3, |_| false)
+            .exit_when(|round| round.resolved_steps.len() == 2);
+        round.run_all_steps(&mut request()).await.unwrap();
+
+        assert_eq!(outcomes(&round.resolved_steps), vec!["1 a", "2 b"]);
+        assert!(round.unresolved_steps.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_repeat_numbers_iterations_on() {
+        let mut round = CascadeRound::new("Repeat");
+        add_guidance(&mut round, "a");
+        add_guidance(&mut round, "b");
+        round.repeat_until(3, |round| round.resolved_steps.len() >= 4);
+        round.run_all_steps(&mut request()).await.unwrap();
+
+        assert_eq!(
+            outcomes(&round.resolved_steps),
+            vec!["1 a", "2 b", "3 a", "4 b"]
+        );
+    }
+
+    #[test]
+    fn test_restore_iteration() {
+        let mut round = CascadeRound::new("Restore");
+        for llm_content in ["a", "b", "c", "d", "e"] {
+            add_guidance(&mut round, llm_content);
+        }
+        // The first iteration resolved 'a', and the second failed at 'e' after resolving 'b' and 'd' and skipping 'c'.
+        for skip in [false, false, true, false] {
+            let step = round.unresolved_steps.pop_front().unwrap();
+            if skip {
+                round.skipped_steps.push_back(step);
+            } else {
+                round.resolved_steps.push_back(step);
+            }
+        }
+        round.restore_iteration((1, 0));
+
+        assert_eq!(outcomes(&round.resolved_steps), vec!["1 a"]);
+        assert!(round.skipped_steps.is_empty());
+        assert_eq!(
+            outcomes(&round.unresolved_steps),
+            vec!["2 b", "3 c", "4 d", "5 e"]
+        );
+    }
+}
@@ -1,8 +1,13 @@
-use super::cascade_request;
-use llm_interface::requests::completion::CompletionRequest;
+use super::{cascade_request, grammar_emulation::GrammarEmulation, CascadeRound};
+use llm_interface::requests::{completion::CompletionRequest, req_components::RequestConfig};
 use llm_utils::grammar::Grammar;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
-#[derive(Clone)]
+/// Decides from the round so far whether a step runs. See [`StepConfig::condition`].
+pub type StepCondition = Arc<dyn Fn(&CascadeRound) -> bool + Send + Sync>;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum CascadeStep {
     Inference(InferenceStep),
     Guidance(GuidanceStep),
@@ -12,6 +17,9 @@ impl CascadeStep {
     pub fn new_inference_step(step_config: StepConfig, step_counter: usize) -> Self {
         CascadeStep::Inference(InferenceStep {
             llm_content: None,
+            logprob: None,
+            duration: None,
+            config: None,
             dynamic_suffix: None,
             outcome: std::cell::RefCell::new(None),
             step_config,
@@ -31,6 +39,35 @@ impl CascadeStep {
         })
     }
 
+    pub fn step_config(&self) -> &StepConfig {
+        match self {
+            Self::Inference(step) => &step.step_config,
+            Self::Guidance(step) => &step.step_config,
+        }
+    }
+
+    pub fn step_config_mut(&mut self) -> &mut StepConfig {
+        match self {
+            Self::Inference(step) => &mut step.step_config,
+            Self::Guidance(step) => &mut step.step_config,
+        }
+    }
+
+    pub fn step_counter(&self) -> usize {
+        match self {
+            Self::Inference(step) => step.step_counter,
+            Self::Guidance(step) => step.step_counter,
+        }
+    }
+
+    /// Sets the number shown before the step's prefix when `StepConfig::use_counter` is set.
+    pub fn set_step_counter(&mut self, step_counter: usize) {
+        match self {
+            Self::Inference(step) => step.step_counter = step_counter,
+            Self::Guidance(step) => step.step_counter = step_counter,
+        }
+    }
+
     pub fn display_step_prefix(&self) -> Option<String> {
         match self {
             Self::Inference(step) => step.step_config.display_prefix(step.step_counter),
@@ -54,8 +91,12 @@ impl CascadeStep {
         generation_prefix: Option<&str>,
         base_req: &mut CompletionRequest,
     ) -> crate::Result<()> {
+        // API backends with prompt caching, like Anthropic, cache on the next real request instead.
+        if !base_req.backend.is_local() || !base_req.backend.capabilities().prompt_cache {
+            return Ok(());
+        }
         if let Some(generation_prefix) = generation_prefix {
-            base_req.prompt.set_generation_prefix(generation_prefix);
+            base_req.set_generation_prefix(generation_prefix);
         }
         base_req
             .backend
@@ -85,11 +126,24 @@ impl CascadeStep {
             Self::Guidance(_) => panic!("GuidanceStep does not have primitive_result."),
         }
     }
+
+    pub fn logprob(&self) -> Option<f32> {
+        match self {
+            Self::Inference(step) => step.logprob,
+            Self::Guidance(_) => None,
+        }
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct InferenceStep {
     pub llm_content: Option<String>, // raw, unformatted result from llm.
+    /// The mean logprob of the response's tokens, when the backend returns token probabilities.
+    pub logprob: Option<f32>,
+    /// How long the step's request took, including any grammar emulation retries.
+    pub duration: Option<std::time::Duration>,
+    /// The request config the step was sent with.
+    pub config: Option<RequestConfig>,
     pub dynamic_suffix: Option<String>, // suffix to be added to the result.
     pub outcome: std::cell::RefCell<Option<String>>,
     pub step_config: StepConfig,
@@ -119,16 +173,25 @@ impl InferenceStep {
         self.step_config
             .grammar
             .set_stop_word_done(&self.step_config.stop_word_done);
-        base_req.grammar_string = Some(self.step_config.grammar.grammar_string());
+        // Backends without native grammars are validated, and re-prompted, in cascade_request.
+        base_req.grammar_string = if base_req.backend.capabilities().grammar {
+            Some(self.step_config.grammar.grammar_string())
+        } else {
+            None
+        };
 
         // Request prompt
         if let Some(generation_prefix) = generation_prefix {
-            base_req.prompt.set_generation_prefix(generation_prefix);
+            base_req.set_generation_prefix(generation_prefix);
         } else {
-            base_req.prompt.clear_generation_prefix();
+            base_req.clear_generation_prefix();
         }
         base_req.config.cache_prompt = self.step_config.cache_prompt;
-        cascade_request(base_req, self).await
+        let start = std::time::Instant::now();
+        let res = cascade_request(base_req, self).await;
+        self.duration = Some(start.elapsed());
+        self.config = Some(base_req.config.clone());
+        res
     }
 
     // step_counter + step_prefix + prefix_delimiter + (llm_content | stop_word_no_result) + dynamic_suffix
@@ -159,7 +222,7 @@ impl InferenceStep {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GuidanceStep {
     pub llm_content: String,
     pub step_config: StepConfig,
@@ -175,14 +238,21 @@ impl GuidanceStep {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct StepConfig {
     pub step_prefix: Option<String>,
     pub stop_word_done: String,
     pub stop_word_no_result: Option<String>,
     pub use_counter: bool,
     pub cache_prompt: bool,
+    /// Serialized as its GBNF, and deserialized as a custom grammar.
+    #[serde(with = "grammar_gbnf")]
     pub grammar: Grammar,
+    pub grammar_emulation: GrammarEmulation,
+    /// When set, the step only runs if this returns true. It's checked with the round's resolved steps just before the step would run,
+    /// and a skipped step is dropped from the round. Not serialized.
+    #[serde(skip)]
+    pub condition: Option<StepCondition>,
 }
 
 impl Default for StepConfig {
@@ -194,6 +264,8 @@ impl Default for StepConfig {
             use_counter: false,
             cache_prompt: true,
             grammar: Grammar::default(),
+            grammar_emulation: GrammarEmulation::default(),
+            condition: None,
         }
     }
 }
@@ -208,3 +280,22 @@ impl StepConfig {
         }
     }
 }
+
+mod grammar_gbnf {
+    use llm_utils::grammar::Grammar;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        grammar: &Grammar,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&grammar.grammar_string())
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Grammar, D::Error> {
+        let gbnf = String::deserialize(deserializer)?;
+        Ok(Grammar::custom().custom_grammar(gbnf).wrap())
+    }
+}
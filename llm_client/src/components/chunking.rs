@@ -0,0 +1,292 @@
+use super::tokens::{available_prompt_tokens, TokenTextSplitter};
+use llm_interface::requests::completion::CompletionRequest;
+
+/// How supporting material is cut into chunks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChunkBy {
+    /// Fixed size windows of tokens. Fast, but may cut sentences and URLs in half.
+    Tokens,
+    /// Whole sentences and lines, packed up to the chunk size.
+    #[default]
+    Sentences,
+    /// Block level HTML elements like `<p>`, `<div>`, `<li>` and `<tr>`, packed up to the chunk size.
+    HtmlBlocks,
+}
+
+/// Splits long supporting material so a workflow can be run once per chunk and the results merged.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkingConfig {
+    pub chunk_by: ChunkBy,
+    /// The maximum tokens per chunk. When `None`, a quarter of the backend's available prompt tokens is used,
+    /// leaving room for the instructions and the workflow's own rounds.
+    pub max_chunk_tokens: Option<u64>,
+    /// Tokens from the end of each chunk repeated at the start of the next, so items on a boundary aren't lost.
+    ///
+    /// Defaults to 64.
+    pub overlap_tokens: u64,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            chunk_by: ChunkBy::default(),
+            max_chunk_tokens: None,
+            overlap_tokens: 64,
+        }
+    }
+}
+
+impl ChunkingConfig {
+    pub fn new(chunk_by: ChunkBy) -> Self {
+        Self {
+            chunk_by,
+            ..Default::default()
+        }
+    }
+
+    pub fn max_chunk_tokens(mut self, max_chunk_tokens: u64) -> Self {
+        self.max_chunk_tokens = Some(max_chunk_tokens);
+        self
+    }
+
+    pub fn overlap_tokens(mut self, overlap_tokens: u64) -> Self {
+        self.overlap_tokens = overlap_tokens;
+        self
+    }
+
+    /// The chunk size for a request, from the request's backend and config.
+    pub fn chunk_tokens(&self, req: &CompletionRequest) -> u64 {
+        self.max_chunk_tokens.unwrap_or_else(|| {
+            available_prompt_tokens(&req.config, req.config.inference_ctx_size() / 4) / 4
+        })
+    }
+
+    /// Returns the text as chunks, sized for the request. Text that fits in a single chunk is returned as is.
+    pub fn chunk(&self, req: &CompletionRequest, text: &str) -> crate::Result<Vec<String>> {
+        let max_tokens = self.chunk_tokens(req);
+        if max_tokens == 0 {
+            crate::bail!(
+                "Chunk size is 0 tokens. The backend's context is too small for chunking."
+            );
+        }
+        if self.overlap_tokens >= max_tokens {
+            crate::bail!(
+                "overlap_tokens ({}) must be smaller than the chunk size ({max_tokens})",
+                self.overlap_tokens
+            );
+        }
+        let tokenizer = req.backend.tokenizer();
+        if tokenizer.count_tokens(text) as u64 <= max_tokens {
+            return Ok(vec![text.to_owned()]);
+        }
+
+        if self.chunk_by == ChunkBy::Tokens {
+            let tokens = tokenizer.tokenize(text);
+            let stride = (max_tokens - self.overlap_tokens) as usize;
+            let mut chunks = Vec::new();
+            let mut start = 0;
+            while start < tokens.len() {
+                let end = (start + max_tokens as usize).min(tokens.len());
+                chunks.push(tokenizer.detokenize_many(&tokens[start..end])?);
+                if end == tokens.len() {
+                    break;
+                }
+                start += stride;
+            }
+            return Ok(chunks);
+        }
+
+        let segments = match self.chunk_by {
+            ChunkBy::HtmlBlocks => split_html_blocks(text),
+            _ => split_sentences(text),
+        };
+        // Segments bigger than a chunk are split further on their own. Whitespace is kept, as pieces are joined without a separator.
+        let splitter = TokenTextSplitter::new(tokenizer.clone(), max_tokens);
+        let mut pieces: Vec<(String, u64)> = Vec::new();
+        for segment in segments {
+            let count = tokenizer.count_tokens(&segment) as u64;
+            if count > max_tokens {
+                for piece in splitter.split_keep_whitespace(&segment)? {
+                    let count = tokenizer.count_tokens(&piece) as u64;
+                    pieces.push((piece, count));
+                }
+            } else {
+                pieces.push((segment, count));
+            }
+        }
+
+        let mut chunks = Vec::new();
+        let mut current: Vec<&(String, u64)> = Vec::new();
+        let mut current_tokens = 0;
+        for piece in &pieces {
+            if current_tokens + piece.1 > max_tokens && !current.is_empty() {
+                chunks.push(join_pieces(&current));
+                // Carry trailing pieces into the next chunk as overlap.
+                let mut overlap = Vec::new();
+                let mut overlap_tokens = 0;
+                for previous in current.iter().rev() {
+                    if overlap_tokens + previous.1 > self.overlap_tokens
+                        || overlap_tokens + previous.1 + piece.1 > max_tokens
+                    {
+                        break;
+                    }
+                    overlap_tokens += previous.1;
+                    overlap.insert(0, *previous);
+                }
+                current = overlap;
+                current_tokens = overlap_tokens;
+            }
+            current.push(piece);
+            current_tokens += piece.1;
+        }
+        if !current.is_empty() {
+            chunks.push(join_pieces(&current));
+        }
+        Ok(chunks)
+    }
+}
+
+fn join_pieces(pieces: &[&(String, u64)]) -> String {
+    pieces
+        .iter()
+        .map(|(piece, _)| piece.as_str())
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+/// Splits after sentence ending punctuation followed by whitespace, and after newlines. Whitespace is kept, so joining the segments returns the text.
+fn split_sentences(text: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next_is_whitespace = chars.peek().map(|(_, n)| n.is_whitespace()) == Some(true);
+        if c == '\n' || (matches!(c, '.' | '!' | '?') && next_is_whitespace) {
+            let end = i + c.len_utf8();
+            segments.push(text[start..end].to_owned());
+            start = end;
+        }
+    }
+    if start < text.len() {
+        segments.push(text[start..].to_owned());
+    }
+    segments
+}
+
+const HTML_BLOCK_END_TAGS: [&str; 17] = [
+    "</p>",
+    "</div>",
+    "</li>",
+    "</tr>",
+    "</table>",
+    "</ul>",
+    "</ol>",
+    "</section>",
+    "</article>",
+    "</blockquote>",
+    "</pre>",
+    "</h1>",
+    "</h2>",
+    "</h3>",
+    "</h4>",
+    "</h5>",
+    "</h6>",
+];
+
+/// Splits after closing tags of block level elements and after `<br>`. Markup is kept, so joining the segments returns the text.
+fn split_html_blocks(text: &str) -> Vec<String> {
+    // ASCII lowercasing keeps byte offsets the same as the original text.
+    let lower = text.to_ascii_lowercase();
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < lower.len() {
+        if lower.as_bytes()[i] != b'<' {
+            i += 1;
+            continue;
+        }
+        let rest = &lower[i..];
+        let tag_len = HTML_BLOCK_END_TAGS
+            .iter()
+            .find(|tag| rest.starts_with(*tag))
+            .map(|tag| tag.len())
+            .or_else(|| {
+                ["<br>", "<br/>", "<br />"]
+                    .iter()
+                    .find(|tag| rest.starts_with(*tag))
+                    .map(|tag| tag.len())
+            });
+        match tag_len {
+            Some(tag_len) => {
+                segments.push(text[start..i + tag_len].to_owned());
+                start = i + tag_len;
+                i = start;
+            }
+            None => i += 1,
+        }
+    }
+    if start < text.len() {
+        segments.push(text[start..].to_owned());
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llm_interface::llms::{
+        api::generic_openai::{GenericApiBackend, GenericApiConfig},
+        LlmBackend,
+    };
+    use llm_utils::models::api_model::ApiLlmModel;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_split_sentences() {
+        let text = "First sentence. Second one!\nThird line";
+        let segments = split_sentences(text);
+        assert_eq!(
+            segments,
+            vec!["First sentence.", " Second one!", "\n", "Third line"]
+        );
+        assert_eq!(segments.concat(), text);
+    }
+
+    #[test]
+    fn test_split_html_blocks() {
+        let text = "<div><p>One <a href=\"https://a.com\">a</a></p><p>Two</p>tail<br>end</div>";
+        let segments = split_html_blocks(text);
+        assert_eq!(
+            segments,
+            vec![
+                "<div><p>One <a href=\"https://a.com\">a</a></p>",
+                "<p>Two</p>",
+                "tail<br>",
+                "end</div>",
+            ]
+        );
+        assert_eq!(segments.concat(), text);
+    }
+
+    #[test]
+    fn test_chunk_keeps_whitespace() {
+        let backend =
+            GenericApiBackend::new(GenericApiConfig::default(), ApiLlmModel::gpt_4_o_mini())
+                .unwrap();
+        let req = CompletionRequest::new(Arc::new(LlmBackend::GenericApi(backend)));
+        // A single sentence, so the splitter cuts it on spaces.
+        let text = "See https://docs.rs for docs and more words ".repeat(40);
+        let chunking = ChunkingConfig::new(ChunkBy::Sentences)
+            .max_chunk_tokens(16)
+            .overlap_tokens(0);
+        let chunks = chunking.chunk(&req, &text).unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| !chunk.contains("docs.rsfor")));
+        let words: Vec<&str> = chunks
+            .iter()
+            .flat_map(|chunk| chunk.split_whitespace())
+            .collect();
+        assert_eq!(words, text.split_whitespace().collect::<Vec<_>>());
+    }
+}
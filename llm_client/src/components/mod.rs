@@ -1,5 +1,7 @@
 // pub mod base_request;
 pub mod cascade;
+pub mod chunking;
 pub mod instruct_prompt;
+pub mod tokens;
 
 pub use instruct_prompt::InstructPromptTrait;
@@ -0,0 +1,135 @@
+use llm_interface::requests::req_components::RequestConfig;
+use llm_utils::tokenizer::LlmTokenizer;
+use std::sync::Arc;
+
+/// Whether a prompt of `prompt_tokens` leaves room for `response_tokens` with a request's config.
+/// Uses the same limits as [`RequestConfig::set_max_tokens_for_request`], including the config's safety tokens.
+pub fn fits_in_context(config: &RequestConfig, prompt_tokens: u64, response_tokens: u64) -> bool {
+    let mut config = config.clone();
+    config.requested_response_tokens = Some(response_tokens);
+    config.set_max_tokens_for_request(prompt_tokens).is_ok()
+        && config.actual_request_tokens() >= Some(response_tokens)
+}
+
+/// The largest prompt, in tokens, that still leaves room for `response_tokens` with a request's config. Returns 0 if nothing fits.
+/// Useful for sizing supporting material before building a request.
+pub fn available_prompt_tokens(config: &RequestConfig, response_tokens: u64) -> u64 {
+    let (mut low, mut high) = (0, config.model_ctx_size());
+    if !fits_in_context(config, low, response_tokens) {
+        return 0;
+    }
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        if fits_in_context(config, mid, response_tokens) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    low
+}
+
+/// Cuts text to at most `max_tokens` tokens. Text that already fits is returned unchanged.
+pub fn truncate_to_tokens(
+    tokenizer: &LlmTokenizer,
+    text: &str,
+    max_tokens: u64,
+) -> crate::Result<String> {
+    let tokens = tokenizer.tokenize(text);
+    if tokens.len() as u64 <= max_tokens {
+        return Ok(text.to_owned());
+    }
+    Ok(tokenizer.detokenize_many(&tokens[..max_tokens as usize])?)
+}
+
+/// Splits text into chunks of at most `max_tokens` tokens.
+///
+/// Prefers to split between paragraphs, then lines, then sentences, then words. Text with no usable boundary is split on token boundaries.
+#[derive(Clone)]
+pub struct TokenTextSplitter {
+    pub tokenizer: Arc<LlmTokenizer>,
+    pub max_tokens: u64,
+}
+
+const SEPARATORS: [&str; 4] = ["\n\n", "\n", ". ", " "];
+
+impl TokenTextSplitter {
+    pub fn new(tokenizer: Arc<LlmTokenizer>, max_tokens: u64) -> Self {
+        Self {
+            tokenizer,
+            max_tokens,
+        }
+    }
+
+    /// Splits the text and trims each chunk.
+    pub fn split(&self, text: &str) -> crate::Result<Vec<String>> {
+        self.split_text(text, true)
+    }
+
+    /// Splits the text without trimming, so joining the chunks returns the text.
+    pub fn split_keep_whitespace(&self, text: &str) -> crate::Result<Vec<String>> {
+        self.split_text(text, false)
+    }
+
+    fn split_text(&self, text: &str, trim: bool) -> crate::Result<Vec<String>> {
+        if self.max_tokens == 0 {
+            crate::bail!("TokenTextSplitter max_tokens must be greater than 0");
+        }
+        self.split_at_level(text, 0, trim)
+    }
+
+    fn count(&self, text: &str) -> u64 {
+        self.tokenizer.count_tokens(text) as u64
+    }
+
+    fn split_at_level(&self, text: &str, level: usize, trim: bool) -> crate::Result<Vec<String>> {
+        if self.count(text) <= self.max_tokens {
+            return Ok(non_empty(text, trim).into_iter().collect());
+        }
+        let Some(separator) = SEPARATORS.get(level) else {
+            return self.split_tokens(text, trim);
+        };
+
+        let mut chunks = Vec::new();
+        let mut current = String::new();
+        let mut current_tokens = 0;
+        for piece in text.split_inclusive(separator) {
+            let piece_tokens = self.count(piece);
+            if piece_tokens > self.max_tokens {
+                chunks.extend(non_empty(&current, trim));
+                current.clear();
+                current_tokens = 0;
+                chunks.extend(self.split_at_level(piece, level + 1, trim)?);
+                continue;
+            }
+            // Pieces end on a separator, so their counts add up to the count of the joined text.
+            if current_tokens + piece_tokens > self.max_tokens {
+                chunks.extend(non_empty(&current, trim));
+                current.clear();
+                current_tokens = 0;
+            }
+            current.push_str(piece);
+            current_tokens += piece_tokens;
+        }
+        chunks.extend(non_empty(&current, trim));
+        Ok(chunks)
+    }
+
+    fn split_tokens(&self, text: &str, trim: bool) -> crate::Result<Vec<String>> {
+        let tokens = self.tokenizer.tokenize(text);
+        let mut chunks = Vec::new();
+        for window in tokens.chunks(self.max_tokens as usize) {
+            chunks.extend(non_empty(&self.tokenizer.detokenize_many(window)?, trim));
+        }
+        Ok(chunks)
+    }
+}
+
+fn non_empty(text: &str, trim: bool) -> Option<String> {
+    let text = if trim { text.trim() } else { text };
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_owned())
+    }
+}
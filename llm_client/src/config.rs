@@ -0,0 +1,457 @@
+use crate::LlmClient;
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+use llm_devices::devices::CudaConfig;
+#[cfg(any(feature = "llama_cpp_backend", feature = "mistral_rs_backend"))]
+use llm_interface::llms::local::{
+    chat_template::{BuiltinChatTemplate, ChatTemplateOverride},
+    LlmLocalTrait,
+};
+use llm_interface::{
+    llms::api::config::LlmApiConfigTrait, requests::req_components::RequestConfig,
+};
+use llm_utils::models::api_model::{
+    anthropic::AnthropicModelTrait, openai::OpenAiModelTrait, perplexity::PerplexityModelTrait,
+};
+#[cfg(any(feature = "llama_cpp_backend", feature = "mistral_rs_backend"))]
+use llm_utils::models::local_model::{gguf::preset::LlmPreset, GgufLoaderTrait, GgufPresetTrait};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Prefix for environment variables that override values loaded from a config file.
+pub const ENV_PREFIX: &str = "LLM_CLIENT_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    LlamaCpp,
+    MistralRs,
+    #[serde(alias = "openai")]
+    OpenAi,
+    Anthropic,
+    Perplexity,
+}
+
+impl std::str::FromStr for BackendKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "llama_cpp" => Ok(Self::LlamaCpp),
+            "mistral_rs" => Ok(Self::MistralRs),
+            "open_ai" | "openai" => Ok(Self::OpenAi),
+            "anthropic" => Ok(Self::Anthropic),
+            "perplexity" => Ok(Self::Perplexity),
+            _ => crate::bail!("Unknown backend kind: {s}"),
+        }
+    }
+}
+
+/// Settings for local backends. Mirrors the builder methods of [`LlmLocalTrait`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceSettings {
+    pub use_gpu: Option<bool>,
+    pub cuda_devices: Option<Vec<u32>>,
+    pub threads: Option<i16>,
+    pub threads_batch: Option<i16>,
+    pub batch_size: Option<u64>,
+    pub inference_ctx_size: Option<u64>,
+    pub use_ram_gb: Option<f32>,
+    pub use_ram_percentage: Option<f32>,
+    pub error_on_config_issue: Option<bool>,
+}
+
+/// Settings for API backends, and for the llama.cpp server. Mirrors the builder methods of [`LlmApiConfigTrait`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiSettings {
+    pub host: Option<String>,
+    pub port: Option<String>,
+    /// The name of the environment variable holding the API key. The key itself is never read from the config file.
+    pub api_key_env_var: Option<String>,
+}
+
+/// Default values applied to the [`RequestConfig`] of every request created by the client.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RequestDefaults {
+    pub max_tokens: Option<u64>,
+    pub safety_tokens: Option<u64>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<u32>,
+    pub frequency_penalty: Option<f32>,
+    pub presence_penalty: Option<f32>,
+    pub retry_after_fail_n_times: Option<u8>,
+    pub increase_limit_on_fail: Option<bool>,
+    pub cache_prompt: Option<bool>,
+}
+
+impl RequestDefaults {
+    pub fn apply(&self, config: &mut RequestConfig) {
+        if let Some(max_tokens) = self.max_tokens {
+            config.requested_response_tokens = Some(max_tokens);
+        }
+        if let Some(safety_tokens) = self.safety_tokens {
+            config.safety_tokens = safety_tokens;
+        }
+        if let Some(temperature) = self.temperature {
+            config.temperature = temperature;
+        }
+        if self.top_p.is_some() {
+            config.top_p = self.top_p;
+        }
+        if self.top_k.is_some() {
+            config.top_k = self.top_k;
+        }
+        if self.frequency_penalty.is_some() {
+            config.frequency_penalty = self.frequency_penalty;
+        }
+        if let Some(presence_penalty) = self.presence_penalty {
+            config.presence_penalty = presence_penalty;
+        }
+        if let Some(retry_after_fail_n_times) = self.retry_after_fail_n_times {
+            config.retry_after_fail_n_times = retry_after_fail_n_times;
+        }
+        if let Some(increase_limit_on_fail) = self.increase_limit_on_fail {
+            config.increase_limit_on_fail = increase_limit_on_fail;
+        }
+        if let Some(cache_prompt) = self.cache_prompt {
+            config.cache_prompt = cache_prompt;
+        }
+    }
+}
+
+/// A serde-deserializable description of an [`LlmClient`].
+///
+/// Loaded from TOML, YAML or JSON with [`LlmClientConfig::from_file`], after which any `LLM_CLIENT_*`
+/// environment variables are applied on top. See [`LlmClientConfig::apply_env_overrides`] for the list.
+///
+/// ```toml
+/// backend = "llama_cpp"
+/// preset = "meta-llama/Meta-Llama-3.1-8B-Instruct"
+///
+/// [device]
+/// use_gpu = true
+/// inference_ctx_size = 8192
+///
+/// [request]
+/// temperature = 0.5
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LlmClientConfig {
+    pub backend: BackendKind,
+    /// The model id for API backends, e.g. `gpt-4o-mini`.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// The model id of a preset for local backends, e.g. `meta-llama/Meta-Llama-3.1-8B-Instruct`.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// A Hugging Face url to a GGUF quant file for local backends.
+    #[serde(default)]
+    pub hf_quant_file_url: Option<String>,
+    /// A path to a local GGUF quant file for local backends.
+    #[serde(default)]
+    pub local_quant_file_path: Option<String>,
+    /// Overrides the chat template of local backends. A built-in template name (`chatml`, `llama3`, `mistral`, `gemma`, `phi3`)
+    /// or a path to a Jinja template file.
+    #[serde(default)]
+    pub chat_template: Option<String>,
+    #[serde(default)]
+    pub device: DeviceSettings,
+    #[serde(default)]
+    pub api: ApiSettings,
+    #[serde(default)]
+    pub request: RequestDefaults,
+}
+
+impl LlmClientConfig {
+    /// Loads a config file. The format is picked from the extension: `.toml`, `.yaml`/`.yml` or `.json`.
+    /// `LLM_CLIENT_*` environment variables are applied after the file is parsed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| crate::anyhow!("Failed to read config file {}: {e}", path.display()))?;
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        let mut config = match extension.as_deref() {
+            Some("toml") => Self::from_toml_str(&contents)?,
+            Some("yaml") | Some("yml") => Self::from_yaml_str(&contents)?,
+            Some("json") => Self::from_json_str(&contents)?,
+            _ => crate::bail!(
+                "Unsupported config file extension for {}. Expected .toml, .yaml, .yml or .json",
+                path.display()
+            ),
+        };
+        config.apply_env_overrides()?;
+        Ok(config)
+    }
+
+    pub fn from_toml_str(contents: &str) -> crate::Result<Self> {
+        toml::from_str(contents).map_err(|e| crate::anyhow!("Failed to parse TOML config: {e}"))
+    }
+
+    pub fn from_yaml_str(contents: &str) -> crate::Result<Self> {
+        serde_yaml::from_str(contents)
+            .map_err(|e| crate::anyhow!("Failed to parse YAML config: {e}"))
+    }
+
+    pub fn from_json_str(contents: &str) -> crate::Result<Self> {
+        serde_json::from_str(contents)
+            .map_err(|e| crate::anyhow!("Failed to parse JSON config: {e}"))
+    }
+
+    /// Overrides values with the following environment variables, if they are set:
+    ///
+    /// `LLM_CLIENT_BACKEND`, `LLM_CLIENT_MODEL`, `LLM_CLIENT_PRESET`, `LLM_CLIENT_HF_QUANT_FILE_URL`,
+    /// `LLM_CLIENT_LOCAL_QUANT_FILE_PATH`, `LLM_CLIENT_USE_GPU`, `LLM_CLIENT_INFERENCE_CTX_SIZE`,
+    /// `LLM_CLIENT_API_HOST`, `LLM_CLIENT_API_PORT`, `LLM_CLIENT_API_KEY_ENV_VAR`,
+    /// `LLM_CLIENT_MAX_TOKENS`, `LLM_CLIENT_TEMPERATURE` and `LLM_CLIENT_TOP_P`.
+    pub fn apply_env_overrides(&mut self) -> crate::Result<()> {
+        if let Some(backend) = env_var("BACKEND") {
+            self.backend = backend.parse()?;
+        }
+        override_string(&mut self.model, "MODEL");
+        override_string(&mut self.preset, "PRESET");
+        override_string(&mut self.hf_quant_file_url, "HF_QUANT_FILE_URL");
+        override_string(&mut self.local_quant_file_path, "LOCAL_QUANT_FILE_PATH");
+        override_parsed(&mut self.device.use_gpu, "USE_GPU")?;
+        override_parsed(&mut self.device.inference_ctx_size, "INFERENCE_CTX_SIZE")?;
+        override_string(&mut self.api.host, "API_HOST");
+        override_string(&mut self.api.port, "API_PORT");
+        override_string(&mut self.api.api_key_env_var, "API_KEY_ENV_VAR");
+        override_parsed(&mut self.request.max_tokens, "MAX_TOKENS")?;
+        override_parsed(&mut self.request.temperature, "TEMPERATURE")?;
+        override_parsed(&mut self.request.top_p, "TOP_P")?;
+        Ok(())
+    }
+
+    /// Builds the backend described by the config and wraps it in an [`LlmClient`].
+    pub async fn init(self) -> crate::Result<LlmClient> {
+        let mut client = match self.backend {
+            BackendKind::LlamaCpp => self.init_llama_cpp().await?,
+            BackendKind::MistralRs => self.init_mistral_rs().await?,
+            BackendKind::OpenAi => {
+                let mut builder = self.apply_api(LlmClient::openai());
+                if let Some(model) = &self.model {
+                    builder = builder.model_id_str(model);
+                }
+                builder.init()?
+            }
+            BackendKind::Anthropic => {
+                let mut builder = self.apply_api(LlmClient::anthropic());
+                if let Some(model) = &self.model {
+                    builder = builder.model_id_str(model);
+                }
+                builder.init()?
+            }
+            BackendKind::Perplexity => {
+                let mut builder = self.apply_api(LlmClient::perplexity());
+                if let Some(model) = &self.model {
+                    builder = builder.model_id_str(model);
+                }
+                builder.init()?
+            }
+        };
+        client.request_defaults = self.request;
+        Ok(client)
+    }
+
+    #[cfg(feature = "llama_cpp_backend")]
+    async fn init_llama_cpp(&self) -> crate::Result<LlmClient> {
+        let builder = self.apply_api(LlmClient::llama_cpp());
+        let builder = self.apply_local(builder)?;
+        builder.init().await
+    }
+
+    #[cfg(not(feature = "llama_cpp_backend"))]
+    async fn init_llama_cpp(&self) -> crate::Result<LlmClient> {
+        crate::bail!("The llama_cpp backend requires the `llama_cpp_backend` feature")
+    }
+
+    #[cfg(feature = "mistral_rs_backend")]
+    async fn init_mistral_rs(&self) -> crate::Result<LlmClient> {
+        let builder = self.apply_local(LlmClient::mistral_rs())?;
+        builder.init().await
+    }
+
+    #[cfg(not(feature = "mistral_rs_backend"))]
+    async fn init_mistral_rs(&self) -> crate::Result<LlmClient> {
+        crate::bail!("The mistral_rs backend requires the `mistral_rs_backend` feature")
+    }
+
+    fn apply_api<B: LlmApiConfigTrait>(&self, mut builder: B) -> B {
+        if let Some(host) = &self.api.host {
+            builder = builder.with_api_host(host);
+        }
+        if let Some(port) = &self.api.port {
+            builder = builder.with_api_port(port);
+        }
+        if let Some(api_key_env_var) = &self.api.api_key_env_var {
+            builder = builder.with_api_key_env_var(api_key_env_var);
+        }
+        builder
+    }
+
+    #[cfg(any(feature = "llama_cpp_backend", feature = "mistral_rs_backend"))]
+    fn apply_local<B: LlmLocalTrait + GgufLoaderTrait + GgufPresetTrait>(
+        &self,
+        mut builder: B,
+    ) -> crate::Result<B> {
+        if let Some(preset) = &self.preset {
+            builder.preset_loader().llm_preset = LlmPreset::from_model_id(preset);
+        }
+        if let Some(hf_quant_file_url) = &self.hf_quant_file_url {
+            builder = builder.hf_quant_file_url(hf_quant_file_url);
+        }
+        if let Some(local_quant_file_path) = &self.local_quant_file_path {
+            builder = builder.local_quant_file_path(local_quant_file_path);
+        }
+        if let Some(chat_template) = &self.chat_template {
+            builder = match parse_chat_template(chat_template)? {
+                ChatTemplateOverride::Builtin(builtin) => builder.builtin_chat_template(builtin),
+                ChatTemplateOverride::File(path) => builder.chat_template_file(path),
+                ChatTemplateOverride::Jinja(template) => builder.chat_template(template),
+            };
+        }
+        let device = &self.device;
+        if let Some(use_gpu) = device.use_gpu {
+            builder = builder.use_gpu(use_gpu);
+        }
+        #[cfg(any(target_os = "linux", target_os = "windows"))]
+        if let Some(cuda_devices) = &device.cuda_devices {
+            builder = builder.cuda_config(CudaConfig::new_from_cuda_devices(cuda_devices.clone()));
+        }
+        if let Some(threads) = device.threads {
+            builder = builder.threads(threads);
+        }
+        if let Some(threads_batch) = device.threads_batch {
+            builder = builder.threads_batch(threads_batch);
+        }
+        if let Some(batch_size) = device.batch_size {
+            builder = builder.batch_size(batch_size);
+        }
+        if let Some(inference_ctx_size) = device.inference_ctx_size {
+            builder = builder.inference_ctx_size(inference_ctx_size);
+        }
+        if let Some(use_ram_gb) = device.use_ram_gb {
+            builder = builder.use_ram_gb(use_ram_gb);
+        }
+        if let Some(use_ram_percentage) = device.use_ram_percentage {
+            builder = builder.use_ram_percentage(use_ram_percentage);
+        }
+        if let Some(error_on_config_issue) = device.error_on_config_issue {
+            builder = builder.error_on_config_issue(error_on_config_issue);
+        }
+        Ok(builder)
+    }
+}
+
+/// A built-in template name, or else a path to an existing template file.
+#[cfg(any(feature = "llama_cpp_backend", feature = "mistral_rs_backend"))]
+fn parse_chat_template(chat_template: &str) -> crate::Result<ChatTemplateOverride> {
+    match chat_template.parse::<BuiltinChatTemplate>() {
+        Ok(builtin) => Ok(ChatTemplateOverride::Builtin(builtin)),
+        Err(_) if Path::new(chat_template).is_file() => {
+            Ok(ChatTemplateOverride::File(chat_template.into()))
+        }
+        Err(e) => crate::bail!(
+            "{e}. Use chatml, llama3, mistral, gemma, phi3, or a path to a Jinja template file"
+        ),
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(format!("{ENV_PREFIX}{name}"))
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+fn override_string(field: &mut Option<String>, name: &str) {
+    if let Some(value) = env_var(name) {
+        *field = Some(value);
+    }
+}
+
+fn override_parsed<T: std::str::FromStr>(field: &mut Option<T>, name: &str) -> crate::Result<()>
+where
+    T::Err: std::fmt::Display,
+{
+    if let Some(value) = env_var(name) {
+        *field = Some(
+            value
+                .trim()
+                .parse()
+                .map_err(|e| crate::anyhow!("Invalid value for {ENV_PREFIX}{name}: {e}"))?,
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml() {
+        let config = LlmClientConfig::from_toml_str(
+            r#"
+            backend = "openai"
+            model = "gpt-4o-mini"
+
+            [api]
+            api_key_env_var = "MY_OPENAI_KEY"
+
+            [request]
+            temperature = 0.3
+            max_tokens = 256
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.backend, BackendKind::OpenAi);
+        assert_eq!(config.model.as_deref(), Some("gpt-4o-mini"));
+        assert_eq!(config.api.api_key_env_var.as_deref(), Some("MY_OPENAI_KEY"));
+        assert_eq!(config.request.temperature, Some(0.3));
+        assert_eq!(config.request.max_tokens, Some(256));
+    }
+
+    #[test]
+    fn test_parse_yaml() {
+        let config = LlmClientConfig::from_yaml_str(
+            "backend: llama_cpp\npreset: meta-llama/Meta-Llama-3.1-8B-Instruct\ndevice:\n  use_gpu: false\n  inference_ctx_size: 4096\n",
+        )
+        .unwrap();
+        assert_eq!(config.backend, BackendKind::LlamaCpp);
+        assert_eq!(config.device.use_gpu, Some(false));
+        assert_eq!(config.device.inference_ctx_size, Some(4096));
+    }
+
+    #[test]
+    fn test_unknown_field_is_rejected() {
+        assert!(
+            LlmClientConfig::from_toml_str("backend = \"anthropic\"\ntemprature = 0.1").is_err()
+        );
+    }
+
+    #[cfg(any(feature = "llama_cpp_backend", feature = "mistral_rs_backend"))]
+    #[test]
+    fn test_parse_chat_template() {
+        assert_eq!(
+            parse_chat_template("llama3").unwrap(),
+            ChatTemplateOverride::Builtin(BuiltinChatTemplate::Llama3)
+        );
+        assert!(parse_chat_template("vicuna").is_err());
+
+        let path = std::env::temp_dir().join("llm_client_config_chat_template.jinja");
+        std::fs::write(&path, "{{ messages }}").unwrap();
+        let path_str = path.to_str().unwrap();
+        assert_eq!(
+            parse_chat_template(path_str).unwrap(),
+            ChatTemplateOverride::File(path.clone())
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,11 +1,14 @@
 pub mod backend_builders;
 pub mod basic_completion;
 pub mod components;
+pub mod config;
 pub mod prelude;
 pub mod primitives;
+pub mod raw_completion;
 pub mod workflows;
 #[allow(unused_imports)]
 pub(crate) use anyhow::{anyhow, bail, Result};
+pub use chrono;
 #[allow(unused_imports)]
 pub(crate) use llm_devices::logging::{i_ln, i_lns, i_nln, i_nlns};
 pub use prelude::*;
@@ -14,6 +17,8 @@ pub(crate) use tracing::{debug, error, info, span, trace, warn, Level};
 
 pub struct LlmClient {
     pub backend: std::sync::Arc<llm_interface::llms::LlmBackend>,
+    /// Defaults applied to the request config of every workflow created by this client. Set by [`LlmClient::from_config`].
+    pub request_defaults: config::RequestDefaults,
 }
 
 impl LlmClient {
@@ -25,7 +30,16 @@ impl LlmClient {
                 colorful::RGB::new(94, 244, 39)
             ))
         );
-        Self { backend }
+        Self {
+            backend,
+            request_defaults: config::RequestDefaults::default(),
+        }
+    }
+
+    /// Creates an `LlmClient` from a TOML, YAML or JSON config file. `LLM_CLIENT_*` environment variables override values in the file.
+    /// See [`config::LlmClientConfig`] for the format.
+    pub async fn from_config<P: AsRef<std::path::Path>>(path: P) -> crate::Result<Self> {
+        config::LlmClientConfig::from_file(path)?.init().await
     }
     #[cfg(feature = "llama_cpp_backend")]
     /// Creates a new instance of the [`LlamaCppBackendBuilder`]. This builder that allows you to specify the model and other parameters. It is converted to an `LlmClient` instance using the `init` method.
@@ -54,20 +68,94 @@ impl LlmClient {
         backend_builders::perplexity::PerplexityBackendBuilder::default()
     }
 
+    /// Creates a new instance of the [`GenericApiBackendBuilder`] for OpenAI compatible servers such as vLLM, LM Studio or LiteLLM. This builder that allows you to specify the host, model and other parameters. It is converted to an `LlmClient` instance using the `init` method.
+    pub fn generic_api() -> backend_builders::generic_openai::GenericApiBackendBuilder {
+        backend_builders::generic_openai::GenericApiBackendBuilder::default()
+    }
+
+    /// Creates a new instance of the [`RouterBackendBuilder`]. This builder chains already initialized backends, and fails over between them. It is converted to an `LlmClient` instance using the `init` method.
+    pub fn router() -> backend_builders::router::RouterBackendBuilder {
+        backend_builders::router::RouterBackendBuilder::default()
+    }
+
     pub fn basic_completion(&self) -> basic_completion::BasicCompletion {
-        basic_completion::BasicCompletion::new(self.backend.clone())
+        let mut completion = basic_completion::BasicCompletion::new(self.backend.clone());
+        self.request_defaults.apply(&mut completion.base_req.config);
+        completion
+    }
+
+    pub fn raw_completion(&self) -> raw_completion::RawCompletion {
+        let mut completion = raw_completion::RawCompletion::new(self.backend.clone());
+        self.request_defaults.apply(&mut completion.base_req.config);
+        completion
     }
 
     pub fn basic_primitive(&self) -> workflows::basic_primitive::BasicPrimitiveWorkflowBuilder {
-        workflows::basic_primitive::BasicPrimitiveWorkflowBuilder::new(self.backend.clone())
+        let mut builder =
+            workflows::basic_primitive::BasicPrimitiveWorkflowBuilder::new(self.backend.clone());
+        self.request_defaults.apply(&mut builder.base_req.config);
+        builder
     }
 
     pub fn reason(&self) -> workflows::reason::ReasonWorkflowBuilder {
-        workflows::reason::ReasonWorkflowBuilder::new(self.backend.clone())
+        let mut builder = workflows::reason::ReasonWorkflowBuilder::new(self.backend.clone());
+        self.request_defaults.apply(&mut builder.base_req.config);
+        builder
     }
 
     pub fn nlp(&self) -> workflows::nlp::Nlp {
-        workflows::nlp::Nlp::new(self.backend.clone())
+        let mut nlp = workflows::nlp::Nlp::new(self.backend.clone());
+        self.request_defaults.apply(&mut nlp.base_req.config);
+        nlp
+    }
+
+    /// The number of tokens in a prompt, including any chat template.
+    pub fn count_tokens(&self, prompt: &LlmPrompt) -> crate::Result<u64> {
+        Ok(prompt.get_total_prompt_tokens()?)
+    }
+
+    /// The number of tokens in a string, using the backend's tokenizer.
+    pub fn count_text_tokens<T: AsRef<str>>(&self, text: T) -> u64 {
+        self.backend.tokenizer().count_tokens(text.as_ref()) as u64
+    }
+
+    /// Cuts text to at most `max_tokens` tokens.
+    pub fn truncate_to_tokens<T: AsRef<str>>(
+        &self,
+        text: T,
+        max_tokens: u64,
+    ) -> crate::Result<String> {
+        components::tokens::truncate_to_tokens(self.backend.tokenizer(), text.as_ref(), max_tokens)
+    }
+
+    /// Whether the prompt leaves room for `response_tokens` in the model's context.
+    /// Checked with the same limits a request uses, so a `true` here won't fail with `RequestTokenLimitError`.
+    pub fn fits_in_context(&self, prompt: &LlmPrompt, response_tokens: u64) -> crate::Result<bool> {
+        Ok(components::tokens::fits_in_context(
+            &self.request_config(),
+            self.count_tokens(prompt)?,
+            response_tokens,
+        ))
+    }
+
+    /// The largest prompt, in tokens, that leaves room for `response_tokens`.
+    pub fn available_prompt_tokens(&self, response_tokens: u64) -> u64 {
+        components::tokens::available_prompt_tokens(&self.request_config(), response_tokens)
+    }
+
+    /// The request config of a new request from this client, with the client's `request_defaults`.
+    fn request_config(&self) -> llm_interface::requests::req_components::RequestConfig {
+        let mut config = llm_interface::requests::req_components::RequestConfig::new(
+            self.backend.model_ctx_size(),
+            self.backend.inference_ctx_size(),
+        );
+        self.request_defaults.apply(&mut config);
+        config
+    }
+
+    /// A splitter that cuts text into chunks of at most `max_tokens` tokens.
+    pub fn text_splitter(&self, max_tokens: u64) -> components::tokens::TokenTextSplitter {
+        components::tokens::TokenTextSplitter::new(self.backend.tokenizer().clone(), max_tokens)
     }
 
     pub fn shutdown(&self) {
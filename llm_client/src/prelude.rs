@@ -1,7 +1,20 @@
 pub use crate::{
-    components::InstructPromptTrait,
+    components::{
+        chunking::{ChunkBy, ChunkingConfig},
+        InstructPromptTrait,
+    },
     primitives::PrimitiveTrait,
-    workflows::reason::{decision::DecisionTrait, ReasonTrait},
+    workflows::nlp::{classify::hierarchy::TagSystem, extract::entities::EntitySchema},
+    workflows::reason::{
+        consensus::{ConsensusStrategy, DecisionError, VoteWeight},
+        decision::DecisionTrait,
+        ensemble::EnsembleMember,
+        sampling::{
+            DynamicTemperature, FixedSampling, LinearRampSampling, PromptPerturbation,
+            SamplingSchedule,
+        },
+        ReasonTrait,
+    },
     LlmClient,
 };
 #[cfg(any(target_os = "linux", target_os = "windows"))]
@@ -11,7 +24,10 @@ pub use llm_devices::logging::LoggingConfigTrait;
 #[cfg(target_os = "macos")]
 pub use llm_devices::devices::MetalConfig;
 pub use llm_interface::{
-    llms::local::LlmLocalTrait,
+    llms::{
+        local::{chat_template::BuiltinChatTemplate, LlmLocalTrait},
+        router::builder::RouterBackendTrait,
+    },
     requests::{
         completion::{CompletionRequest, CompletionResponse},
         constraints::logit_bias::LogitBiasTrait,
@@ -52,12 +52,12 @@ impl PrimitiveTrait for BooleanPrimitive {
 }
 
 impl ReasonTrait for BooleanPrimitive {
-    fn primitive_to_result_index(&self, content: &str) -> u32 {
-        let output = self.parse_to_primitive(content).unwrap();
+    fn primitive_to_result_index(&self, content: &str) -> Result<u32> {
+        let output = self.parse_to_primitive(content)?;
         if output {
-            1
+            Ok(1)
         } else {
-            0
+            Ok(0)
         }
     }
 
@@ -72,4 +72,8 @@ impl ReasonTrait for BooleanPrimitive {
             Ok(None)
         }
     }
+
+    fn result_to_string(&self, result: Self::PrimitiveResult) -> String {
+        result.to_string()
+    }
 }
@@ -0,0 +1,164 @@
+use super::PrimitiveTrait;
+use crate::workflows::reason::ReasonTrait;
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use llm_utils::grammar::Grammar;
+
+/// GBNF rules for an ISO-8601 calendar date, `YYYY-MM-DD`, with years from 1000 to 9999.
+/// Days are limited by month, except that February 29th is allowed in any year. It fails parsing in non-leap years.
+pub(super) const DATE_RULES: &str = "\
+date ::= year \"-\" (month31 \"-\" day31 | month30 \"-\" day30 | \"02-\" day29)
+year ::= [1-9] [0-9] [0-9] [0-9]
+month31 ::= \"0\" [13578] | \"1\" [02]
+month30 ::= \"0\" [469] | \"11\"
+day29 ::= \"0\" [1-9] | [12] [0-9]
+day30 ::= day29 | \"30\"
+day31 ::= day30 | \"31\"";
+
+/// A calendar date, such as a contract start date, written by the model as `YYYY-MM-DD`.
+///
+/// Dates outside `min`..=`max` fail to parse.
+/// As a reason primitive, the result index is the day number, so decisions vote on exact dates.
+#[derive(Debug, Clone, Default)]
+pub struct DatePrimitive {
+    pub min: Option<NaiveDate>,
+    pub max: Option<NaiveDate>,
+}
+
+impl DatePrimitive {
+    /// The earliest date allowed. Defaults to `None`.
+    pub fn min(&mut self, min: NaiveDate) -> &mut Self {
+        self.min = Some(min);
+        self
+    }
+
+    /// The latest date allowed. Defaults to `None`.
+    pub fn max(&mut self, max: NaiveDate) -> &mut Self {
+        self.max = Some(max);
+        self
+    }
+
+    fn grammar_inner(&self) -> String {
+        format!("root ::= \" \"? date\n{DATE_RULES}")
+    }
+}
+
+impl PrimitiveTrait for DatePrimitive {
+    type PrimitiveResult = NaiveDate;
+
+    fn clear_primitive(&mut self) {}
+
+    fn type_description(&self, result_can_be_none: bool) -> &str {
+        if result_can_be_none {
+            "date or 'Unknown.'"
+        } else {
+            "date"
+        }
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        let description = format!(
+            "a date in the format YYYY-MM-DD{}",
+            bounds_description(&self.min, &self.max)
+        );
+        if result_can_be_none {
+            format!("{description} or, if the date is unknown, 'Unknown.'")
+        } else {
+            description
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("Unknown.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::custom()
+            .custom_grammar(self.grammar_inner())
+            .wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        let content = content.trim().trim_end_matches('.');
+        let parsed = NaiveDate::parse_from_str(content, "%Y-%m-%d")
+            .map_err(|e| anyhow::format_err!("Failed to parse '{content}' as a date: {e}"))?;
+        check_bounds(parsed, &self.min, &self.max)
+    }
+}
+
+impl ReasonTrait for DatePrimitive {
+    fn primitive_to_result_index(&self, content: &str) -> Result<u32> {
+        Ok(self.parse_to_primitive(content)?.num_days_from_ce() as u32)
+    }
+
+    fn result_index_to_primitive(&self, result_index: Option<u32>) -> Result<Option<NaiveDate>> {
+        if let Some(result_index) = result_index {
+            match NaiveDate::from_num_days_from_ce_opt(result_index as i32) {
+                Some(date) => Ok(Some(date)),
+                None => Err(anyhow::format_err!(
+                    "{result_index} is not a valid day number"
+                )),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn result_to_string(&self, result: Self::PrimitiveResult) -> String {
+        result.to_string()
+    }
+}
+
+pub(super) fn bounds_description<T: std::fmt::Display>(min: &Option<T>, max: &Option<T>) -> String {
+    match (min, max) {
+        (Some(min), Some(max)) => format!(", from {min} to {max}"),
+        (Some(min), None) => format!(", no earlier than {min}"),
+        (None, Some(max)) => format!(", no later than {max}"),
+        (None, None) => String::new(),
+    }
+}
+
+/// Errors if the value is outside `min`..=`max`.
+pub(super) fn check_bounds<T: PartialOrd + std::fmt::Display>(
+    value: T,
+    min: &Option<T>,
+    max: &Option<T>,
+) -> Result<T> {
+    let below_min = min.as_ref().is_some_and(|min| value < *min);
+    let above_max = max.as_ref().is_some_and(|max| value > *max);
+    if below_min || above_max {
+        return Err(anyhow::format_err!(
+            "{value} is out of range{}",
+            bounds_description(min, max)
+        ));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date() {
+        let mut primitive = DatePrimitive::default();
+        let date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(primitive.parse_to_primitive(" 2024-02-29.").unwrap(), date);
+        assert!(primitive.parse_to_primitive("2023-02-29").is_err());
+        assert!(primitive.primitive_to_result_index("2023-02-29").is_err());
+        let index = primitive.primitive_to_result_index("2024-02-29").unwrap();
+        assert_eq!(
+            primitive.result_index_to_primitive(Some(index)).unwrap(),
+            Some(date)
+        );
+
+        primitive.min(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+        assert!(primitive.parse_to_primitive("2024-02-29").is_err());
+        assert!(primitive.primitive_to_result_index("2024-02-29").is_err());
+        assert!(primitive.parse_to_primitive("2025-01-01").is_ok());
+    }
+}
@@ -0,0 +1,166 @@
+use super::{
+    date::{bounds_description, check_bounds, DATE_RULES},
+    PrimitiveTrait,
+};
+use crate::workflows::reason::ReasonTrait;
+use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use llm_utils::grammar::Grammar;
+
+/// A date and time of day without a time zone, written by the model as `YYYY-MM-DDTHH:MM` or `YYYY-MM-DDTHH:MM:SS`.
+///
+/// Values outside `min`..=`max` fail to parse.
+/// As a reason primitive, the result index counts minutes from `min`, or from 1900-01-01 if `min` isn't set,
+/// so decisions vote to the minute. Times before 1900-01-01 have no result index when `min` isn't set.
+#[derive(Debug, Clone, Default)]
+pub struct DateTimePrimitive {
+    pub min: Option<NaiveDateTime>,
+    pub max: Option<NaiveDateTime>,
+}
+
+impl DateTimePrimitive {
+    /// The earliest date and time allowed. Defaults to `None`.
+    pub fn min(&mut self, min: NaiveDateTime) -> &mut Self {
+        self.min = Some(min);
+        self
+    }
+
+    /// The latest date and time allowed. Defaults to `None`.
+    pub fn max(&mut self, max: NaiveDateTime) -> &mut Self {
+        self.max = Some(max);
+        self
+    }
+
+    fn grammar_inner(&self) -> String {
+        format!(
+            "root ::= \" \"? date \"T\" time\n{DATE_RULES}
+time ::= hour \":\" sixty (\":\" sixty)?
+hour ::= [01] [0-9] | \"2\" [0-3]
+sixty ::= [0-5] [0-9]"
+        )
+    }
+
+    fn index_origin(&self) -> NaiveDateTime {
+        self.min.unwrap_or(
+            NaiveDate::from_ymd_opt(1900, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        )
+    }
+}
+
+impl PrimitiveTrait for DateTimePrimitive {
+    type PrimitiveResult = NaiveDateTime;
+
+    fn clear_primitive(&mut self) {}
+
+    fn type_description(&self, result_can_be_none: bool) -> &str {
+        if result_can_be_none {
+            "date and time or 'Unknown.'"
+        } else {
+            "date and time"
+        }
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        let description = format!(
+            "a date and time in the format YYYY-MM-DDTHH:MM{}",
+            bounds_description(&self.min, &self.max)
+        );
+        if result_can_be_none {
+            format!("{description} or, if the date and time are unknown, 'Unknown.'")
+        } else {
+            description
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("Unknown.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::custom()
+            .custom_grammar(self.grammar_inner())
+            .wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        let content = content.trim().trim_end_matches('.');
+        let parsed = NaiveDateTime::parse_from_str(content, "%Y-%m-%dT%H:%M:%S")
+            .or_else(|_| NaiveDateTime::parse_from_str(content, "%Y-%m-%dT%H:%M"))
+            .map_err(|e| {
+                anyhow::format_err!("Failed to parse '{content}' as a date and time: {e}")
+            })?;
+        check_bounds(parsed, &self.min, &self.max)
+    }
+}
+
+impl ReasonTrait for DateTimePrimitive {
+    fn primitive_to_result_index(&self, content: &str) -> Result<u32> {
+        let output = self.parse_to_primitive(content)?;
+        let minutes = (output - self.index_origin()).num_minutes();
+        u32::try_from(minutes).map_err(|_| {
+            anyhow::format_err!(
+                "{output} is too far from {} for a result index",
+                self.index_origin()
+            )
+        })
+    }
+
+    fn result_index_to_primitive(
+        &self,
+        result_index: Option<u32>,
+    ) -> Result<Option<NaiveDateTime>> {
+        if let Some(result_index) = result_index {
+            Ok(Some(
+                self.index_origin() + TimeDelta::minutes(result_index as i64),
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn result_to_string(&self, result: Self::PrimitiveResult) -> String {
+        result.format("%Y-%m-%dT%H:%M").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_time() {
+        let mut primitive = DateTimePrimitive::default();
+        let date_time = NaiveDate::from_ymd_opt(2024, 7, 1)
+            .unwrap()
+            .and_hms_opt(9, 30, 0)
+            .unwrap();
+        assert_eq!(
+            primitive.parse_to_primitive(" 2024-07-01T09:30").unwrap(),
+            date_time
+        );
+        assert_eq!(
+            primitive.parse_to_primitive("2024-07-01T09:30:45").unwrap(),
+            date_time + TimeDelta::seconds(45)
+        );
+        let index = primitive
+            .primitive_to_result_index("2024-07-01T09:30:45")
+            .unwrap();
+        assert_eq!(
+            primitive.result_index_to_string(index).unwrap(),
+            "2024-07-01T09:30"
+        );
+        assert!(primitive
+            .primitive_to_result_index("1899-12-31T23:59")
+            .is_err());
+
+        primitive.max(date_time);
+        assert!(primitive.parse_to_primitive("2024-07-01T09:31").is_err());
+    }
+}
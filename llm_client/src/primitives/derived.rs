@@ -0,0 +1,438 @@
+use super::PrimitiveTrait;
+use crate::workflows::reason::ReasonTrait;
+use anyhow::Result;
+use llm_utils::grammar::Grammar;
+use std::collections::HashSet;
+
+/// A Rust type the LLM can produce directly, usually implemented with `#[derive(LlmPrimitive)]`.
+///
+/// On enums with unit variants, the derive allows exactly one variant name, with descriptions taken from the
+/// variants' doc comments, and also implements [`LlmChoice`], `Display` and `FromStr`. Use `#[llm(rename = "..")]` to change a name.
+/// On structs with named fields, the derive writes the fields in order as `name: value; other: value`,
+/// where each field's type must also implement `LlmPrimitive`.
+///
+/// ```ignore
+/// #[derive(Debug, LlmPrimitive)]
+/// enum Sentiment {
+///     /// The text is favorable.
+///     Positive,
+///     Negative,
+/// }
+/// let sentiment = llm_client.basic_primitive().derived::<Sentiment>().return_primitive().await?;
+/// ```
+pub trait LlmPrimitive: Sized + std::fmt::Debug {
+    /// GBNF rules, with `root` as the entry rule.
+    fn gbnf() -> String;
+
+    /// A short name for the type, as in "the {type_description} solution".
+    fn type_description() -> &'static str;
+
+    fn solution_description() -> String;
+
+    fn parse_llm(content: &str) -> std::result::Result<Self, String>;
+}
+
+/// An [`LlmPrimitive`] with a fixed set of values, so it can be used in a `Decision`.
+/// Implemented by `#[derive(LlmPrimitive)]` on enums.
+pub trait LlmChoice: LlmPrimitive + std::fmt::Display {
+    /// The index a `Decision` votes on.
+    fn result_index(&self) -> u32;
+
+    fn from_result_index(result_index: u32) -> Option<Self>;
+}
+
+/// Wraps an [`LlmPrimitive`] type as a primitive for `BasicPrimitiveWorkflow` and `Decision`.
+pub struct DerivedPrimitive<T> {
+    _result: std::marker::PhantomData<T>,
+}
+
+impl<T> Default for DerivedPrimitive<T> {
+    fn default() -> Self {
+        Self {
+            _result: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: LlmPrimitive> PrimitiveTrait for DerivedPrimitive<T> {
+    type PrimitiveResult = T;
+
+    fn clear_primitive(&mut self) {}
+
+    fn type_description(&self, _result_can_be_none: bool) -> &str {
+        T::type_description()
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        if result_can_be_none {
+            format!(
+                "{}, or, possibly, 'None of the above.'",
+                T::solution_description()
+            )
+        } else {
+            T::solution_description()
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("None of the above.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::custom()
+            .custom_grammar(format!(
+                "root ::= \" \"? value-root\n{}",
+                prefix_rule_names(&T::gbnf(), "value-")
+            ))
+            .wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        T::parse_llm(content.trim()).map_err(|e| anyhow::format_err!(e))
+    }
+}
+
+impl<T: LlmChoice> ReasonTrait for DerivedPrimitive<T> {
+    fn primitive_to_result_index(&self, content: &str) -> Result<u32> {
+        Ok(self.parse_to_primitive(content)?.result_index())
+    }
+
+    fn result_index_to_primitive(&self, result_index: Option<u32>) -> Result<Option<T>> {
+        if let Some(result_index) = result_index {
+            match T::from_result_index(result_index) {
+                Some(result) => Ok(Some(result)),
+                None => Err(anyhow::format_err!(
+                    "{result_index} is not a valid {} result index",
+                    T::type_description()
+                )),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn result_to_string(&self, result: Self::PrimitiveResult) -> String {
+        result.to_string()
+    }
+}
+
+impl LlmPrimitive for bool {
+    fn gbnf() -> String {
+        "root ::= \"true\" | \"false\"".to_owned()
+    }
+
+    fn type_description() -> &'static str {
+        "boolean"
+    }
+
+    fn solution_description() -> String {
+        "true or false".to_owned()
+    }
+
+    fn parse_llm(content: &str) -> std::result::Result<Self, String> {
+        content
+            .trim()
+            .to_lowercase()
+            .parse()
+            .map_err(|e| format!("Failed to parse '{content}' as a boolean: {e}"))
+    }
+}
+
+macro_rules! llm_primitive_integer_impl {
+    ($($type:ty => $gbnf:expr),*) => {
+        $(
+            impl LlmPrimitive for $type {
+                fn gbnf() -> String {
+                    $gbnf.to_owned()
+                }
+
+                fn type_description() -> &'static str {
+                    "number"
+                }
+
+                fn solution_description() -> String {
+                    format!("a whole number from {} to {}", <$type>::MIN, <$type>::MAX)
+                }
+
+                fn parse_llm(content: &str) -> std::result::Result<Self, String> {
+                    content
+                        .trim()
+                        .parse()
+                        .map_err(|e| format!("Failed to parse '{content}' as a number: {e}"))
+                }
+            }
+        )*
+    }
+}
+
+llm_primitive_integer_impl! {
+    u8 => "root ::= [0-9] [0-9]? [0-9]?",
+    u16 => "root ::= [0-9] [0-9]? [0-9]? [0-9]? [0-9]?",
+    u32 => "root ::= [0-9] [0-9]? [0-9]? [0-9]? [0-9]? [0-9]? [0-9]? [0-9]? [0-9]? [0-9]?",
+    u64 => "root ::= [0-9]+",
+    i8 => "root ::= \"-\"? [0-9] [0-9]? [0-9]?",
+    i16 => "root ::= \"-\"? [0-9] [0-9]? [0-9]? [0-9]? [0-9]?",
+    i32 => "root ::= \"-\"? [0-9] [0-9]? [0-9]? [0-9]? [0-9]? [0-9]? [0-9]? [0-9]? [0-9]? [0-9]?",
+    i64 => "root ::= \"-\"? [0-9]+"
+}
+
+macro_rules! llm_primitive_float_impl {
+    ($($type:ty),*) => {
+        $(
+            impl LlmPrimitive for $type {
+                fn gbnf() -> String {
+                    "root ::= \"-\"? [0-9]+ (\".\" [0-9]+)?".to_owned()
+                }
+
+                fn type_description() -> &'static str {
+                    "decimal number"
+                }
+
+                fn solution_description() -> String {
+                    "a decimal number".to_owned()
+                }
+
+                fn parse_llm(content: &str) -> std::result::Result<Self, String> {
+                    content
+                        .trim()
+                        .parse()
+                        .map_err(|e| format!("Failed to parse '{content}' as a number: {e}"))
+                }
+            }
+        )*
+    }
+}
+
+llm_primitive_float_impl! { f32, f64 }
+
+impl LlmPrimitive for String {
+    /// A single line of text. Semicolons are excluded, since they separate struct fields.
+    fn gbnf() -> String {
+        "root ::= [^;\\n] [^;\\n]*".to_owned()
+    }
+
+    fn type_description() -> &'static str {
+        "text"
+    }
+
+    fn solution_description() -> String {
+        "a short text on one line, without semicolons".to_owned()
+    }
+
+    fn parse_llm(content: &str) -> std::result::Result<Self, String> {
+        let content = content.trim();
+        if content.is_empty() {
+            Err("Expected text, but the response was empty".to_owned())
+        } else {
+            Ok(content.to_owned())
+        }
+    }
+}
+
+/// GBNF for one of `labels`. Used by the enum derive.
+#[doc(hidden)]
+pub fn enum_gbnf(labels: &[&str]) -> String {
+    format!(
+        "root ::= {}",
+        labels
+            .iter()
+            .map(|label| format!("\"{}\"", escape_gbnf_literal(label)))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    )
+}
+
+/// Finds the label matching `content`, exactly or ignoring case. Used by the enum derive.
+#[doc(hidden)]
+pub fn enum_parse(content: &str, labels: &[&str]) -> std::result::Result<u32, String> {
+    let content = content.trim().trim_end_matches('.');
+    labels
+        .iter()
+        .position(|label| *label == content)
+        .or_else(|| {
+            labels
+                .iter()
+                .position(|label| label.eq_ignore_ascii_case(content))
+        })
+        .map(|index| index as u32)
+        .ok_or_else(|| format!("'{content}' is not one of: {}", labels.join(", ")))
+}
+
+/// Used by the enum derive.
+#[doc(hidden)]
+pub fn enum_solution_description(variants: &[(&str, Option<&str>)]) -> String {
+    format!(
+        "one of the following: {}",
+        variants
+            .iter()
+            .map(|(label, description)| match description {
+                Some(description) => format!("{label} ({description})"),
+                None => label.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// GBNF for `label: value; other: value`, embedding each field's grammar. Used by the struct derive.
+#[doc(hidden)]
+pub fn struct_gbnf(fields: &[(&str, String)]) -> String {
+    let mut root = Vec::new();
+    let mut rules = Vec::new();
+    for (i, (label, gbnf)) in fields.iter().enumerate() {
+        let separator = if i == 0 { "" } else { "; " };
+        root.push(format!(
+            "\"{separator}{}: \" f{i}-root",
+            escape_gbnf_literal(label)
+        ));
+        rules.push(prefix_rule_names(gbnf, &format!("f{i}-")));
+    }
+    format!("root ::= {}\n{}", root.join(" "), rules.join("\n"))
+}
+
+/// Used by the struct derive.
+#[doc(hidden)]
+pub fn struct_solution_description(fields: &[(&str, Option<&str>, String)]) -> String {
+    format!(
+        "each field in order, formatted as '{}', where {}",
+        fields
+            .iter()
+            .map(|(label, _, _)| format!("{label}: value"))
+            .collect::<Vec<_>>()
+            .join("; "),
+        fields
+            .iter()
+            .map(|(label, description, solution)| match description {
+                Some(description) => format!("{label} ({description}) is {solution}"),
+                None => format!("{label} is {solution}"),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Splits `label: value; other: value` into the values, in order. Used by the struct derive.
+#[doc(hidden)]
+pub fn struct_split<'a>(
+    content: &'a str,
+    labels: &[&str],
+) -> std::result::Result<Vec<&'a str>, String> {
+    let mut values = Vec::with_capacity(labels.len());
+    let mut rest = content.trim();
+    for (i, label) in labels.iter().enumerate() {
+        let prefix = format!("{label}:");
+        rest = rest
+            .strip_prefix(&prefix)
+            .ok_or_else(|| format!("Expected field '{label}' in '{content}'"))?;
+        match labels.get(i + 1) {
+            Some(next) => {
+                let separator = format!("; {next}:");
+                let end = rest
+                    .find(&separator)
+                    .ok_or_else(|| format!("Expected field '{next}' in '{content}'"))?;
+                values.push(rest[..end].trim());
+                rest = &rest[end + 2..];
+            }
+            None => values.push(rest.trim()),
+        }
+    }
+    Ok(values)
+}
+
+fn escape_gbnf_literal(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Prefixes every rule name defined in a GBNF grammar, and every reference to one,
+/// so the grammar can be embedded in another without name clashes.
+pub(crate) fn prefix_rule_names(gbnf: &str, prefix: &str) -> String {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '-';
+    let names: HashSet<&str> = gbnf
+        .lines()
+        .filter_map(|line| line.split_once("::="))
+        .map(|(name, _)| name.trim())
+        .filter(|name| !name.is_empty() && name.chars().all(is_name_char))
+        .collect();
+
+    let mut output = String::with_capacity(gbnf.len());
+    let mut chars = gbnf.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' | '[' => {
+                // Copy string literals and character classes as they are, including escapes.
+                let close = if c == '"' { '"' } else { ']' };
+                output.push(c);
+                while let Some((_, c)) = chars.next() {
+                    output.push(c);
+                    if c == '\\' {
+                        if let Some((_, escaped)) = chars.next() {
+                            output.push(escaped);
+                        }
+                    } else if c == close {
+                        break;
+                    }
+                }
+            }
+            '#' => {
+                output.push(c);
+                for (_, c) in chars.by_ref() {
+                    output.push(c);
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            c if is_name_char(c) => {
+                let mut end = i + c.len_utf8();
+                while let Some((j, c)) = chars.peek().copied() {
+                    if !is_name_char(c) {
+                        break;
+                    }
+                    end = j + c.len_utf8();
+                    chars.next();
+                }
+                let name = &gbnf[i..end];
+                if names.contains(name) {
+                    output.push_str(prefix);
+                }
+                output.push_str(name);
+            }
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_rule_names() {
+        let gbnf = "root ::= \" \"? digits # one or two\ndigits ::= [0-9] \"root\"? [0-9]?";
+        assert_eq!(
+            prefix_rule_names(gbnf, "item-"),
+            "item-root ::= \" \"? item-digits # one or two\nitem-digits ::= [0-9] \"root\"? [0-9]?"
+        );
+    }
+
+    #[test]
+    fn test_struct_helpers() {
+        assert_eq!(
+            struct_gbnf(&[("name", String::gbnf()), ("age", u8::gbnf())]),
+            "root ::= \"name: \" f0-root \"; age: \" f1-root\nf0-root ::= [^;\\n] [^;\\n]*\nf1-root ::= [0-9] [0-9]? [0-9]?"
+        );
+        assert_eq!(
+            struct_split("name: Ada; Lovelace; age: 36", &["name", "age"]).unwrap(),
+            vec!["Ada; Lovelace", "36"]
+        );
+        assert!(struct_split("name: Ada", &["name", "age"]).is_err());
+        assert_eq!(enum_parse(" positive.", &["Positive", "Negative"]), Ok(0));
+    }
+}
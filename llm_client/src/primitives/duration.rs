@@ -0,0 +1,231 @@
+use super::{
+    date::{bounds_description, check_bounds},
+    PrimitiveTrait,
+};
+use crate::workflows::reason::ReasonTrait;
+use anyhow::Result;
+use chrono::TimeDelta;
+use llm_utils::grammar::Grammar;
+
+/// A length of time, written by the model as an ISO-8601 duration such as `P2W`, `P1DT12H` or `PT90M`.
+///
+/// Only weeks, days, hours, minutes and seconds are allowed, since years and months have no fixed length.
+/// Durations outside `min`..=`max` fail to parse.
+/// As a reason primitive, the result index is the duration in seconds, so decisions vote on exact durations.
+#[derive(Debug, Clone, Default)]
+pub struct DurationPrimitive {
+    pub min: Option<TimeDelta>,
+    pub max: Option<TimeDelta>,
+}
+
+impl DurationPrimitive {
+    /// The shortest duration allowed. Defaults to `None`.
+    pub fn min(&mut self, min: TimeDelta) -> &mut Self {
+        self.min = Some(min);
+        self
+    }
+
+    /// The longest duration allowed. Defaults to `None`.
+    pub fn max(&mut self, max: TimeDelta) -> &mut Self {
+        self.max = Some(max);
+        self
+    }
+
+    fn grammar_inner(&self) -> String {
+        "\
+root ::= \" \"? \"P\" duration
+duration ::= weeks days? time? | days time? | time
+weeks ::= num \"W\"
+days ::= num \"D\"
+time ::= \"T\" (hours minutes? seconds? | minutes seconds? | seconds)
+hours ::= num \"H\"
+minutes ::= num \"M\"
+seconds ::= num \"S\"
+num ::= [0-9] [0-9]? [0-9]? [0-9]?"
+            .to_owned()
+    }
+}
+
+impl PrimitiveTrait for DurationPrimitive {
+    type PrimitiveResult = TimeDelta;
+
+    fn clear_primitive(&mut self) {}
+
+    fn type_description(&self, result_can_be_none: bool) -> &str {
+        if result_can_be_none {
+            "duration or 'Unknown.'"
+        } else {
+            "duration"
+        }
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        let min = self.min.map(format_iso_duration);
+        let max = self.max.map(format_iso_duration);
+        let description = format!(
+            "an ISO-8601 duration using weeks, days, hours, minutes and seconds, such as P1W2D or PT1H30M{}",
+            bounds_description(&min, &max)
+        );
+        if result_can_be_none {
+            format!("{description} or, if the duration is unknown, 'Unknown.'")
+        } else {
+            description
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("Unknown.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::custom()
+            .custom_grammar(self.grammar_inner())
+            .wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        let content = content.trim().trim_end_matches('.');
+        let parsed = parse_iso_duration(content)?;
+        check_bounds(parsed, &self.min, &self.max)
+    }
+}
+
+impl ReasonTrait for DurationPrimitive {
+    fn primitive_to_result_index(&self, content: &str) -> Result<u32> {
+        let output = self.parse_to_primitive(content)?;
+        u32::try_from(output.num_seconds())
+            .map_err(|_| anyhow::format_err!("{content} is too long for a result index"))
+    }
+
+    fn result_index_to_primitive(&self, result_index: Option<u32>) -> Result<Option<TimeDelta>> {
+        if let Some(result_index) = result_index {
+            Ok(Some(TimeDelta::seconds(result_index as i64)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn result_to_string(&self, result: Self::PrimitiveResult) -> String {
+        format_iso_duration(result)
+    }
+}
+
+fn parse_iso_duration(content: &str) -> Result<TimeDelta> {
+    let rest = content
+        .strip_prefix('P')
+        .ok_or_else(|| anyhow::format_err!("'{content}' is not an ISO-8601 duration"))?;
+    let (date_part, time_part) = match rest.split_once('T') {
+        Some((date_part, time_part)) => (date_part, Some(time_part)),
+        None => (rest, None),
+    };
+    let mut seconds: i64 = 0;
+    let mut components = 0;
+    for (part, units) in [
+        (date_part, &[('W', 604_800), ('D', 86_400)][..]),
+        (
+            time_part.unwrap_or_default(),
+            &[('H', 3_600), ('M', 60), ('S', 1)][..],
+        ),
+    ] {
+        let mut number = String::new();
+        for c in part.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let multiplier = units
+                .iter()
+                .find(|(unit, _)| *unit == c)
+                .map(|(_, multiplier)| *multiplier)
+                .ok_or_else(|| anyhow::format_err!("Unexpected '{c}' in duration '{content}'"))?;
+            if number.is_empty() {
+                return Err(anyhow::format_err!(
+                    "Missing number before '{c}' in '{content}'"
+                ));
+            }
+            seconds = number
+                .parse::<i64>()
+                .ok()
+                .and_then(|value| value.checked_mul(multiplier))
+                .and_then(|value| seconds.checked_add(value))
+                .ok_or_else(|| anyhow::format_err!("Duration '{content}' is too long"))?;
+            components += 1;
+            number.clear();
+        }
+        if !number.is_empty() {
+            return Err(anyhow::format_err!(
+                "Missing unit after '{number}' in '{content}'"
+            ));
+        }
+    }
+    if components == 0 {
+        return Err(anyhow::format_err!(
+            "'{content}' has no duration components"
+        ));
+    }
+    TimeDelta::try_seconds(seconds)
+        .ok_or_else(|| anyhow::format_err!("Duration '{content}' is too long"))
+}
+
+fn format_iso_duration(duration: TimeDelta) -> String {
+    let total = duration.num_seconds().max(0);
+    let (days, hours, minutes, seconds) = (
+        total / 86_400,
+        total % 86_400 / 3_600,
+        total % 3_600 / 60,
+        total % 60,
+    );
+    let mut iso = "P".to_owned();
+    if days > 0 {
+        iso.push_str(&format!("{days}D"));
+    }
+    if hours > 0 || minutes > 0 || seconds > 0 || days == 0 {
+        iso.push('T');
+        if hours > 0 {
+            iso.push_str(&format!("{hours}H"));
+        }
+        if minutes > 0 {
+            iso.push_str(&format!("{minutes}M"));
+        }
+        if seconds > 0 || total == 0 {
+            iso.push_str(&format!("{seconds}S"));
+        }
+    }
+    iso
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duration() {
+        let mut primitive = DurationPrimitive::default();
+        assert_eq!(
+            primitive.parse_to_primitive(" P1W2DT3H.").unwrap(),
+            TimeDelta::days(9) + TimeDelta::hours(3)
+        );
+        assert_eq!(
+            primitive.parse_to_primitive("PT90M").unwrap(),
+            TimeDelta::minutes(90)
+        );
+        assert!(primitive.parse_to_primitive("P").is_err());
+        assert!(primitive.parse_to_primitive("P1Y").is_err());
+        assert!(primitive.parse_to_primitive("PT5").is_err());
+        assert!(primitive.parse_to_primitive("P99999999999999999W").is_err());
+        assert!(primitive.parse_to_primitive("PT9999999999999999S").is_err());
+
+        let index = primitive.primitive_to_result_index("PT90M").unwrap();
+        assert_eq!(index, 5400);
+        assert_eq!(primitive.result_index_to_string(index).unwrap(), "PT1H30M");
+        assert_eq!(primitive.result_index_to_string(86_400).unwrap(), "P1D");
+        assert_eq!(primitive.result_index_to_string(0).unwrap(), "PT0S");
+
+        primitive.max(TimeDelta::hours(1));
+        assert!(primitive.parse_to_primitive("P1D").is_err());
+    }
+}
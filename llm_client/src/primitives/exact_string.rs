@@ -87,12 +87,14 @@ impl PrimitiveTrait for ExactStringPrimitive {
 }
 
 impl ReasonTrait for ExactStringPrimitive {
-    fn primitive_to_result_index(&self, content: &str) -> u32 {
-        let output = self.parse_to_primitive(content).unwrap();
+    fn primitive_to_result_index(&self, content: &str) -> Result<u32> {
+        let output = self.parse_to_primitive(content)?;
         if let Some(index) = self.allowed_strings.iter().position(|s| s == &output) {
-            index as u32
+            Ok(index as u32)
         } else {
-            panic!("This shouldn't happen.")
+            Err(anyhow::format_err!(
+                "'{output}' is not one of the allowed strings"
+            ))
         }
     }
 
@@ -107,4 +109,8 @@ impl ReasonTrait for ExactStringPrimitive {
             Ok(None)
         }
     }
+
+    fn result_to_string(&self, result: Self::PrimitiveResult) -> String {
+        result
+    }
 }
@@ -0,0 +1,261 @@
+use super::{signed_integer::range_rules, PrimitiveTrait};
+use crate::workflows::reason::ReasonTrait;
+use anyhow::Result;
+use llm_utils::grammar::Grammar;
+use std::collections::HashMap;
+
+/// How a `Decision` combines float votes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatAggregation {
+    /// Votes are rounded into buckets of `bucket_size`, and the bucket with a majority wins.
+    Bucket,
+    /// Every vote is collected and the median wins. The winner's votes are those within one bucket of the median.
+    #[default]
+    Median,
+}
+
+/// A decimal number, such as a score from 0.0 to 1.0, a percentage or a negative delta.
+///
+/// The grammar only allows numbers within the bounds, with up to `decimal_places` digits after the point.
+/// Values outside the bounds fail to parse.
+/// As a reason primitive, the result index is the value's bucket, counted from the lower bound.
+#[derive(Debug, Clone)]
+pub struct FloatPrimitive {
+    pub lower_bound: f64,
+    pub upper_bound: f64,
+    /// Defaults to 2.
+    pub decimal_places: u8,
+    /// The width of a voting bucket. Defaults to `None`, using the smallest step `decimal_places` allows.
+    pub bucket_size: Option<f64>,
+    pub aggregation: FloatAggregation,
+}
+
+impl Default for FloatPrimitive {
+    fn default() -> Self {
+        FloatPrimitive {
+            lower_bound: 0.0,
+            upper_bound: 1.0,
+            decimal_places: 2,
+            bucket_size: None,
+            aggregation: FloatAggregation::default(),
+        }
+    }
+}
+
+impl FloatPrimitive {
+    /// Set the lower bound of the range. Default is 0.0.
+    pub fn lower_bound(&mut self, lower_bound: f64) -> &mut Self {
+        self.lower_bound = lower_bound;
+        self
+    }
+
+    /// Set the upper bound of the range. Default is 1.0.
+    pub fn upper_bound(&mut self, upper_bound: f64) -> &mut Self {
+        self.upper_bound = upper_bound;
+        self
+    }
+
+    /// Set the most digits allowed after the decimal point. Zero allows whole numbers only.
+    pub fn decimal_places(&mut self, decimal_places: u8) -> &mut Self {
+        self.decimal_places = decimal_places;
+        self
+    }
+
+    pub fn bucket_size(&mut self, bucket_size: f64) -> &mut Self {
+        self.bucket_size = Some(bucket_size);
+        self
+    }
+
+    pub fn aggregation(&mut self, aggregation: FloatAggregation) -> &mut Self {
+        self.aggregation = aggregation;
+        self
+    }
+
+    fn step(&self) -> f64 {
+        match self.bucket_size {
+            Some(bucket_size) if bucket_size > 0.0 => bucket_size,
+            _ => 10f64.powi(-(self.decimal_places as i32)),
+        }
+    }
+
+    fn round(&self, value: f64) -> f64 {
+        let factor = 10f64.powi(self.decimal_places as i32);
+        (value * factor).round() / factor
+    }
+
+    /// A bound in steps of the smallest decimal place, rounded inward so the grammar stays within the bounds.
+    fn bound_steps(&self, bound: f64, round_up: bool) -> i64 {
+        let steps = bound * 10f64.powi(self.decimal_places as i32);
+        // Ignore float error, as in 0.1 * 10.0.
+        if (steps - steps.round()).abs() < 1e-6 {
+            steps.round() as i64
+        } else if round_up {
+            steps.ceil() as i64
+        } else {
+            steps.floor() as i64
+        }
+    }
+
+    fn grammar_inner(&self) -> String {
+        range_rules(
+            self.bound_steps(self.lower_bound, true),
+            self.bound_steps(self.upper_bound, false),
+            self.decimal_places as u32,
+        )
+        .join("\n")
+    }
+}
+
+impl PrimitiveTrait for FloatPrimitive {
+    type PrimitiveResult = f64;
+
+    fn clear_primitive(&mut self) {}
+
+    fn type_description(&self, result_can_be_none: bool) -> &str {
+        if result_can_be_none {
+            "decimal number or 'Unknown.'"
+        } else {
+            "decimal number"
+        }
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        let description = format!(
+            "a number from {} to {} with at most {} decimal places",
+            self.lower_bound, self.upper_bound, self.decimal_places
+        );
+        if result_can_be_none {
+            format!("{description} or, if the solution is unknown or not in range, 'Unknown.'")
+        } else {
+            description
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("Unknown.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::custom()
+            .custom_grammar(self.grammar_inner())
+            .wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        let content = content.trim().trim_end_matches('.');
+        let parsed: f64 = content
+            .parse()
+            .map_err(|e| anyhow::format_err!("Failed to parse '{content}' as a number: {e}"))?;
+        if !parsed.is_finite() {
+            return Err(anyhow::format_err!("'{content}' is not a finite number"));
+        }
+        let rounded = self.round(parsed);
+        if rounded < self.lower_bound || rounded > self.upper_bound {
+            return Err(anyhow::format_err!(
+                "{parsed} is not between {} and {}",
+                self.lower_bound,
+                self.upper_bound
+            ));
+        }
+        Ok(rounded)
+    }
+}
+
+impl ReasonTrait for FloatPrimitive {
+    fn primitive_to_result_index(&self, content: &str) -> Result<u32> {
+        let output = self.parse_to_primitive(content)?;
+        Ok(((output - self.lower_bound) / self.step()).round() as u32)
+    }
+
+    fn result_index_to_primitive(&self, result_index: Option<u32>) -> Result<Option<f64>> {
+        if let Some(result_index) = result_index {
+            let value = self.lower_bound + result_index as f64 * self.step();
+            Ok(Some(self.round(value.min(self.upper_bound))))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn result_to_string(&self, result: Self::PrimitiveResult) -> String {
+        format!("{result:.*}", self.decimal_places as usize)
+    }
+
+    fn collect_all_votes(&self) -> bool {
+        self.aggregation == FloatAggregation::Median
+    }
+
+    fn tally_votes(&self, votes: &HashMap<u32, u16>, _total_votes: u16) -> Option<(u32, u16)> {
+        match self.aggregation {
+            FloatAggregation::Bucket => votes
+                .iter()
+                .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+                .map(|(result_index, votes)| (*result_index, *votes)),
+            FloatAggregation::Median => {
+                let mut sorted: Vec<(u32, u16)> = votes.iter().map(|(i, v)| (*i, *v)).collect();
+                sorted.sort_unstable();
+                let counted: u32 = sorted.iter().map(|(_, v)| *v as u32).sum();
+                // The lower median, so the winner is always a value that was voted for.
+                let middle = counted.div_ceil(2);
+                let mut seen = 0;
+                let median = sorted.iter().find_map(|(index, votes)| {
+                    seen += *votes as u32;
+                    (seen >= middle).then_some(*index)
+                })?;
+                let winner_votes = sorted
+                    .iter()
+                    .filter(|(index, _)| index.abs_diff(median) <= 1)
+                    .fold(0u16, |sum, (_, votes)| sum.saturating_add(*votes));
+                Some((median, winner_votes))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float() {
+        let mut primitive = FloatPrimitive::default();
+        assert_eq!(
+            primitive.grammar_inner(),
+            "root ::= \" \"? pos\npos ::= [0] (\".\" [0-9] [0-9]?)? | [1] (\".\" [0] [0]?)?"
+        );
+        assert_eq!(primitive.parse_to_primitive(" 0.256.").unwrap(), 0.26);
+        assert!(primitive.parse_to_primitive("3").is_err());
+        assert!(primitive.parse_to_primitive("-0.5").is_err());
+        assert_eq!(primitive.primitive_to_result_index("0.25").unwrap(), 25);
+        assert_eq!(primitive.result_index_to_string(25).unwrap(), "0.25");
+
+        primitive
+            .lower_bound(-100.0)
+            .upper_bound(100.0)
+            .decimal_places(1);
+        assert_eq!(
+            primitive.grammar_inner(),
+            "root ::= \" \"? (\"-\" neg | pos)\nneg ::= [0] \".\" [1-9] | ([1-9] | [1-9] [0-9]) (\".\" [0-9])? | [1] [0] [0] (\".\" [0])?\npos ::= ([0-9] | [1-9] [0-9]) (\".\" [0-9])? | [1] [0] [0] (\".\" [0])?"
+        );
+        assert_eq!(primitive.parse_to_primitive("-12.5").unwrap(), -12.5);
+        primitive.bucket_size(5.0);
+        assert_eq!(primitive.primitive_to_result_index("-12.5").unwrap(), 18);
+        assert_eq!(
+            primitive.result_index_to_primitive(Some(18)).unwrap(),
+            Some(-10.0)
+        );
+    }
+
+    #[test]
+    fn test_tally_votes() {
+        let mut primitive = FloatPrimitive::default();
+        // 0.20, 0.21, 0.21, 0.90
+        let votes = HashMap::from([(20, 1), (21, 2), (90, 1)]);
+        assert_eq!(primitive.tally_votes(&votes, 4), Some((21, 3)));
+        primitive.aggregation(FloatAggregation::Bucket);
+        assert_eq!(primitive.tally_votes(&votes, 4), Some((21, 2)));
+    }
+}
@@ -84,8 +84,8 @@ impl PrimitiveTrait for IntegerPrimitive {
 }
 
 impl ReasonTrait for IntegerPrimitive {
-    fn primitive_to_result_index(&self, content: &str) -> u32 {
-        self.parse_to_primitive(content).unwrap()
+    fn primitive_to_result_index(&self, content: &str) -> Result<u32> {
+        self.parse_to_primitive(content)
     }
 
     fn result_index_to_primitive(&self, result_index: Option<u32>) -> Result<Option<u32>> {
@@ -95,4 +95,8 @@ impl ReasonTrait for IntegerPrimitive {
             Ok(None)
         }
     }
+
+    fn result_to_string(&self, result: Self::PrimitiveResult) -> String {
+        result.to_string()
+    }
 }
@@ -0,0 +1,247 @@
+use super::{derived::prefix_rule_names, PrimitiveTrait};
+use anyhow::Result;
+use llm_utils::grammar::Grammar;
+use std::collections::HashSet;
+
+/// A list of another primitive's results, such as 3 to 7 integers or a list of allowed labels.
+///
+/// The grammar repeats the item primitive's grammar, with its rules renamed to `item-*`, separated by `delimiter`.
+/// Items are split on the delimiter when parsing. An item that fails to parse is joined with the next one,
+/// so items that contain the delimiter, like sentences, still parse.
+///
+/// ```ignore
+/// let mut gen = llm_client.basic_primitive().list::<IntegerPrimitive>();
+/// gen.primitive.min_count(3).max_count(7).unique(true);
+/// gen.primitive.item.upper_bound(100);
+/// ```
+pub struct ListPrimitive<P> {
+    pub item: P,
+    /// Defaults to 1. The grammar always produces at least one item.
+    pub min_count: u8,
+    /// Defaults to `None`, allowing any number of items.
+    pub max_count: Option<u8>,
+    /// Defaults to ", ".
+    pub delimiter: String,
+    /// Drops repeated items, compared by their text. Defaults to false.
+    ///
+    /// The grammar can't rule out repeats, so they're dropped after the response. If fewer than `min_count` items remain,
+    /// the response fails to parse, and the basic primitive workflow counts it as a failed attempt and retries.
+    pub unique: bool,
+}
+
+impl<P: PrimitiveTrait> Default for ListPrimitive<P> {
+    fn default() -> Self {
+        ListPrimitive {
+            item: P::default(),
+            min_count: 1,
+            max_count: None,
+            delimiter: ", ".to_string(),
+            unique: false,
+        }
+    }
+}
+
+impl<P: PrimitiveTrait> ListPrimitive<P> {
+    pub fn min_count(&mut self, min_count: u8) -> &mut Self {
+        self.min_count = min_count;
+        self
+    }
+
+    pub fn max_count(&mut self, max_count: u8) -> &mut Self {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    pub fn delimiter(&mut self, delimiter: &str) -> &mut Self {
+        self.delimiter = delimiter.to_string();
+        self
+    }
+
+    pub fn unique(&mut self, unique: bool) -> &mut Self {
+        self.unique = unique;
+        self
+    }
+
+    fn min(&self) -> u8 {
+        self.min_count.max(1)
+    }
+
+    fn count_description(&self) -> String {
+        match self.max_count {
+            Some(max_count) if max_count == self.min() => format!("{max_count}"),
+            Some(max_count) => format!("between {}-{}", self.min(), max_count),
+            None => format!("at least {}", self.min()),
+        }
+    }
+
+    fn grammar_inner(&self, item_grammar: &str) -> String {
+        let delimiter = self
+            .delimiter
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        let min = self.min();
+        let mut items = vec!["item-root".to_owned()];
+        items.extend((1..min).map(|_| "list-next".to_owned()));
+        match self.max_count {
+            None => items.push("list-next*".to_owned()),
+            Some(max_count) => {
+                // Nested optionals, as in `next (next next?)?`, so each count has one parse.
+                let optional = (min..max_count.max(min)).fold(String::new(), |inner, _| {
+                    if inner.is_empty() {
+                        "list-next?".to_owned()
+                    } else {
+                        format!("(list-next {inner})?")
+                    }
+                });
+                if !optional.is_empty() {
+                    items.push(optional);
+                }
+            }
+        }
+        format!(
+            "root ::= {}\nlist-next ::= \"{delimiter}\" item-root\n{}",
+            items.join(" "),
+            prefix_rule_names(item_grammar, "item-")
+        )
+    }
+
+    /// Splits on the delimiter, joining pieces until the item primitive can parse them.
+    fn parse_items<'a>(&self, content: &'a str) -> Result<Vec<(&'a str, P::PrimitiveResult)>> {
+        let delimiter = if self.delimiter.trim().is_empty() {
+            self.delimiter.as_str()
+        } else {
+            self.delimiter.trim()
+        };
+        let mut items = Vec::new();
+        let mut start: Option<usize> = None;
+        let mut offset = 0;
+        let pieces: Vec<&str> = content.split(delimiter).collect();
+        for (i, piece) in pieces.iter().enumerate() {
+            let item_start = *start.get_or_insert(offset);
+            offset += piece.len() + delimiter.len();
+            let end = (offset - delimiter.len()).min(content.len());
+            let text = content[item_start..end].trim();
+            match self.item.parse_to_primitive(text) {
+                Ok(result) => {
+                    items.push((text, result));
+                    start = None;
+                }
+                Err(e) if i + 1 == pieces.len() => {
+                    return Err(anyhow::format_err!(
+                        "Failed to parse list item '{text}': {e}"
+                    ))
+                }
+                Err(_) => (),
+            }
+        }
+        Ok(items)
+    }
+}
+
+impl<P: PrimitiveTrait> PrimitiveTrait for ListPrimitive<P> {
+    type PrimitiveResult = Vec<P::PrimitiveResult>;
+
+    fn clear_primitive(&mut self) {
+        self.item.clear_primitive();
+    }
+
+    fn type_description(&self, result_can_be_none: bool) -> &str {
+        if result_can_be_none {
+            "list or 'None.'"
+        } else {
+            "list"
+        }
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        let description = format!(
+            "a list of {}{} items separated by '{}', where each item is {}",
+            self.count_description(),
+            if self.unique { " different" } else { "" },
+            self.delimiter,
+            self.item.solution_description(false)
+        );
+        if result_can_be_none {
+            format!("{description}, or, possibly, 'None.'")
+        } else {
+            description
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("None.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::custom()
+            .custom_grammar(self.grammar_inner(&self.item.grammar().grammar_string()))
+            .wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        let mut items = self.parse_items(content.trim())?;
+        let parsed_count = items.len();
+        if self.unique {
+            let mut seen = HashSet::new();
+            items.retain(|(text, _)| seen.insert(*text));
+        }
+        let count = items.len();
+        if count < self.min() as usize || self.max_count.is_some_and(|max| count > max as usize) {
+            let repeats = if parsed_count > count {
+                " after dropping repeated items"
+            } else {
+                ""
+            };
+            return Err(anyhow::format_err!(
+                "Expected {} items, but found {count}{repeats}",
+                self.count_description()
+            ));
+        }
+        Ok(items.into_iter().map(|(_, result)| result).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::SignedIntegerPrimitive;
+
+    #[test]
+    fn test_list_grammar() {
+        let mut list = ListPrimitive::<SignedIntegerPrimitive>::default();
+        list.min_count(2).max_count(4);
+        assert_eq!(
+            list.grammar_inner("root ::= [0-9]"),
+            "root ::= item-root list-next (list-next list-next?)?\nlist-next ::= \", \" item-root\nitem-root ::= [0-9]"
+        );
+    }
+
+    #[test]
+    fn test_list_parse() {
+        let mut list = ListPrimitive::<SignedIntegerPrimitive>::default();
+        assert_eq!(
+            list.parse_to_primitive(" 3, -1, 3").unwrap(),
+            vec![3, -1, 3]
+        );
+        list.unique(true);
+        assert_eq!(list.parse_to_primitive("3, -1, 3").unwrap(), vec![3, -1]);
+        list.min_count(3);
+        assert_eq!(
+            list.parse_to_primitive("3, -1, 3").unwrap_err().to_string(),
+            "Expected at least 3 items, but found 2 after dropping repeated items"
+        );
+        assert!(list.parse_to_primitive("3, x, 4").is_err());
+
+        let mut list = ListPrimitive::<crate::primitives::RegexPrimitive>::default();
+        list.item.pattern("[a-z]+(, [a-z]+)? [0-9]").unwrap();
+        assert_eq!(
+            list.parse_to_primitive("red, blue 1, green 2").unwrap(),
+            vec!["red, blue 1", "green 2"]
+        );
+    }
+}
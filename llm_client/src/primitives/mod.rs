@@ -1,21 +1,40 @@
 pub mod boolean;
+pub mod date;
+pub mod date_time;
+pub mod derived;
+pub mod duration;
 pub mod exact_string;
+pub mod float;
 pub mod integer;
+pub mod list;
+pub mod multi_choice;
+pub mod regex;
 pub mod sentences;
+pub mod signed_integer;
 pub mod text;
 pub mod words;
 
+pub use self::regex::RegexPrimitive;
 use anyhow::Result;
 pub use boolean::BooleanPrimitive;
+pub use date::DatePrimitive;
+pub use date_time::DateTimePrimitive;
+pub use derived::{DerivedPrimitive, LlmChoice, LlmPrimitive};
+pub use duration::DurationPrimitive;
 pub use exact_string::ExactStringPrimitive;
+pub use float::{FloatAggregation, FloatPrimitive};
 pub use integer::IntegerPrimitive;
+pub use list::ListPrimitive;
+pub use llm_client_derive::LlmPrimitive;
 use llm_utils::grammar::Grammar;
+pub use multi_choice::MultiChoicePrimitive;
 pub use sentences::SentencesPrimitive;
+pub use signed_integer::SignedIntegerPrimitive;
 pub use text::TextPrimitive;
 pub use words::WordsPrimitive;
 
 pub trait PrimitiveTrait: Default {
-    type PrimitiveResult: std::str::FromStr + std::fmt::Display;
+    type PrimitiveResult: std::fmt::Debug;
 
     fn clear_primitive(&mut self);
 
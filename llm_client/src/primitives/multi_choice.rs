@@ -0,0 +1,375 @@
+use super::PrimitiveTrait;
+use crate::workflows::reason::ReasonTrait;
+use anyhow::Result;
+use llm_utils::grammar::Grammar;
+use std::collections::HashMap;
+
+/// Selects a subset of the allowed strings, as in "select all that apply".
+///
+/// The grammar allows between `min_count` and `max_count` of the strings, in the order they were added, each at most once, separated by `, `.
+/// As a reason primitive, the result index is a bitmask of the chosen strings, so at most 32 strings are allowed.
+/// In a `Decision`, every vote is collected and each string chosen by at least `vote_threshold` of the votes is kept.
+#[derive(Debug, Clone)]
+pub struct MultiChoicePrimitive {
+    pub allowed_strings: Vec<String>,
+    /// Defaults to 1.
+    pub min_count: u8,
+    /// Defaults to `None`, allowing every string.
+    pub max_count: Option<u8>,
+    /// The share of a decision's votes a string needs to be kept. Defaults to 0.5.
+    pub vote_threshold: f32,
+}
+
+impl Default for MultiChoicePrimitive {
+    fn default() -> Self {
+        MultiChoicePrimitive {
+            allowed_strings: Vec::new(),
+            min_count: 1,
+            max_count: None,
+            vote_threshold: 0.5,
+        }
+    }
+}
+
+const MAX_ALLOWED_STRINGS: usize = 32;
+
+impl MultiChoicePrimitive {
+    pub fn add_strings_to_allowed<T: AsRef<str>>(&mut self, words: &[T]) -> Result<&mut Self> {
+        for word in words {
+            self.add_string_to_allowed(word)?;
+        }
+        Ok(self)
+    }
+
+    /// Errors if there are already 32 strings, the most a result index can hold, or if the string contains a comma,
+    /// which separates the chosen strings in a response.
+    pub fn add_string_to_allowed<T: AsRef<str>>(&mut self, word: T) -> Result<&mut Self> {
+        if word.as_ref().contains(',') {
+            return Err(anyhow::format_err!(
+                "MultiChoicePrimitive strings can't contain a comma: '{}'",
+                word.as_ref()
+            ));
+        }
+        if self
+            .allowed_strings
+            .iter()
+            .any(|text| text == word.as_ref())
+        {
+            return Ok(self);
+        }
+        if self.allowed_strings.len() >= MAX_ALLOWED_STRINGS {
+            return Err(anyhow::format_err!(
+                "MultiChoicePrimitive allows at most {MAX_ALLOWED_STRINGS} strings"
+            ));
+        }
+        self.allowed_strings.push(word.as_ref().to_owned());
+        Ok(self)
+    }
+
+    pub fn remove_string_from_allowed<T: AsRef<str>>(&mut self, word: T) -> &mut Self {
+        self.allowed_strings.retain(|w| w != word.as_ref());
+        self
+    }
+
+    /// The fewest strings to select. The grammar always selects at least one.
+    pub fn min_count(&mut self, min_count: u8) -> &mut Self {
+        self.min_count = min_count;
+        self
+    }
+
+    pub fn max_count(&mut self, max_count: u8) -> &mut Self {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    pub fn vote_threshold(&mut self, vote_threshold: f32) -> &mut Self {
+        self.vote_threshold = vote_threshold;
+        self
+    }
+
+    fn max(&self) -> usize {
+        match self.max_count {
+            Some(max_count) => (max_count as usize).min(self.allowed_strings.len()),
+            None => self.allowed_strings.len(),
+        }
+    }
+
+    fn min(&self) -> usize {
+        (self.min_count as usize).max(1)
+    }
+
+    /// An ordered subset of `min` to `max` strings. Rule `c{i}-{k}` picks the next string, from string `i` on,
+    /// with `k` strings picked so far. Only the rules that can still reach `min` strings are added.
+    fn grammar_inner(&self) -> String {
+        let count = self.allowed_strings.len();
+        let max = self.max();
+        let min = self.min().min(max);
+        // Whether a string from `i` on can be picked after `k` strings, and still reach `min`.
+        let reachable =
+            |i: usize, k: usize| i < count && k < max && count - i >= min.saturating_sub(k);
+        let mut rules = vec!["root ::= \" \"? c0-0".to_owned()];
+        for (i, string) in self.allowed_strings.iter().enumerate() {
+            let literal = string.replace('\\', "\\\\").replace('"', "\\\"");
+            rules.push(format!("i{i} ::= \"{literal}\""));
+        }
+        for i in 0..count {
+            for k in 0..=i.min(max) {
+                if !reachable(i, k) {
+                    continue;
+                }
+                let mut alternatives = Vec::new();
+                // Pick string `i`, then continue after it.
+                let picked = k + 1;
+                if picked < min {
+                    if reachable(i + 1, picked) {
+                        alternatives.push(format!("i{i} \", \" c{}-{picked}", i + 1));
+                    }
+                } else if reachable(i + 1, picked) {
+                    alternatives.push(format!("i{i} (\", \" c{}-{picked})?", i + 1));
+                } else {
+                    alternatives.push(format!("i{i}"));
+                }
+                // Skip string `i`.
+                if reachable(i + 1, k) {
+                    alternatives.push(format!("c{}-{k}", i + 1));
+                }
+                rules.push(format!("c{i}-{k} ::= {}", alternatives.join(" | ")));
+            }
+        }
+        rules.join("\n")
+    }
+
+    fn label_votes(&self, votes: &HashMap<u32, u16>) -> Vec<u16> {
+        (0..self.allowed_strings.len())
+            .map(|i| {
+                votes
+                    .iter()
+                    .filter(|(mask, _)| *mask & (1u32 << i) != 0)
+                    .fold(0u16, |sum, (_, votes)| sum.saturating_add(*votes))
+            })
+            .collect()
+    }
+}
+
+impl PrimitiveTrait for MultiChoicePrimitive {
+    type PrimitiveResult = Vec<String>;
+
+    fn clear_primitive(&mut self) {
+        self.allowed_strings.clear();
+    }
+
+    fn type_description(&self, result_can_be_none: bool) -> &str {
+        if result_can_be_none {
+            "comma separated list of strings or 'None of the above.'"
+        } else {
+            "comma separated list of strings"
+        }
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        let count = match (self.min(), self.max()) {
+            (min, max) if min == max => format!("{min}"),
+            (1, max) if max == self.allowed_strings.len() => "one or more".to_owned(),
+            (min, max) => format!("between {min} and {max}"),
+        };
+        if result_can_be_none {
+            format!(
+                "{count} of the following strings, separated by commas: {}, or, possibly, 'None of the above.'",
+                self.allowed_strings.join(", ")
+            )
+        } else {
+            format!(
+                "{count} of the following strings, separated by commas: {}",
+                self.allowed_strings.join(", ")
+            )
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("None of the above.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::custom()
+            .custom_grammar(self.grammar_inner())
+            .wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        let mut chosen: Vec<usize> = Vec::new();
+        for item in content.trim().trim_end_matches('.').split(',') {
+            let item = item.trim();
+            if item.is_empty() {
+                continue;
+            }
+            let index = self
+                .allowed_strings
+                .iter()
+                .position(|s| s == item)
+                .or_else(|| {
+                    self.allowed_strings
+                        .iter()
+                        .position(|s| s.eq_ignore_ascii_case(item))
+                })
+                .ok_or_else(|| anyhow::format_err!("'{item}' is not one of the allowed strings"))?;
+            if !chosen.contains(&index) {
+                chosen.push(index);
+            }
+        }
+        if chosen.len() < self.min() || chosen.len() > self.max() {
+            return Err(anyhow::format_err!(
+                "Selected {} strings, but between {} and {} are required",
+                chosen.len(),
+                self.min(),
+                self.max()
+            ));
+        }
+        chosen.sort_unstable();
+        Ok(chosen
+            .into_iter()
+            .map(|index| self.allowed_strings[index].clone())
+            .collect())
+    }
+}
+
+impl ReasonTrait for MultiChoicePrimitive {
+    fn primitive_to_result_index(&self, content: &str) -> Result<u32> {
+        if self.allowed_strings.len() > MAX_ALLOWED_STRINGS {
+            return Err(anyhow::format_err!(
+                "MultiChoicePrimitive allows at most {MAX_ALLOWED_STRINGS} strings in a decision"
+            ));
+        }
+        let output = self.parse_to_primitive(content)?;
+        Ok(self
+            .allowed_strings
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| output.contains(*s))
+            .fold(0, |mask, (i, _)| mask | (1u32 << i)))
+    }
+
+    fn result_index_to_primitive(&self, result_index: Option<u32>) -> Result<Option<Vec<String>>> {
+        if let Some(result_index) = result_index {
+            Ok(Some(
+                self.allowed_strings
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| result_index & (1u32 << i) != 0)
+                    .map(|(_, s)| s.clone())
+                    .collect(),
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn result_to_string(&self, result: Self::PrimitiveResult) -> String {
+        result.join(", ")
+    }
+
+    fn collect_all_votes(&self) -> bool {
+        true
+    }
+
+    /// Keeps every string with at least `vote_threshold` of the votes, most voted first, up to `max_count`.
+    /// If fewer than `min_count` pass, the next most voted strings are added. The winner's votes are those of its least voted string.
+    fn tally_votes(&self, votes: &HashMap<u32, u16>, total_votes: u16) -> Option<(u32, u16)> {
+        let label_votes = self.label_votes(votes);
+        let mut ranked: Vec<usize> = (0..label_votes.len())
+            .filter(|i| label_votes[*i] > 0)
+            .collect();
+        // Ties go to the lowest index.
+        ranked.sort_by(|a, b| label_votes[*b].cmp(&label_votes[*a]).then(a.cmp(b)));
+
+        let threshold = self.vote_threshold * total_votes as f32;
+        let passing = ranked
+            .iter()
+            .filter(|i| label_votes[**i] as f32 >= threshold)
+            .count();
+        let keep = passing.max(self.min()).min(self.max()).min(ranked.len());
+        if keep == 0 {
+            return None;
+        }
+        let kept = &ranked[..keep];
+        let mask = kept.iter().fold(0, |mask, i| mask | (1u32 << i));
+        let winner_votes = kept.iter().map(|i| label_votes[*i]).min().unwrap_or(0);
+        Some((mask, winner_votes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primitive() -> MultiChoicePrimitive {
+        let mut primitive = MultiChoicePrimitive::default();
+        primitive
+            .add_strings_to_allowed(&["red", "green", "blue"])
+            .unwrap();
+        primitive
+    }
+
+    #[test]
+    fn test_parse() {
+        let primitive = primitive();
+        assert_eq!(
+            primitive.parse_to_primitive(" blue, red, blue.").unwrap(),
+            vec!["red", "blue"]
+        );
+        assert!(primitive.parse_to_primitive("purple").is_err());
+        assert!(primitive.parse_to_primitive("").is_err());
+        // A comma in a string would split it when parsed.
+        assert!(primitive
+            .clone()
+            .add_string_to_allowed("red, green")
+            .is_err());
+        assert_eq!(
+            primitive.primitive_to_result_index("red, blue").unwrap(),
+            0b101
+        );
+        assert_eq!(
+            primitive.result_index_to_primitive(Some(0b110)).unwrap(),
+            Some(vec!["green".to_owned(), "blue".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_tally_votes() {
+        let mut primitive = primitive();
+        // red: 3 votes, green: 1 vote, blue: 2 votes.
+        let votes = HashMap::from([(0b101, 2), (0b011, 1)]);
+        assert_eq!(primitive.tally_votes(&votes, 3), Some((0b101, 2)));
+        primitive.max_count(1);
+        assert_eq!(primitive.tally_votes(&votes, 3), Some((0b001, 3)));
+        primitive.max_count(3).vote_threshold(1.0).min_count(2);
+        assert_eq!(primitive.tally_votes(&votes, 3), Some((0b101, 2)));
+    }
+
+    #[test]
+    fn test_grammar_string() {
+        assert_eq!(
+            primitive().grammar_inner(),
+            "root ::= \" \"? c0-0\ni0 ::= \"red\"\ni1 ::= \"green\"\ni2 ::= \"blue\"\nc0-0 ::= i0 (\", \" c1-1)? | c1-0\nc1-0 ::= i1 (\", \" c2-1)? | c2-0\nc1-1 ::= i1 (\", \" c2-2)? | c2-1\nc2-0 ::= i2\nc2-1 ::= i2\nc2-2 ::= i2"
+        );
+        // Exactly two strings.
+        assert_eq!(
+            primitive().min_count(2).max_count(2).grammar_inner(),
+            "root ::= \" \"? c0-0\ni0 ::= \"red\"\ni1 ::= \"green\"\ni2 ::= \"blue\"\nc0-0 ::= i0 \", \" c1-1 | c1-0\nc1-0 ::= i1 \", \" c2-1\nc1-1 ::= i1 | c2-1\nc2-1 ::= i2"
+        );
+    }
+
+    #[test]
+    fn test_max_allowed_strings() {
+        let mut primitive = MultiChoicePrimitive::default();
+        let strings: Vec<String> = (0..MAX_ALLOWED_STRINGS).map(|i| i.to_string()).collect();
+        primitive.add_strings_to_allowed(&strings).unwrap();
+        // Duplicates are ignored rather than rejected.
+        assert!(primitive.add_string_to_allowed("0").is_ok());
+        assert!(primitive.add_string_to_allowed("32").is_err());
+        assert_eq!(primitive.allowed_strings.len(), MAX_ALLOWED_STRINGS);
+    }
+}
@@ -0,0 +1,376 @@
+use super::PrimitiveTrait;
+use anyhow::Result;
+use llm_utils::grammar::Grammar;
+use regex_syntax::hir::{Class, Hir, HirKind, Look};
+
+/// The most times a bounded repetition, like `\d{3}`, is written out in the grammar.
+const MAX_REPETITION: u32 = 256;
+
+/// A string that matches a regex, such as a SKU, an ISO country code or a version string.
+///
+/// The pattern is compiled to a GBNF grammar when it's set, so constructs the grammar can't express, like
+/// look-around, backreferences and word boundaries, fail there instead of at inference time.
+/// Anchors are only allowed at the start and end of the pattern, since the whole response must match.
+/// `\d`, `\w` and `\s` match ASCII only, which keeps the grammar small. Use `\p{..}` classes for Unicode.
+/// The response is also validated with the regex itself, which covers backends without native grammar support.
+///
+/// Create it with a pattern, as in `basic_primitive().regex(pattern)?` or [`RegexPrimitive::new`].
+/// Without one, as after `Default::default()`, every response fails to parse.
+///
+/// ```ignore
+/// let mut gen = llm_client.basic_primitive().regex(r"[A-Z]{3}-\d{4}")?;
+/// gen.primitive.description("a SKU, like ABC-1234");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RegexPrimitive {
+    pub pattern: Option<String>,
+    /// Describes the expected format to the model. Defaults to the pattern itself.
+    pub description: Option<String>,
+    regex: Option<::regex::Regex>,
+    gbnf: Option<String>,
+}
+
+impl RegexPrimitive {
+    pub fn new(pattern: &str) -> crate::Result<Self> {
+        let mut primitive = Self::default();
+        primitive.pattern(pattern)?;
+        Ok(primitive)
+    }
+
+    /// Sets the pattern and compiles it. Errors if the regex is invalid or uses constructs the grammar can't express.
+    pub fn pattern(&mut self, pattern: &str) -> crate::Result<&mut Self> {
+        let ascii_pattern = ascii_perl_classes(pattern);
+        let hir = regex_syntax::ParserBuilder::new()
+            .build()
+            .parse(&ascii_pattern)
+            .map_err(|e| crate::anyhow!("Invalid regex pattern {pattern:?}: {e}"))?;
+        let gbnf = hir_to_gbnf(&strip_anchors(&hir))
+            .map_err(|e| crate::anyhow!("Unsupported regex pattern {pattern:?}: {e}"))?;
+        let regex = ::regex::Regex::new(&format!("^(?:{ascii_pattern})$"))?;
+        self.pattern = Some(pattern.to_owned());
+        self.regex = Some(regex);
+        self.gbnf = Some(format!("root ::= \" \"? {gbnf}"));
+        Ok(self)
+    }
+
+    pub fn description(&mut self, description: &str) -> &mut Self {
+        self.description = Some(description.to_owned());
+        self
+    }
+
+    fn format_description(&self) -> String {
+        match (&self.description, &self.pattern) {
+            (Some(description), _) => description.clone(),
+            (None, Some(pattern)) => format!("a string matching the regex {pattern}"),
+            (None, None) => "a string".to_owned(),
+        }
+    }
+
+    /// Without a pattern, only an empty response is allowed, and it fails to parse.
+    fn grammar_inner(&self) -> &str {
+        self.gbnf.as_deref().unwrap_or("root ::= \"\"")
+    }
+}
+
+impl PrimitiveTrait for RegexPrimitive {
+    type PrimitiveResult = String;
+
+    /// Keeps the pattern and description, since they're set when the primitive is created.
+    fn clear_primitive(&mut self) {}
+
+    fn type_description(&self, result_can_be_none: bool) -> &str {
+        if result_can_be_none {
+            "formatted string or 'None of the above.'"
+        } else {
+            "formatted string"
+        }
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        if result_can_be_none {
+            format!(
+                "{}, or, if there is no such string, 'None of the above.'",
+                self.format_description()
+            )
+        } else {
+            self.format_description()
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("None of the above.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::custom()
+            .custom_grammar(self.grammar_inner().to_owned())
+            .wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        let regex = match &self.regex {
+            Some(regex) => regex,
+            None => return Err(anyhow::format_err!("RegexPrimitive requires a pattern")),
+        };
+        let content = content.trim();
+        if regex.is_match(content) {
+            Ok(content.to_owned())
+        } else {
+            Err(anyhow::format_err!(
+                "'{content}' does not match the regex {}",
+                self.pattern.as_deref().unwrap_or_default()
+            ))
+        }
+    }
+}
+
+/// Replaces `\d`, `\w` and `\s`, and their negations, with ASCII classes. Nested classes, as in `[[0-9]-]`, are valid.
+fn ascii_perl_classes(pattern: &str) -> String {
+    let mut output = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('d') => output.push_str("[0-9]"),
+            Some('D') => output.push_str("[^0-9]"),
+            Some('w') => output.push_str("[0-9A-Za-z_]"),
+            Some('W') => output.push_str("[^0-9A-Za-z_]"),
+            Some('s') => output.push_str("[\\t\\n\\x0B\\x0C\\r ]"),
+            Some('S') => output.push_str("[^\\t\\n\\x0B\\x0C\\r ]"),
+            Some(next) => {
+                output.push(c);
+                output.push(next);
+            }
+            None => output.push(c),
+        }
+    }
+    output
+}
+
+/// Removes the leading and trailing line anchors, since the response is always matched as a whole.
+fn strip_anchors(hir: &Hir) -> Hir {
+    let is_start = |hir: &Hir| {
+        matches!(
+            hir.kind(),
+            HirKind::Look(Look::Start | Look::StartLF | Look::StartCRLF)
+        )
+    };
+    let is_end = |hir: &Hir| {
+        matches!(
+            hir.kind(),
+            HirKind::Look(Look::End | Look::EndLF | Look::EndCRLF)
+        )
+    };
+    match hir.kind() {
+        HirKind::Concat(subs) => {
+            let mut subs = subs.as_slice();
+            if subs.first().is_some_and(is_start) {
+                subs = &subs[1..];
+            }
+            if subs.last().is_some_and(is_end) {
+                subs = &subs[..subs.len() - 1];
+            }
+            Hir::concat(subs.to_vec())
+        }
+        _ if is_start(hir) || is_end(hir) => Hir::empty(),
+        _ => hir.clone(),
+    }
+}
+
+fn hir_to_gbnf(hir: &Hir) -> std::result::Result<String, String> {
+    Ok(match hir.kind() {
+        HirKind::Empty => "\"\"".to_owned(),
+        HirKind::Literal(literal) => {
+            let text = std::str::from_utf8(&literal.0)
+                .map_err(|_| "literals must be valid UTF-8".to_owned())?;
+            format!(
+                "\"{}\"",
+                text.chars().map(escape_literal_char).collect::<String>()
+            )
+        }
+        HirKind::Class(class) => class_to_gbnf(class)?,
+        HirKind::Look(look) => {
+            return Err(format!(
+                "{look:?} assertions are only supported at the start or end of the pattern"
+            ))
+        }
+        HirKind::Repetition(repetition) => {
+            // Every expression is written as a single term, so it can be repeated without more parentheses.
+            let sub = hir_to_gbnf(&repetition.sub)?;
+            let min = repetition.min;
+            if min > MAX_REPETITION || repetition.max.is_some_and(|max| max > MAX_REPETITION) {
+                return Err(format!("repetitions are limited to {MAX_REPETITION}"));
+            }
+            let mut parts = vec![sub.clone(); min as usize];
+            match repetition.max {
+                None => parts.push(format!("{sub}*")),
+                Some(max) => {
+                    // Nested optionals, as in `x (x (x)?)?`, so each count has one parse.
+                    let optional = (min..max).fold(String::new(), |inner, _| {
+                        if inner.is_empty() {
+                            format!("{sub}?")
+                        } else {
+                            format!("({sub} {inner})?")
+                        }
+                    });
+                    if !optional.is_empty() {
+                        parts.push(optional);
+                    }
+                }
+            }
+            if parts.is_empty() {
+                "\"\"".to_owned()
+            } else {
+                format!("({})", parts.join(" "))
+            }
+        }
+        HirKind::Capture(capture) => hir_to_gbnf(&capture.sub)?,
+        HirKind::Concat(subs) => format!(
+            "({})",
+            subs.iter()
+                .map(hir_to_gbnf)
+                .collect::<std::result::Result<Vec<_>, _>>()?
+                .join(" ")
+        ),
+        HirKind::Alternation(subs) => format!(
+            "({})",
+            subs.iter()
+                .map(hir_to_gbnf)
+                .collect::<std::result::Result<Vec<_>, _>>()?
+                .join(" | ")
+        ),
+    })
+}
+
+/// Writes a class as a GBNF character class, negated if that's shorter, as with `.` becoming `[^\n]`.
+fn class_to_gbnf(class: &Class) -> std::result::Result<String, String> {
+    let ranges = |class: &Class| -> std::result::Result<Vec<(char, char)>, String> {
+        match class {
+            Class::Unicode(class) => Ok(class
+                .ranges()
+                .iter()
+                .map(|range| (range.start(), range.end()))
+                .collect()),
+            Class::Bytes(class) => class
+                .ranges()
+                .iter()
+                .map(|range| {
+                    if range.end().is_ascii() {
+                        Ok((range.start() as char, range.end() as char))
+                    } else {
+                        Err("byte classes must be ASCII".to_owned())
+                    }
+                })
+                .collect(),
+        }
+    };
+    let positive = ranges(class)?;
+    if positive.is_empty() {
+        return Err("empty character classes can't match anything".to_owned());
+    }
+    let mut negated_class = class.clone();
+    negated_class.negate();
+    let negated = match class {
+        // A negated byte class includes non-ASCII bytes, so only Unicode classes are negated.
+        Class::Unicode(_) => Some(ranges(&negated_class)?),
+        Class::Bytes(_) => None,
+    };
+    let write = |ranges: &[(char, char)]| {
+        ranges
+            .iter()
+            .map(|(start, end)| {
+                if start == end {
+                    escape_class_char(*start)
+                } else {
+                    format!("{}-{}", escape_class_char(*start), escape_class_char(*end))
+                }
+            })
+            .collect::<String>()
+    };
+    Ok(match negated {
+        Some(negated) if !negated.is_empty() && negated.len() < positive.len() => {
+            format!("[^{}]", write(&negated))
+        }
+        _ => format!("[{}]", write(&positive)),
+    })
+}
+
+fn escape_literal_char(c: char) -> String {
+    match c {
+        '"' => "\\\"".to_owned(),
+        '\\' => "\\\\".to_owned(),
+        _ => escape_control_char(c),
+    }
+}
+
+/// GBNF has no `\-` or `\^` escapes, so those are written as hex.
+fn escape_class_char(c: char) -> String {
+    match c {
+        ']' | '[' | '\\' => format!("\\{c}"),
+        '-' | '^' => format!("\\x{:02X}", c as u32),
+        _ => escape_control_char(c),
+    }
+}
+
+fn escape_control_char(c: char) -> String {
+    match c {
+        '\n' => "\\n".to_owned(),
+        '\r' => "\\r".to_owned(),
+        '\t' => "\\t".to_owned(),
+        c if (c as u32) < 0x20 || c as u32 == 0x7f => format!("\\x{:02X}", c as u32),
+        c if (c as u32) > 0xFFFF => format!("\\U{:08X}", c as u32),
+        c if !c.is_ascii() && (c.is_control() || c.is_whitespace()) => {
+            format!("\\u{:04X}", c as u32)
+        }
+        c => c.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex_grammar() {
+        let mut primitive = RegexPrimitive::new(r"^[A-Z]{3}-\d{2,3}$").unwrap();
+        assert_eq!(
+            primitive.grammar_inner(),
+            "root ::= \" \"? (([A-Z] [A-Z] [A-Z]) \"-\" ([0-9] [0-9] [0-9]?))"
+        );
+        assert_eq!(primitive.parse_to_primitive(" ABC-123").unwrap(), "ABC-123");
+        assert!(primitive.parse_to_primitive("ABC-1").is_err());
+
+        primitive.pattern(r"v(0|[1-9]\d*)\.(?:x|\d+)").unwrap();
+        assert_eq!(
+            primitive.grammar_inner(),
+            "root ::= \" \"? (\"v\" (\"0\" | ([1-9] ([0-9]*))) \".\" (\"x\" | ([0-9] [0-9]*)))"
+        );
+        assert!(primitive.parse_to_primitive("v10.2").is_ok());
+
+        primitive.pattern("a.b").unwrap();
+        assert_eq!(
+            primitive.grammar_inner(),
+            "root ::= \" \"? (\"a\" [^\\n] \"b\")"
+        );
+        primitive.pattern(r"[\w.-]{2}").unwrap();
+        assert_eq!(
+            primitive.grammar_inner(),
+            "root ::= \" \"? ([\\x2D-.0-9A-Z_a-z] [\\x2D-.0-9A-Z_a-z])"
+        );
+
+        assert!(RegexPrimitive::new(r"\bword\b").is_err());
+        assert!(RegexPrimitive::new(r"(a)\1").is_err());
+        assert!(RegexPrimitive::new(r"a(?=b)").is_err());
+
+        let primitive = RegexPrimitive::default();
+        assert_eq!(primitive.grammar_inner(), "root ::= \"\"");
+        assert!(primitive.parse_to_primitive("").is_err());
+    }
+}
@@ -0,0 +1,356 @@
+use super::PrimitiveTrait;
+use crate::workflows::reason::ReasonTrait;
+use anyhow::Result;
+use llm_utils::grammar::Grammar;
+
+/// An integer that can be negative, such as a delta or a change in rank.
+///
+/// The grammar only allows whole numbers within the bounds. Values outside them fail to parse.
+/// As a reason primitive, the result index is the offset from the lower bound, so decisions vote on exact values.
+#[derive(Debug, Clone)]
+pub struct SignedIntegerPrimitive {
+    pub lower_bound: i32,
+    pub upper_bound: i32,
+}
+
+impl Default for SignedIntegerPrimitive {
+    fn default() -> Self {
+        SignedIntegerPrimitive {
+            lower_bound: -9999,
+            upper_bound: 9999,
+        }
+    }
+}
+
+impl SignedIntegerPrimitive {
+    /// Set the lower bound of the integer range. Default is -9999.
+    pub fn lower_bound(&mut self, lower_bound: i32) -> &mut Self {
+        self.lower_bound = lower_bound;
+        self
+    }
+
+    /// Set the upper bound of the integer range. Default is 9999.
+    pub fn upper_bound(&mut self, upper_bound: i32) -> &mut Self {
+        self.upper_bound = upper_bound;
+        self
+    }
+
+    fn grammar_inner(&self) -> String {
+        range_rules(self.lower_bound as i64, self.upper_bound as i64, 0).join("\n")
+    }
+}
+
+impl PrimitiveTrait for SignedIntegerPrimitive {
+    type PrimitiveResult = i32;
+
+    fn clear_primitive(&mut self) {}
+
+    fn type_description(&self, result_can_be_none: bool) -> &str {
+        if result_can_be_none {
+            "number or 'Unknown.'"
+        } else {
+            "number"
+        }
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        if result_can_be_none {
+            format!(
+                "a whole number from {} to {} or, if the solution is unknown or not in range, 'Unknown.'",
+                self.lower_bound, self.upper_bound
+            )
+        } else {
+            format!(
+                "a whole number from {} to {}",
+                self.lower_bound, self.upper_bound
+            )
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("Unknown.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::custom()
+            .custom_grammar(self.grammar_inner())
+            .wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        let content = content.trim().trim_end_matches('.');
+        let parsed: i64 = content
+            .parse()
+            .map_err(|e| anyhow::format_err!("Failed to parse '{content}' as an integer: {e}"))?;
+        if parsed < self.lower_bound as i64 || parsed > self.upper_bound as i64 {
+            return Err(anyhow::format_err!(
+                "{parsed} is not between {} and {}",
+                self.lower_bound,
+                self.upper_bound
+            ));
+        }
+        Ok(parsed as i32)
+    }
+}
+
+impl ReasonTrait for SignedIntegerPrimitive {
+    fn primitive_to_result_index(&self, content: &str) -> Result<u32> {
+        let output = self.parse_to_primitive(content)?;
+        Ok((output as i64 - self.lower_bound as i64) as u32)
+    }
+
+    fn result_index_to_primitive(&self, result_index: Option<u32>) -> Result<Option<i32>> {
+        if let Some(result_index) = result_index {
+            Ok(Some((self.lower_bound as i64 + result_index as i64) as i32))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn result_to_string(&self, result: Self::PrimitiveResult) -> String {
+        result.to_string()
+    }
+}
+
+/// Rules `root`, and `neg` or `pos`, for the numbers from `lower` to `upper`, counted in steps of `10^-decimal_places`.
+/// The grammar allows up to `decimal_places` digits after the point and no leading zeros, so every number it allows is in range.
+pub(super) fn range_rules(lower: i64, upper: i64, decimal_places: u32) -> Vec<String> {
+    let mut signs = Vec::new();
+    let mut rules = Vec::new();
+    // Negative numbers are a `-` before their magnitude. `-0` is left to the positive side.
+    if lower < 0 {
+        let magnitude_lower = upper.saturating_neg().max(1);
+        if magnitude_lower.unsigned_abs() <= lower.unsigned_abs() {
+            signs.push("\"-\" neg");
+            rules.push(format!(
+                "neg ::= {}",
+                magnitude_rule(magnitude_lower as u64, lower.unsigned_abs(), decimal_places)
+            ));
+        }
+    }
+    if upper >= 0 {
+        signs.push("pos");
+        rules.push(format!(
+            "pos ::= {}",
+            magnitude_rule(lower.max(0) as u64, upper as u64, decimal_places)
+        ));
+    }
+    rules.insert(0, format!("root ::= \" \"? {}", group(&signs.join(" | "))));
+    rules
+}
+
+/// Magnitudes from `lower` to `upper`, in steps of `10^-decimal_places`.
+fn magnitude_rule(lower: u64, upper: u64, decimal_places: u32) -> String {
+    if decimal_places == 0 {
+        return integer_range(lower, upper);
+    }
+    let scale = 10u64.pow(decimal_places);
+    let (lower_int, lower_frac) = (lower / scale, lower % scale);
+    let (upper_int, upper_frac) = (upper / scale, upper % scale);
+    let fraction = |lower_frac: u64, upper_frac: u64| {
+        let digits = digit_range(
+            &padded_digits(lower_frac, decimal_places),
+            &padded_digits(upper_frac, decimal_places),
+            true,
+        );
+        // A number without a fraction has a fraction of zero.
+        if lower_frac == 0 {
+            format!("(\".\" {digits})?")
+        } else {
+            format!("\".\" {digits}")
+        }
+    };
+    if lower_int == upper_int {
+        return format!(
+            "{} {}",
+            integer_range(lower_int, lower_int),
+            fraction(lower_frac, upper_frac)
+        );
+    }
+    let mut alternatives = Vec::new();
+    // Whole numbers whose every fraction is in range share a single alternative.
+    let mut middle_lower = lower_int;
+    if lower_frac > 0 {
+        alternatives.push(format!(
+            "{} {}",
+            integer_range(lower_int, lower_int),
+            fraction(lower_frac, scale - 1)
+        ));
+        middle_lower += 1;
+    }
+    let middle_upper = if upper_frac < scale - 1 {
+        upper_int.checked_sub(1)
+    } else {
+        Some(upper_int)
+    };
+    if let Some(middle_upper) = middle_upper.filter(|middle_upper| middle_lower <= *middle_upper) {
+        alternatives.push(format!(
+            "{} {}",
+            group(&integer_range(middle_lower, middle_upper)),
+            fraction(0, scale - 1)
+        ));
+    }
+    if upper_frac < scale - 1 {
+        alternatives.push(format!(
+            "{} {}",
+            integer_range(upper_int, upper_int),
+            fraction(0, upper_frac)
+        ));
+    }
+    alternatives.join(" | ")
+}
+
+/// Whole numbers from `lower` to `upper`, without leading zeros.
+fn integer_range(lower: u64, upper: u64) -> String {
+    let mut alternatives = Vec::new();
+    for length in digit_count(lower)..=digit_count(upper) {
+        let shortest = if length == 1 {
+            0
+        } else {
+            10u64.pow(length - 1)
+        };
+        let longest = 10u64.pow(length) - 1;
+        alternatives.push(digit_range(
+            &padded_digits(lower.max(shortest), length),
+            &padded_digits(upper.min(longest), length),
+            false,
+        ));
+    }
+    alternatives.join(" | ")
+}
+
+/// Digit strings from `lower` to `upper`, which have the same length.
+/// With `trailing_optional`, digits after the first can be left off, as in a fraction where a missing digit is a zero.
+fn digit_range(lower: &[u8], upper: &[u8], trailing_optional: bool) -> String {
+    digits_between(lower, upper, true, true, trailing_optional, true)
+}
+
+fn digits_between(
+    lower: &[u8],
+    upper: &[u8],
+    mut at_lower: bool,
+    mut at_upper: bool,
+    trailing_optional: bool,
+    first: bool,
+) -> String {
+    if lower.is_empty() {
+        return String::new();
+    }
+    // Bounds of all zeros or all nines don't limit the remaining digits.
+    at_lower &= lower.iter().any(|digit| *digit != 0);
+    at_upper &= upper.iter().any(|digit| *digit != 9);
+    let optional = trailing_optional && !first && !at_lower;
+    if !at_lower && !at_upper {
+        let rest = if trailing_optional { "[0-9]?" } else { "[0-9]" };
+        let first_digit = if optional { "[0-9]?" } else { "[0-9]" };
+        return std::iter::once(first_digit)
+            .chain(std::iter::repeat(rest).take(lower.len() - 1))
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+    let low = if at_lower { lower[0] } else { 0 };
+    let high = if at_upper { upper[0] } else { 9 };
+    let rest = |at_lower: bool, at_upper: bool| {
+        digits_between(
+            &lower[1..],
+            &upper[1..],
+            at_lower,
+            at_upper,
+            trailing_optional,
+            false,
+        )
+    };
+    // The first digit only limits the rest at a bound that isn't all zeros or nines after it.
+    let lower_edge = at_lower && lower[1..].iter().any(|digit| *digit != 0);
+    let upper_edge = at_upper && upper[1..].iter().any(|digit| *digit != 9);
+    let mut alternatives = Vec::new();
+    if low == high {
+        alternatives.push(sequence(digit_class(low, low), rest(at_lower, at_upper)));
+    } else {
+        if lower_edge {
+            alternatives.push(sequence(digit_class(low, low), rest(true, false)));
+        }
+        let (middle_low, middle_high) = (low + u8::from(lower_edge), high - u8::from(upper_edge));
+        if middle_low <= middle_high {
+            alternatives.push(sequence(
+                digit_class(middle_low, middle_high),
+                rest(false, false),
+            ));
+        }
+        if upper_edge {
+            alternatives.push(sequence(digit_class(high, high), rest(false, true)));
+        }
+    }
+    let alternatives = alternatives.join(" | ");
+    if !optional {
+        group(&alternatives)
+    } else if alternatives.contains(' ') {
+        format!("({alternatives})?")
+    } else {
+        format!("{alternatives}?")
+    }
+}
+
+fn sequence(digit: String, rest: String) -> String {
+    if rest.is_empty() {
+        digit
+    } else {
+        format!("{digit} {rest}")
+    }
+}
+
+fn digit_class(low: u8, high: u8) -> String {
+    if low == high {
+        format!("[{low}]")
+    } else {
+        format!("[{low}-{high}]")
+    }
+}
+
+/// Wraps alternatives in parentheses, so they can be followed by more of a sequence.
+fn group(alternatives: &str) -> String {
+    if alternatives.contains(" | ") {
+        format!("({alternatives})")
+    } else {
+        alternatives.to_owned()
+    }
+}
+
+fn padded_digits(value: u64, length: u32) -> Vec<u8> {
+    format!("{value:0>width$}", width = length as usize)
+        .bytes()
+        .map(|byte| byte - b'0')
+        .collect()
+}
+
+fn digit_count(value: u64) -> u32 {
+    value.checked_ilog10().unwrap_or(0) + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_integer() {
+        let mut primitive = SignedIntegerPrimitive::default();
+        primitive.lower_bound(-10).upper_bound(5);
+        assert_eq!(
+            primitive.grammar_inner(),
+            "root ::= \" \"? (\"-\" neg | pos)\nneg ::= [1-9] | [1] [0]\npos ::= [0-5]"
+        );
+        assert_eq!(primitive.parse_to_primitive(" -7.").unwrap(), -7);
+        assert!(primitive.parse_to_primitive("42").is_err());
+        assert!(primitive.parse_to_primitive("-11").is_err());
+        assert!(primitive.parse_to_primitive("seven").is_err());
+        assert_eq!(primitive.primitive_to_result_index("-10").unwrap(), 0);
+        assert_eq!(
+            primitive.result_index_to_primitive(Some(13)).unwrap(),
+            Some(3)
+        );
+    }
+}
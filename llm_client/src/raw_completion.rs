@@ -0,0 +1,75 @@
+use llm_interface::{
+    llms::LlmBackend,
+    requests::{
+        completion::{CompletionRequest, CompletionResponse, RawPrompt},
+        constraints::logit_bias::{LogitBias, LogitBiasTrait},
+        req_components::{RequestConfig, RequestConfigTrait},
+    },
+};
+
+/// Text completion without a chat template, for base models and fill-in-the-middle code models.
+/// Uses llama.cpp's `/completion` and `/infill` endpoints, and the `/completions` endpoint of OpenAI compatible APIs.
+#[derive(Clone)]
+pub struct RawCompletion {
+    pub base_req: CompletionRequest,
+}
+
+impl RawCompletion {
+    pub fn new(backend: std::sync::Arc<LlmBackend>) -> Self {
+        Self {
+            base_req: CompletionRequest::new(backend),
+        }
+    }
+
+    /// Sets the prompt text, sent as-is.
+    pub fn prompt<T: AsRef<str>>(&mut self, text: T) -> &mut Self {
+        self.base_req.raw_prompt = Some(RawPrompt::Text(text.as_ref().to_owned()));
+        self
+    }
+
+    /// Sets the prompt as token ids from the backend's tokenizer.
+    pub fn prompt_tokens(&mut self, tokens: Vec<u32>) -> &mut Self {
+        self.base_req.raw_prompt = Some(RawPrompt::Tokens(tokens));
+        self
+    }
+
+    /// Sets a fill-in-the-middle prompt. The response is the text between `prefix` and `suffix`.
+    pub fn infill<T: AsRef<str>>(&mut self, prefix: T, suffix: T) -> &mut Self {
+        self.base_req.raw_prompt = Some(RawPrompt::Infill {
+            prefix: prefix.as_ref().to_owned(),
+            suffix: suffix.as_ref().to_owned(),
+        });
+        self
+    }
+
+    /// Renders the prompt as it would be sent to the backend, without sending it.
+    pub fn dry_run(&self) -> crate::Result<String> {
+        self.base_req.render_prompt()
+    }
+
+    /// The response content is returned untrimmed, as whitespace is often significant for base and code models.
+    pub async fn run(&mut self) -> crate::Result<CompletionResponse> {
+        if self.base_req.raw_prompt.is_none() {
+            crate::bail!(
+                "RawCompletion requires a prompt. Set one with prompt, prompt_tokens or infill."
+            );
+        }
+        Ok(self.base_req.request().await?)
+    }
+}
+
+impl RequestConfigTrait for RawCompletion {
+    fn config(&mut self) -> &mut RequestConfig {
+        &mut self.base_req.config
+    }
+
+    fn reset_request(&mut self) {
+        self.base_req.reset_completion_request();
+    }
+}
+
+impl LogitBiasTrait for RawCompletion {
+    fn lb_mut(&mut self) -> &mut Option<LogitBias> {
+        &mut self.base_req.logit_bias
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     components::{
-        cascade::{step::StepConfig, CascadeFlow},
+        cascade::{grammar_emulation::GrammarEmulation, step::StepConfig, CascadeFlow},
         instruct_prompt::InstructPrompt,
         InstructPromptTrait,
     },
@@ -19,6 +19,8 @@ pub struct BasicPrimitiveWorkflow<P> {
     pub base_req: CompletionRequest,
     pub result_can_be_none: bool,
     pub instruct_prompt: InstructPrompt,
+    /// Used in place of the primitive's grammar on backends without native grammar support.
+    pub grammar_emulation: GrammarEmulation,
 }
 
 impl<P: PrimitiveTrait> BasicPrimitiveWorkflow<P> {
@@ -28,27 +30,21 @@ impl<P: PrimitiveTrait> BasicPrimitiveWorkflow<P> {
             base_req: CompletionRequest::new(backend),
             result_can_be_none: false,
             instruct_prompt: InstructPrompt::default(),
+            grammar_emulation: GrammarEmulation::default(),
         }
     }
 
     pub async fn return_primitive(&mut self) -> crate::Result<P::PrimitiveResult> {
         self.result_can_be_none = false;
-        let res = self.return_result().await?;
-        if let Some(primitive_result) = res.primitive_result {
-            Ok(self.primitive.parse_to_primitive(&primitive_result)?)
-        } else {
-            Err(anyhow::format_err!("No result returned."))
+        match self.run_and_parse().await? {
+            Some(primitive_result) => Ok(primitive_result),
+            None => Err(anyhow::format_err!("No result returned.")),
         }
     }
 
     pub async fn return_optional_primitive(&mut self) -> crate::Result<Option<P::PrimitiveResult>> {
         self.result_can_be_none = true;
-        let res = self.return_result().await?;
-        if let Some(primitive_result) = res.primitive_result {
-            Ok(Some(self.primitive.parse_to_primitive(&primitive_result)?))
-        } else {
-            Ok(None)
-        }
+        self.run_and_parse().await
     }
 
     pub async fn return_result(&mut self) -> crate::Result<BasicPrimitiveResult> {
@@ -65,6 +61,35 @@ impl<P: PrimitiveTrait> BasicPrimitiveWorkflow<P> {
         BasicPrimitiveResult::new(flow)
     }
 
+    /// Runs the flow until the result parses. A result that passes the grammar can still fail to parse,
+    /// as with a date that doesn't exist, so each failure counts against `retry_after_fail_n_times`.
+    async fn run_and_parse(&mut self) -> crate::Result<Option<P::PrimitiveResult>> {
+        let mut failed_attempts: u8 = 0;
+        loop {
+            let mut flow = self.basic_primitive()?;
+            flow.run_all_rounds(&mut self.base_req).await?;
+            let Some(primitive_result) = flow.primitive_result() else {
+                return Ok(None);
+            };
+            match self.primitive.parse_to_primitive(&primitive_result) {
+                Ok(primitive_result) => return Ok(Some(primitive_result)),
+                Err(e) => {
+                    failed_attempts += 1;
+                    if failed_attempts >= self.base_req.config.retry_after_fail_n_times {
+                        return Err(e);
+                    }
+                    crate::warn!(failed_attempts, error = %e, "Basic primitive result failed to parse");
+                }
+            }
+        }
+    }
+
+    /// Sets how many times to re-prompt backends without native grammar support when the response fails validation.
+    pub fn grammar_retries(&mut self, max_retries: u8) -> &mut Self {
+        self.grammar_emulation.max_retries = max_retries;
+        self
+    }
+
     fn basic_primitive(&mut self) -> crate::Result<CascadeFlow> {
         let mut flow = CascadeFlow::new("BasicPrimitive");
         let task = self.instruct_prompt.build_instruct_prompt(false)?;
@@ -78,6 +103,7 @@ impl<P: PrimitiveTrait> BasicPrimitiveWorkflow<P> {
                 .primitive
                 .stop_word_result_is_none(self.result_can_be_none),
             grammar: self.primitive.grammar(),
+            grammar_emulation: self.grammar_emulation.clone(),
             ..StepConfig::default()
         };
 
@@ -116,13 +142,33 @@ impl BasicPrimitiveWorkflowBuilder {
     }
 
     fn build<P: PrimitiveTrait>(self) -> BasicPrimitiveWorkflow<P> {
+        self.build_with(P::default())
+    }
+
+    fn build_with<P: PrimitiveTrait>(self, primitive: P) -> BasicPrimitiveWorkflow<P> {
         BasicPrimitiveWorkflow {
-            primitive: P::default(),
+            primitive,
             base_req: self.base_req,
             result_can_be_none: false,
             instruct_prompt: InstructPrompt::default(),
+            grammar_emulation: GrammarEmulation::default(),
         }
     }
+
+    /// A list of `P` results, as in `list::<IntegerPrimitive>()`. The item primitive is at `primitive.item`.
+    pub fn list<P: PrimitiveTrait>(self) -> BasicPrimitiveWorkflow<ListPrimitive<P>> {
+        self.build()
+    }
+
+    /// A type implementing [`LlmPrimitive`], usually with `#[derive(LlmPrimitive)]`.
+    pub fn derived<T: LlmPrimitive>(self) -> BasicPrimitiveWorkflow<DerivedPrimitive<T>> {
+        self.build()
+    }
+
+    /// A string matching `pattern`. Errors if the regex is invalid or uses constructs the grammar can't express.
+    pub fn regex(self, pattern: &str) -> crate::Result<BasicPrimitiveWorkflow<RegexPrimitive>> {
+        Ok(self.build_with(RegexPrimitive::new(pattern)?))
+    }
 }
 
 macro_rules! basic_primitive_workflow_primitive_impl {
@@ -142,7 +188,13 @@ basic_primitive_workflow_primitive_impl! {
     integer => IntegerPrimitive,
     sentences => SentencesPrimitive,
     words => WordsPrimitive,
-    exact_string => ExactStringPrimitive
+    exact_string => ExactStringPrimitive,
+    multi_choice => MultiChoicePrimitive,
+    float => FloatPrimitive,
+    signed_integer => SignedIntegerPrimitive,
+    date => DatePrimitive,
+    date_time => DateTimePrimitive,
+    duration => DurationPrimitive
 }
 
 #[derive(Clone)]
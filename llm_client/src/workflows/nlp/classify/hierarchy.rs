@@ -1,113 +1,194 @@
-use std::collections::HashMap;
+use std::path::Path;
 
-use llm_utils::grammar::Grammar;
-
-use crate::{primitives::ExactStringPrimitive, PrimitiveTrait};
-
-#[derive(Debug, Clone)]
-struct TagCollection {
-    tags: HashMap<String, Tag>,
-    tag_grammar: ExactStringPrimitive,
+/// A taxonomy of nested tags. Classification walks it from the root tags down to a leaf.
+///
+/// Tags keep the order they were added in, which is the order they're offered to the model.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagSystem {
+    pub tags: Vec<Tag>,
 }
 
-impl TagCollection {
-    fn new() -> Self {
-        Self {
-            tags: HashMap::new(),
-            tag_grammar: ExactStringPrimitive::default(),
-        }
+impl TagSystem {
+    pub fn new() -> Self {
+        Self::default()
     }
+
+    /// One tag path per line, with levels separated by `:`, as in `aquatic:fresh water:lake`.
+    /// Parent tags are created as needed. Empty lines and lines starting with `#` are skipped.
     pub fn create_from_string(input: &str) -> Self {
-        let mut tags = HashMap::new();
-        let mut tag_grammar = ExactStringPrimitive::default();
+        let mut system = Self::new();
         for line in input.lines() {
-            let parts: Vec<&str> = line.split(':').collect();
-            let mut current_tag = Tag::new(&parts[0]);
-
-            for &part in &parts[1..] {
-                current_tag.add_child_tag(part);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut tags = &mut system.tags;
+            for name in line.split(':') {
+                if name.trim().is_empty() {
+                    break;
+                }
+                tags = &mut add_tag(tags, name).children;
             }
-            tag_grammar.add_string_to_allowed(current_tag.name.clone());
-            tags.insert(current_tag.name.clone(), current_tag);
         }
-
-        Self { tags, tag_grammar }
+        system
     }
 
-    fn add_tag(&mut self, name: &str) -> &mut Tag {
-        let name = name.trim().to_lowercase();
-        self.tag_grammar.add_string_to_allowed(&name);
-        self.tags
-            .entry(name.to_owned())
-            .or_insert_with(|| Tag::new(&name))
+    /// Nested JSON objects, where keys are tag names. A value can be an object of child tags, an array of child
+    /// tag names or objects, a string describing a leaf tag, or `null` for a leaf tag.
+    /// Object keys are read in serde_json's map order, which is sorted by default, so use arrays where order matters.
+    ///
+    /// ```json
+    /// {"aquatic": {"fresh water": ["lake", "river"], "marine": "Oceans and seas."}, "terrestrial": null}
+    /// ```
+    pub fn create_from_json(input: &str) -> crate::Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(input)?;
+        let mut system = Self::new();
+        add_json_children(&mut system.tags, &value)?;
+        Ok(system)
     }
 
-    fn remove_tag(&mut self, name: &str) -> crate::Result<()> {
-        match self.tags.remove(name) {
-            Some(_) => (),
-            None => crate::bail!("Tag not found."),
+    /// Reads a taxonomy file. Files ending in `.json` are parsed with `create_from_json`, others with `create_from_string`.
+    pub fn create_from_file<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let input = std::fs::read_to_string(path)
+            .map_err(|e| crate::anyhow!("Failed to read tags {}: {e}", path.display()))?;
+        if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            Self::create_from_json(&input)
+        } else {
+            Ok(Self::create_from_string(&input))
         }
-        self.tag_grammar.remove_string_from_allowed(&name);
-        Ok(())
     }
 
-    fn get_tag(&self, name: &str) -> Option<&Tag> {
-        self.tags.get(name)
+    pub fn add_tag(&mut self, name: &str) -> &mut Tag {
+        add_tag(&mut self.tags, name)
+    }
+
+    pub fn remove_tag(&mut self, name: &str) -> crate::Result<()> {
+        remove_tag(&mut self.tags, name)
+    }
+
+    pub fn get_tag(&self, name: &str) -> Option<&Tag> {
+        self.tags.iter().find(|tag| tag.name == name)
     }
 
-    fn get_tags(&self) -> Vec<&Tag> {
-        self.tags.values().collect()
+    /// Follows a path of tag names from the root, as in `["aquatic", "fresh water"]`.
+    pub fn get_tag_by_path<T: AsRef<str>>(&self, path: &[T]) -> Option<&Tag> {
+        let (first, rest) = path.split_first()?;
+        let mut tag = self.get_tag(first.as_ref())?;
+        for name in rest {
+            tag = tag.get_child_tag(name.as_ref())?;
+        }
+        Some(tag)
     }
 
-    fn get_tag_names(&self) -> Vec<&str> {
-        self.tags.keys().map(|s| s.as_str()).collect()
+    pub fn get_tag_names(&self) -> Vec<&str> {
+        self.tags.iter().map(|tag| tag.name.as_str()).collect()
     }
 
-    fn grammar(&self) -> Grammar {
-        self.tag_grammar.grammar()
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Tag {
     pub name: String,
-    tags: TagCollection,
+    /// Shown to the model next to the tag name.
+    pub description: Option<String>,
+    pub children: Vec<Tag>,
 }
 
 impl Tag {
-    fn new(name: &str) -> Self {
+    pub fn new(name: &str) -> Self {
         Tag {
-            name: name.trim().to_lowercase(),
-            tags: TagCollection::new(),
+            name: name.trim().to_owned(),
+            description: None,
+            children: Vec::new(),
         }
     }
 
-    fn add_child_tag(&mut self, name: &str) -> &mut Self {
-        self.tags.add_tag(name)
+    pub fn description(&mut self, description: &str) -> &mut Self {
+        self.description = Some(description.trim().to_owned());
+        self
+    }
+
+    pub fn add_child_tag(&mut self, name: &str) -> &mut Tag {
+        add_tag(&mut self.children, name)
     }
 
     pub fn remove_child_tag(&mut self, name: &str) -> crate::Result<()> {
-        self.tags.remove_tag(name)
+        remove_tag(&mut self.children, name)
     }
 
-    pub fn get_child_tags(&self) -> Vec<&Tag> {
-        self.tags.get_tags()
+    pub fn get_child_tag(&self, name: &str) -> Option<&Tag> {
+        self.children.iter().find(|tag| tag.name == name)
     }
 
     pub fn get_child_tag_names(&self) -> Vec<&str> {
-        self.tags.get_tag_names()
+        self.children.iter().map(|tag| tag.name.as_str()).collect()
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty()
     }
+}
+
+fn add_tag<'a>(tags: &'a mut Vec<Tag>, name: &str) -> &'a mut Tag {
+    let name = name.trim();
+    match tags.iter().position(|tag| tag.name == name) {
+        Some(index) => &mut tags[index],
+        None => {
+            tags.push(Tag::new(name));
+            tags.last_mut().unwrap()
+        }
+    }
+}
+
+fn remove_tag(tags: &mut Vec<Tag>, name: &str) -> crate::Result<()> {
+    match tags.iter().position(|tag| tag.name == name) {
+        Some(index) => {
+            tags.remove(index);
+            Ok(())
+        }
+        None => crate::bail!("Tag not found: {name}"),
+    }
+}
 
-    pub fn grammar(&self) -> Grammar {
-        self.tags.grammar()
+fn add_json_children(tags: &mut Vec<Tag>, value: &serde_json::Value) -> crate::Result<()> {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (name, children) in map {
+                let tag = add_tag(tags, name);
+                match children {
+                    serde_json::Value::String(description) => {
+                        tag.description(description);
+                    }
+                    serde_json::Value::Null => (),
+                    children => add_json_children(&mut tag.children, children)?,
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                match item {
+                    serde_json::Value::String(name) => {
+                        add_tag(tags, name);
+                    }
+                    serde_json::Value::Object(_) => add_json_children(tags, item)?,
+                    _ => crate::bail!("Tag arrays may only contain tag names or objects: {item}"),
+                }
+            }
+        }
+        _ => crate::bail!("Expected an object or array of tags: {value}"),
     }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn create_sample_tag_collection() -> TagCollection {
+    fn create_sample_tag_system() -> TagSystem {
         let input = "\
 terrestrial
 terrestrial:soil
@@ -122,12 +203,12 @@ tract:mouth
 salinity:low
 salinity
 age group:infant";
-        TagCollection::create_from_string(input)
+        TagSystem::create_from_string(input)
     }
 
     #[test]
-    fn test_tag_collection_creation() {
-        let tags = create_sample_tag_collection();
+    fn test_tag_system_creation() {
+        let tags = create_sample_tag_system();
 
         // Test root tags
         assert!(tags.get_tag("terrestrial").is_some());
@@ -143,12 +224,12 @@ age group:infant";
         assert_eq!(tags.get_tag("terrestrial").unwrap().name, "terrestrial");
 
         // Test number of root tags
-        assert_eq!(tags.get_tags().len(), 7);
+        assert_eq!(tags.tags.len(), 8);
     }
 
     #[test]
     fn test_child_tags() {
-        let tags = create_sample_tag_collection();
+        let tags = create_sample_tag_system();
 
         // Test child tags
         let terrestrial = tags.get_tag("terrestrial").unwrap();
@@ -157,7 +238,25 @@ age group:infant";
         let aquatic = tags.get_tag("aquatic").unwrap();
         assert_eq!(aquatic.get_child_tag_names(), vec!["fresh water"]);
 
-        let fresh_water = aquatic.tags.get_tag("fresh water").unwrap();
-        assert_eq!(fresh_water.get_child_tag_names(), vec!["lake"]);
+        let lake = tags
+            .get_tag_by_path(&["aquatic", "fresh water", "lake"])
+            .unwrap();
+        assert!(lake.is_leaf());
+    }
+
+    #[test]
+    fn test_tag_system_from_json() {
+        let tags = TagSystem::create_from_json(
+            r#"{"aquatic": {"fresh water": ["lake", {"river": "Flowing fresh water."}], "marine": null}, "terrestrial": ["soil"]}"#,
+        )
+        .unwrap();
+        assert_eq!(tags.get_tag_names(), vec!["aquatic", "terrestrial"]);
+        let fresh_water = tags.get_tag_by_path(&["aquatic", "fresh water"]).unwrap();
+        assert_eq!(fresh_water.get_child_tag_names(), vec!["lake", "river"]);
+        assert_eq!(
+            fresh_water.get_child_tag("river").unwrap().description,
+            Some("Flowing fresh water.".to_owned())
+        );
+        assert!(TagSystem::create_from_json(r#"{"aquatic": [1]}"#).is_err());
     }
 }
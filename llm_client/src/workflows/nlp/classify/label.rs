@@ -1,139 +1,144 @@
-use crate::components::cascade::CascadeFlow;
-use crate::{components::cascade::step::StepConfig, primitives::*};
-use hierarchy::TagSystem;
+use super::hierarchy::{Tag, TagSystem};
+use crate::{
+    components::InstructPromptTrait,
+    primitives::*,
+    workflows::reason::{
+        decision::{DecisionResult, DecisionTrait},
+        ReasonWorkflowBuilder,
+    },
+};
 use llm_interface::requests::{
     completion::CompletionRequest,
     req_components::{RequestConfig, RequestConfigTrait},
 };
-pub mod entity;
-pub mod hierarchy;
-
 
+/// Labels text with a path through a [`TagSystem`], from a root tag down towards a leaf.
+///
+/// Each level is a `Decision` between the child tags of the previous level's winner, constrained with an
+/// `ExactStringPrimitive`. The walk stops at a leaf, when the model picks 'None of the above.',
+/// or when a level's confidence is below `min_confidence`.
 pub struct LabelEntity {
     pub base_req: CompletionRequest,
-    pub entity: ClassifyEntity,
+    pub content: String,
     pub tags: TagSystem,
+    /// Votes per level. Defaults to 3.
+    pub best_of_n_votes: u8,
+    /// Stops the walk when the winning tag's share of the votes is lower than this. Defaults to `None`.
+    pub min_confidence: Option<f32>,
+    /// Allows 'None of the above.' at the root level. Below the root it's always allowed, so the walk can stop early.
+    pub root_can_be_none: bool,
 }
 
 impl LabelEntity {
     pub fn new(base_req: CompletionRequest, content: &str, tags: &TagSystem) -> Self {
         Self {
             base_req,
-            entity: ClassifyEntity::new(content),
+            content: content.to_owned(),
             tags: tags.clone(),
+            best_of_n_votes: 3,
+            min_confidence: None,
+            root_can_be_none: false,
         }
     }
 
-    pub async fn run(mut self) -> crate::Result<ClassifyEntity> {
-        let flow = self.run_backend().await?;
-        println!("{}", flow);
-        self.entity.duration = flow.duration;
-        Ok(self.entity.clone())
+    pub fn best_of_n_votes(&mut self, best_of_n_votes: u8) -> &mut Self {
+        self.best_of_n_votes = best_of_n_votes;
+        self
     }
 
-    async fn run_backend(&mut self) -> crate::Result<CascadeFlow> {
-        self.run_cascade().await
+    pub fn min_confidence(&mut self, min_confidence: f32) -> &mut Self {
+        self.min_confidence = Some(min_confidence);
+        self
     }
 
-    fn build_tag_list(&self) -> String {
-        let mut list = String::new();
-        for name in self.tags.get_parent_tag_names() {
-            if list.is_empty() {
-                list.push('"');
-                list.push_str(name);
-            } else {
-                list.push_str(", ");
-                list.push('"');
-                list.push_str(name);
-            }
-            list.push('"');
-        }
-        list
+    pub fn root_can_be_none(&mut self, root_can_be_none: bool) -> &mut Self {
+        self.root_can_be_none = root_can_be_none;
+        self
     }
 
-    async fn run_cascade(&mut self) -> crate::Result<CascadeFlow> {
-        let mut flow = CascadeFlow::new("LabelEntity");
-        flow.open_cascade();
-
-        flow.new_round(
-            format!("In the following text, identify the primary entity that serves as the main focus. This entity should be a concrete, tangible entity, such as a physical object, creature, or any specific item mentioned.\nGuidelines:\nIdentify the Single Primary Entity: Determine the most significant concrete entity that the text is mainly about.\nFocus on Concrete Entities Only: The primary entity should be tangible and physically existing (e.g., a thing, object, noun).\nIgnore Descriptive Attributes: disambiguate by ignoring adjective and prepositions.\nConsider Nested Entities: Be attentive if the primary entity includes or is part of another entity.\nAfter analyzing the text, state your answer as: \"The primary entity in the text is:\": ... \nThe text is: '{}'", self.entity.content)
-        );
-
-        let step_config = StepConfig {
-            step_prefix: Some(
-                "Thinking out loud about the entity discussed in the text...".to_owned(),
-            ),
-            stop_word_done: "The primary entity in the text is".to_owned(),
-            grammar: TextPrimitive::default().text_token_length(200).grammar(),
-            ..StepConfig::default()
-        };
-        flow.last_round()?.add_inference_step(&step_config);
-
-        let step_config = StepConfig {
-            step_prefix: Some("The primary entity in the text is:".to_owned()),
-            grammar: TextPrimitive::default().text_token_length(10).grammar(),
-            ..StepConfig::default()
-        };
-        flow.last_round()?.add_inference_step(&step_config);
-        flow.last_round()?.run_all_steps(&mut self.base_req).await?;
-        match flow.primitive_result() {
-            Some(entity_name) => {
-                self.entity.entity = Some(entity_name);
-            }
-            None => {
-                crate::bail!("No entity identified.");
-            }
-        };
-
-        flow.new_round(
-                format!("Pontificate about the previously identified entity and its relation to one or more of the listed categories.\nDiscuss which category or categories most accurately and comprehensively describe the entity.\nWhen talking about multiple categories, go in order of relevance, with the most applicable category first.\nIf none of the categories are relevant, state \"None of the above.\"\nCategories: {}.", self.build_tag_list())
-            );
-        let step_config = StepConfig {
-            step_prefix: Some(
-                "Thinking about the entity and and how it might be classified...".to_owned(),
-            ),
-            grammar: TextPrimitive::default().text_token_length(200).grammar(),
-            ..StepConfig::default()
-        };
-
-        flow.last_round()?.add_inference_step(&step_config);
-        flow.last_round()?.open_round(&mut self.base_req)?;
-        flow.last_round()?.run_next_step(&mut self.base_req).await?;
-
-        for i in 1..=self.tags.get_parent_tag_names().len() {
-            let step_config = if i == 1 {
-                StepConfig {
-                    step_prefix: Some("The best classification is:".to_owned()),
-                    stop_word_no_result: Some("None of the above.".to_owned()),
-                    grammar: self.tags.grammar(),
-                    ..StepConfig::default()
-                }
-            } else {
-                StepConfig {
-                    step_prefix: Some("The next most accurate classification is:".to_owned()),
-                    stop_word_no_result: Some("None of the above.".to_owned()),
-                    grammar: self.tags.grammar(),
-                    ..StepConfig::default()
-                }
-            };
-
-            flow.last_round()?.add_inference_step(&step_config);
-            flow.last_round()?.run_next_step(&mut self.base_req).await?;
-
-            match flow.primitive_result() {
-                Some(tag_name) => {
-                    self.tags.remove_parent_tag(&tag_name)?;
-                    self.entity.tags.push(tag_name);
-                }
+    pub async fn run(&mut self) -> crate::Result<LabelResult> {
+        if self.tags.is_empty() {
+            crate::bail!("LabelEntity requires at least one tag");
+        }
+        let start = std::time::Instant::now();
+        let mut result = LabelResult::new(&self.content);
+        let mut options = self.tags.tags.clone();
+
+        while !options.is_empty() {
+            let decision_result = self.decide_level(&options, &result.path).await?;
+            let tag = match decision_result.winner_index {
+                Some(index) => options[index as usize].clone(),
                 None => {
+                    result.decisions.push(decision_result);
                     break;
                 }
             };
+            let label = LabelLevel {
+                tag: tag.name.clone(),
+                confidence: decision_result.confidence,
+                winner_votes: decision_result.winner_votes,
+                total_votes: decision_result.total_votes,
+            };
+            result.decisions.push(decision_result);
+            if let Some(min_confidence) = self.min_confidence {
+                if label.confidence < min_confidence {
+                    crate::info!(
+                        "LabelEntity: stopping at {:?}, confidence {} is below {min_confidence}",
+                        label.tag,
+                        label.confidence
+                    );
+                    break;
+                }
+            }
+            result.path.push(label);
+            options = tag.children;
         }
-        flow.last_round()?.close_round(&mut self.base_req)?;
-        flow.close_cascade()?;
 
-        Ok(flow)
+        result.duration = start.elapsed();
+        Ok(result)
+    }
+
+    async fn decide_level(
+        &self,
+        options: &[Tag],
+        path: &[LabelLevel],
+    ) -> crate::Result<DecisionResult> {
+        let mut reason = ReasonWorkflowBuilder {
+            base_req: self.base_req.clone(),
+        }
+        .exact_string();
+        reason
+            .primitive
+            .add_strings_to_allowed(&options.iter().map(|tag| &tag.name).collect::<Vec<_>>());
+
+        let categories = options
+            .iter()
+            .map(|tag| match &tag.description {
+                Some(description) => format!("{}: {description}", tag.name),
+                None => tag.name.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let instructions = if path.is_empty() {
+            format!("Which category best describes the text?\nCategories:\n{categories}")
+        } else {
+            let parents = path
+                .iter()
+                .map(|label| label.tag.as_str())
+                .collect::<Vec<_>>()
+                .join(" > ");
+            format!("The text has been classified as '{parents}'. Which subcategory best describes the text? If none of the subcategories fit, say so.\nSubcategories:\n{categories}")
+        };
+        reason.instructions().set_content(instructions);
+        reason.supporting_material().set_content(&self.content);
+
+        let mut decision = reason.decision();
+        decision.best_of_n_votes(self.best_of_n_votes);
+        if path.is_empty() && !self.root_can_be_none {
+            decision.return_result().await
+        } else {
+            decision.return_optional_result().await
+        }
     }
 }
 
@@ -143,68 +148,79 @@ impl RequestConfigTrait for LabelEntity {
     }
 
     fn reset_request(&mut self) {
-        self.tags = TagSystem::new();
         self.base_req.reset_completion_request();
     }
 }
 
+/// One level of a label path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabelLevel {
+    pub tag: String,
+    /// The winning tag's share of the level's votes.
+    pub confidence: f32,
+    pub winner_votes: u16,
+    pub total_votes: u16,
+}
+
 #[derive(Clone)]
-pub struct ClassifyEntity {
+pub struct LabelResult {
     pub content: String,
-    pub entity: Option<String>,
-    pub tags: Vec<String>,
+    /// The tags from the root level down. Empty if no root tag was chosen.
+    pub path: Vec<LabelLevel>,
+    /// The decision for each level, including the one that ended the walk.
+    pub decisions: Vec<DecisionResult>,
     pub duration: std::time::Duration,
 }
 
-impl ClassifyEntity {
-    pub fn new(content: &str) -> Self {
+impl LabelResult {
+    fn new(content: &str) -> Self {
         Self {
             content: content.to_owned(),
-            entity: None,
-            tags: vec![],
+            path: Vec::new(),
+            decisions: Vec::new(),
             duration: std::time::Duration::default(),
         }
     }
+
+    /// The tag names from the root level down.
+    pub fn labels(&self) -> Vec<&str> {
+        self.path.iter().map(|label| label.tag.as_str()).collect()
+    }
+
+    /// The most specific tag chosen.
+    pub fn leaf(&self) -> Option<&str> {
+        self.path.last().map(|label| label.tag.as_str())
+    }
+
+    /// The product of each level's confidence.
+    pub fn path_confidence(&self) -> f32 {
+        self.path.iter().map(|label| label.confidence).product()
+    }
 }
 
-impl std::fmt::Display for ClassifyEntity {
+impl std::fmt::Display for LabelResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f)?;
-        writeln!(f, "ClassifyEntity:")?;
+        writeln!(f, "LabelEntity:")?;
         crate::i_nln(f, format_args!("content: {}", self.content))?;
-        crate::i_nln(f, format_args!("duration: {:?}", self.duration))?;
-        for tag in &self.tags {
-            crate::i_nln(f, format_args!("tag: {}", tag))?;
+        for (i, label) in self.path.iter().enumerate() {
+            crate::i_nln(
+                f,
+                format_args!(
+                    "level {}: {} ({} of {} votes, confidence {})",
+                    i + 1,
+                    label.tag,
+                    label.winner_votes,
+                    label.total_votes,
+                    label.confidence
+                ),
+            )?;
         }
-        Ok(())
-    }
-}
-
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::LlmClient;
-
-    #[tokio::test]
-    #[ignore]
-    pub async fn test() -> crate::Result<()> {
-        let llm_client = LlmClient::llama_cpp().init().await?;
-        let input = "\
-        terrestrial
-        aquatic
-        host-associated
-        salinity
-        age group
-        space
-        human";
-
-        let tag_system = TagSystem::create_from_string(input);
-
-        let req = llm_client
-            .nlp()
-            .classify("A green turtle on a log in a mountain lake.", &tag_system);
-        let entity = req.run().await?;
-        println!("{}", entity);
+        crate::i_nln(
+            f,
+            format_args!("path confidence: {}", self.path_confidence()),
+        )?;
+        crate::i_nln(f, format_args!("duration: {:?}", self.duration))?;
         Ok(())
     }
 }
@@ -1,6 +1,7 @@
 use llm_interface::requests::completion::CompletionRequest;
 pub mod entity;
-// pub mod hierarchy;
+pub mod hierarchy;
+pub mod label;
 
 pub struct Classify {
     pub base_req: CompletionRequest,
@@ -14,4 +15,9 @@ impl Classify {
     pub fn entity(self, content: &str) -> entity::ClassifyEntity {
         entity::ClassifyEntity::new(self.base_req, content)
     }
+
+    /// Labels the content with a path of tags from the taxonomy, one level at a time.
+    pub fn labels(self, content: &str, tags: &hierarchy::TagSystem) -> label::LabelEntity {
+        label::LabelEntity::new(self.base_req, content, tags)
+    }
 }
@@ -0,0 +1,593 @@
+use crate::{
+    components::{
+        cascade::{step::StepConfig, CascadeFlow},
+        instruct_prompt::{InstructPrompt, InstructPromptTrait},
+    },
+    primitives::*,
+};
+use anyhow::Result;
+use llm_interface::requests::{
+    completion::CompletionRequest,
+    req_components::{RequestConfig, RequestConfigTrait},
+};
+use std::ops::Range;
+
+/// A kind of entity to extract, with optional fields to fill in for each entity found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntitySchema {
+    pub entity_type: String,
+    /// Added to the prompt to tell the model what counts as this type.
+    pub description: Option<String>,
+    pub fields: Vec<EntityField>,
+}
+
+impl EntitySchema {
+    pub fn new<T: Into<String>>(entity_type: T) -> Self {
+        Self {
+            entity_type: entity_type.into(),
+            description: None,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn person() -> Self {
+        Self::new("person").description("a real or fictional individual")
+    }
+
+    pub fn organization() -> Self {
+        Self::new("organization")
+            .description("a company, institution, agency, team or other group of people")
+    }
+
+    pub fn product() -> Self {
+        Self::new("product").description("a named product, game, software or service")
+    }
+
+    pub fn description<T: Into<String>>(mut self, description: T) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Adds a field to fill in for each entity of this type, answered in a few words.
+    pub fn field<T: Into<String>>(mut self, name: T, description: T) -> Self {
+        self.fields.push(EntityField {
+            name: name.into(),
+            description: description.into(),
+            max_words: 5,
+        });
+        self
+    }
+
+    fn type_description(&self) -> String {
+        match &self.description {
+            Some(description) => {
+                let mut type_with_article = self.type_with_article();
+                type_with_article[..1].make_ascii_uppercase();
+                format!("{type_with_article} is {description}.")
+            }
+            None => String::new(),
+        }
+    }
+
+    /// The entity type with its indefinite article, as in 'a person' or 'an organization'.
+    fn type_with_article(&self) -> String {
+        let article = if self
+            .entity_type
+            .starts_with(|c: char| matches!(c.to_ascii_lowercase(), 'a' | 'e' | 'i' | 'o' | 'u'))
+        {
+            "an"
+        } else {
+            "a"
+        };
+        format!("{article} {}", self.entity_type)
+    }
+
+    /// The instructions and response of a guidance example for this type, in the format of the validate and field steps.
+    fn guidance_example(&self) -> (String, String) {
+        let entity_type = &self.entity_type;
+        let type_with_article = self.type_with_article();
+        let (with_field, field_line, field_given) = match self.fields.first() {
+            Some(field) => (
+                format!(" with the field '{}'", field.name),
+                format!("\n{}: [{}].", field.name, field.description),
+                format!(" and its {} is given", field.name),
+            ),
+            None => (String::new(), String::new(), String::new()),
+        };
+        let instructions = format!(
+            "We are extracting entities from text. Please provide an example of extracting an entity of the type '{entity_type}'{with_field}."
+        );
+        let response = format!(
+            "`[text] is {type_with_article}: true.{field_line}` In this example, the text '[text]' is {type_with_article}, so it is extracted{field_given}.\n`[other text] is {type_with_article}: false.` In this example, the text '[other text]' is not {type_with_article}, so it is not extracted."
+        );
+        (instructions, response)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityField {
+    pub name: String,
+    pub description: String,
+    pub max_words: u8,
+}
+
+/// Extracts typed entities from the supporting material.
+///
+/// Candidates are the capitalized phrases of the supporting material, plus any added with `candidates`.
+/// The model picks from the candidates with an exact string grammar, so every result is a span of the text.
+/// Each pick is then validated against the entity type before its fields are filled in.
+#[derive(Clone)]
+pub struct ExtractEntities {
+    pub base_req: CompletionRequest,
+    pub instruct_prompt: InstructPrompt,
+    /// Defaults to people, organizations and products when empty.
+    pub schemas: Vec<EntitySchema>,
+    pub candidates: Vec<String>,
+    /// The maximum number of entities extracted across all types. Defaults to 10.
+    pub max_entity_count: u8,
+    pub results: Vec<ExtractedEntity>,
+}
+
+impl ExtractEntities {
+    pub fn new(base_req: CompletionRequest) -> Self {
+        ExtractEntities {
+            instruct_prompt: InstructPrompt::new(),
+            base_req,
+            schemas: Vec::new(),
+            candidates: Vec::new(),
+            max_entity_count: 10,
+            results: Vec::new(),
+        }
+    }
+
+    /// Adds an entity type without fields.
+    pub fn entity_type<T: Into<String>>(&mut self, entity_type: T) -> &mut Self {
+        self.schemas.push(EntitySchema::new(entity_type));
+        self
+    }
+
+    pub fn schema(&mut self, schema: EntitySchema) -> &mut Self {
+        self.schemas.push(schema);
+        self
+    }
+
+    pub fn max_entity_count(&mut self, max_entity_count: u8) -> &mut Self {
+        self.max_entity_count = max_entity_count;
+        self
+    }
+
+    /// Adds strings the model may return as entities, for entities that aren't capitalized.
+    /// Candidates not found in the supporting material are ignored.
+    pub fn candidates<T: AsRef<str>>(&mut self, candidates: &[T]) -> &mut Self {
+        self.candidates
+            .extend(candidates.iter().map(|c| c.as_ref().to_owned()));
+        self
+    }
+
+    pub async fn run_return_entities(&mut self) -> Result<Option<Vec<ExtractedEntity>>> {
+        Ok(self.run_return_result().await?.results)
+    }
+
+    pub async fn run_return_result(&mut self) -> Result<ExtractEntitiesResult> {
+        self.results.clear();
+        let flow = self.run_backend().await?;
+        let results = if self.results.is_empty() {
+            None
+        } else {
+            Some(self.results.clone())
+        };
+        Ok(ExtractEntitiesResult::new(flow, results))
+    }
+
+    async fn run_backend(&mut self) -> Result<CascadeFlow> {
+        let text = match self.instruct_prompt.build_supporting_material() {
+            Some(text) => text,
+            None => crate::bail!("ExtractEntities requires supporting material"),
+        };
+        if self.schemas.is_empty() {
+            self.schemas = vec![
+                EntitySchema::person(),
+                EntitySchema::organization(),
+                EntitySchema::product(),
+            ];
+        }
+        let mut candidates = entity_candidates(&text);
+        for candidate in &self.candidates {
+            if text.contains(candidate.as_str()) && !candidates.contains(candidate) {
+                candidates.push(candidate.to_owned());
+            }
+        }
+        if candidates.is_empty() {
+            crate::bail!("No entity candidates found in the supporting material");
+        }
+
+        let mut flow = CascadeFlow::new("ExtractEntities");
+        flow.open_cascade();
+        let (instructions, response) = self.schemas[0].guidance_example();
+        flow.new_round(instructions)
+            .add_guidance_step(&StepConfig::default(), response);
+        flow.last_round()?.run_all_steps(&mut self.base_req).await?;
+
+        let schemas = self.schemas.clone();
+        for (i, schema) in schemas.iter().enumerate() {
+            if self.results.len() >= self.max_entity_count as usize {
+                break;
+            }
+            let mut primitive = ExactStringPrimitive::default();
+            primitive.add_strings_to_allowed(&candidates);
+            for entity in &self.results {
+                primitive.remove_string_from_allowed(&entity.text);
+            }
+            if primitive.allowed_strings.is_empty() {
+                break;
+            }
+            self.run_schema(&mut flow, &mut primitive, schema, &text, i == 0)
+                .await?;
+        }
+
+        flow.close_cascade()?;
+        Ok(flow)
+    }
+
+    async fn run_schema(
+        &mut self,
+        flow: &mut CascadeFlow,
+        primitive: &mut ExactStringPrimitive,
+        schema: &EntitySchema,
+        text: &str,
+        include_text: bool,
+    ) -> Result<()> {
+        let entity_type = &schema.entity_type;
+        let no_result = format!("No more {entity_type}s.");
+        let fields = schema
+            .fields
+            .iter()
+            .map(|field| format!("{}: {}", field.name, field.description))
+            .collect::<Vec<_>>();
+        let fields = if fields.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\nFor each {entity_type}, also give:\n{}",
+                fields.join("\n")
+            )
+        };
+        let task = if include_text {
+            format!(
+                "Text with entities to extract:\n{text}\nReturn the first {entity_type} mentioned in the text. {} If the text contains no {entity_type}s say '{no_result}'.{fields}",
+                schema.type_description()
+            )
+        } else {
+            format!(
+                "Return the first {entity_type} mentioned in the text. {} If the text contains no {entity_type}s say '{no_result}'.{fields}",
+                schema.type_description()
+            )
+        };
+
+        let mut first = true;
+        while !primitive.allowed_strings.is_empty()
+            && self.results.len() < self.max_entity_count as usize
+        {
+            if first {
+                flow.new_round(task.clone()).step_separator = None;
+                first = false;
+            } else {
+                flow.new_round(format!("Return the next {entity_type} mentioned in the text, or if there are no more {entity_type}s say '{no_result}'.")).step_separator = None;
+            }
+            flow.last_round()?.open_round(&mut self.base_req)?;
+            self.extract_step(flow, primitive, &no_result).await?;
+            let Some(entity_text) = flow.primitive_result() else {
+                flow.last_round()?.close_round(&mut self.base_req)?;
+                break;
+            };
+            primitive.remove_string_from_allowed(&entity_text);
+            if self.validate_step(flow, schema).await? {
+                let mut entity = ExtractedEntity::new(entity_type, &entity_text, text);
+                for field in &schema.fields {
+                    let value = self.field_step(flow, field).await?;
+                    entity.fields.push((field.name.clone(), value));
+                }
+                self.results.push(entity);
+            }
+            flow.last_round()?.close_round(&mut self.base_req)?;
+        }
+        Ok(())
+    }
+
+    async fn extract_step(
+        &mut self,
+        flow: &mut CascadeFlow,
+        primitive: &mut ExactStringPrimitive,
+        no_result: &str,
+    ) -> Result<()> {
+        let config = StepConfig {
+            cache_prompt: true,
+            stop_word_no_result: Some(no_result.to_owned()),
+            grammar: primitive.grammar(),
+            ..StepConfig::default()
+        };
+
+        flow.last_round()?.add_inference_step(&config);
+        flow.last_round()?.run_next_step(&mut self.base_req).await
+    }
+
+    async fn validate_step(
+        &mut self,
+        flow: &mut CascadeFlow,
+        schema: &EntitySchema,
+    ) -> Result<bool> {
+        let type_with_article = schema.type_with_article();
+        let config = StepConfig {
+            cache_prompt: true,
+            step_prefix: Some(format!(" is {type_with_article}:")),
+            grammar: BooleanPrimitive::default().grammar(),
+            ..StepConfig::default()
+        };
+
+        flow.last_round()?.add_inference_step(&config);
+        flow.last_round()?.run_next_step(&mut self.base_req).await?;
+        // A response that isn't a boolean, e.g. after failed grammar emulation, doesn't validate the entity.
+        let validated = flow
+            .primitive_result()
+            .and_then(|result| result.trim().parse::<bool>().ok())
+            .unwrap_or(false);
+        if validated {
+            flow.last_round()?
+                .last_step()?
+                .set_dynamic_suffix(".\n".to_owned());
+            Ok(true)
+        } else {
+            flow.last_round()?
+                .last_step()?
+                .set_dynamic_suffix(format!(". I apologize. This is not {type_with_article} and was returned by mistake. In the future, we'll only return {}s.\n", schema.entity_type));
+            Ok(false)
+        }
+    }
+
+    async fn field_step(
+        &mut self,
+        flow: &mut CascadeFlow,
+        field: &EntityField,
+    ) -> Result<Option<String>> {
+        let config = StepConfig {
+            cache_prompt: true,
+            step_prefix: Some(format!("{}:", field.name)),
+            stop_word_no_result: Some("Unknown.".to_owned()),
+            grammar: WordsPrimitive::default()
+                .min_count(1)
+                .max_count(field.max_words)
+                .grammar(),
+            ..StepConfig::default()
+        };
+
+        flow.last_round()?.add_inference_step(&config);
+        flow.last_round()?.run_next_step(&mut self.base_req).await?;
+        let value = flow.primitive_result();
+        let suffix = if value.is_some() { ".\n" } else { "\n" };
+        flow.last_round()?.last_step()?.set_dynamic_suffix(suffix);
+        Ok(value)
+    }
+}
+
+impl RequestConfigTrait for ExtractEntities {
+    fn config(&mut self) -> &mut RequestConfig {
+        &mut self.base_req.config
+    }
+
+    fn reset_request(&mut self) {
+        self.instruct_prompt.reset_instruct_prompt();
+        self.base_req.reset_completion_request();
+        self.results.clear();
+    }
+}
+
+impl InstructPromptTrait for ExtractEntities {
+    fn instruct_prompt_mut(&mut self) -> &mut InstructPrompt {
+        &mut self.instruct_prompt
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtractedEntity {
+    pub entity_type: String,
+    pub text: String,
+    /// The byte range of the entity's first mention in the supporting material.
+    pub span: Range<usize>,
+    /// Field names and values, in schema order. `None` when the model answered 'Unknown.'.
+    pub fields: Vec<(String, Option<String>)>,
+}
+
+impl ExtractedEntity {
+    fn new(entity_type: &str, entity_text: &str, text: &str) -> Self {
+        let start = text.find(entity_text).unwrap_or_default();
+        Self {
+            entity_type: entity_type.to_owned(),
+            text: entity_text.to_owned(),
+            span: start..start + entity_text.len(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+}
+
+impl std::fmt::Display for ExtractedEntity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "\x1b[1m\x1b[38;5;174m{}\x1b[0m: '{}' ({:?})",
+            self.entity_type, self.text, self.span
+        )?;
+        for (name, value) in &self.fields {
+            writeln!(f, "  \x1b[38;5;175m{name}\x1b[0m: {value:?}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct ExtractEntitiesResult {
+    pub results: Option<Vec<ExtractedEntity>>,
+    pub duration: std::time::Duration,
+    pub workflow: CascadeFlow,
+}
+
+impl ExtractEntitiesResult {
+    fn new(flow: CascadeFlow, results: Option<Vec<ExtractedEntity>>) -> Self {
+        ExtractEntitiesResult {
+            results,
+            duration: flow.duration,
+            workflow: flow,
+        }
+    }
+}
+
+impl std::fmt::Display for ExtractEntitiesResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f)?;
+        writeln!(
+            f,
+            "\x1b[38;5;172m\x1b[1m{}\x1b[0m",
+            self.workflow.cascade_name
+        )?;
+        writeln!(f)?;
+        for result in self.results.iter().flatten() {
+            writeln!(f, "{}", result)?;
+        }
+        writeln!(f, "\x1b[38;5;43mduration\x1b[0m: {:?}", self.duration)?;
+        Ok(())
+    }
+}
+
+const CONNECTORS: [&str; 7] = ["of", "the", "and", "for", "de", "von", "van"];
+
+/// Capitalized phrases of the text, like 'Alan Turing' or 'University of Manchester', in order of first mention.
+/// Only phrases that appear verbatim in the text are returned. False positives are rejected by the model.
+fn entity_candidates(text: &str) -> Vec<String> {
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    let mut candidates: Vec<String> = Vec::new();
+    let mut run: Vec<&str> = Vec::new();
+    let mut run_at_sentence_start = false;
+    let mut sentence_start = true;
+
+    for (i, raw) in words.iter().enumerate() {
+        let leading = raw.starts_with(|c: char| !c.is_alphanumeric());
+        let trimmed = raw.trim_start_matches(|c: char| !c.is_alphanumeric());
+        let word_len = trimmed
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '\'' | '-' | '&')))
+            .unwrap_or(trimmed.len());
+        let rest = &trimmed[word_len..];
+        let is_initial =
+            word_len == 1 && trimmed.starts_with(|c: char| c.is_uppercase()) && rest == ".";
+        let word = if is_initial {
+            &trimmed[..2]
+        } else {
+            &trimmed[..word_len]
+        };
+        let ends_run = !rest.is_empty() && !is_initial;
+        let next_capitalized = words
+            .get(i + 1)
+            .map(|next| next.starts_with(|c: char| c.is_uppercase()))
+            == Some(true);
+
+        if leading {
+            push_candidate(&mut candidates, &run, run_at_sentence_start, text);
+            run.clear();
+        }
+        let continues = word.starts_with(|c: char| c.is_uppercase())
+            || (!run.is_empty() && word.starts_with(|c: char| c.is_ascii_digit()))
+            || (!run.is_empty() && !ends_run && next_capitalized && CONNECTORS.contains(&word));
+        if continues && !word.is_empty() {
+            if run.is_empty() {
+                run_at_sentence_start = sentence_start;
+            }
+            run.push(word);
+        } else {
+            push_candidate(&mut candidates, &run, run_at_sentence_start, text);
+            run.clear();
+        }
+        if ends_run || run.len() >= 6 {
+            push_candidate(&mut candidates, &run, run_at_sentence_start, text);
+            run.clear();
+        }
+        sentence_start = !is_initial && rest.contains(['.', '!', '?']);
+    }
+    push_candidate(&mut candidates, &run, run_at_sentence_start, text);
+    candidates
+}
+
+fn push_candidate(candidates: &mut Vec<String>, run: &[&str], at_sentence_start: bool, text: &str) {
+    let mut run = run;
+    // A sentence's first word is capitalized anyway, so drop it if it's used lowercase elsewhere.
+    if at_sentence_start {
+        if let Some(first) = run.first() {
+            if text.contains(&format!(" {} ", first.to_lowercase())) {
+                run = &run[1..];
+            }
+        }
+    }
+    while let Some(last) = run.last() {
+        if CONNECTORS.contains(last) {
+            run = &run[..run.len() - 1];
+        } else {
+            break;
+        }
+    }
+    if run.is_empty() {
+        return;
+    }
+    let candidate = run.join(" ");
+    if text.contains(&candidate) && !candidates.contains(&candidate) {
+        candidates.push(candidate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_with_article() {
+        assert_eq!(EntitySchema::person().type_with_article(), "a person");
+        assert_eq!(
+            EntitySchema::organization().type_with_article(),
+            "an organization"
+        );
+        assert_eq!(
+            EntitySchema::new("Event")
+                .description("something that happened")
+                .type_description(),
+            "An Event is something that happened."
+        );
+    }
+
+    #[test]
+    fn test_entity_candidates() {
+        let text = "The award is named after Alan Turing, a reader at the University of Manchester. The first recipient was Alan Perlis, of Carnegie Mellon University. Doom 3: BFG Edition was released.[19] The ACM A. M. Turing Award is given by (ACM) yearly.";
+        let candidates = entity_candidates(text);
+        for expected in [
+            "Alan Turing",
+            "University of Manchester",
+            "Alan Perlis",
+            "Carnegie Mellon University",
+            "Doom 3",
+            "BFG Edition",
+            "ACM A. M. Turing Award",
+            "ACM",
+        ] {
+            assert!(
+                candidates.contains(&expected.to_owned()),
+                "missing {expected} in {candidates:?}"
+            );
+        }
+        assert!(!candidates.contains(&"The".to_owned()));
+        for candidate in &candidates {
+            assert!(text.contains(candidate.as_str()));
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use llm_interface::requests::completion::CompletionRequest;
 
+pub mod entities;
 pub mod urls;
 
 pub struct Extract {
@@ -11,6 +12,10 @@ impl Extract {
         Self { base_req }
     }
 
+    pub fn entities(self) -> entities::ExtractEntities {
+        entities::ExtractEntities::new(self.base_req)
+    }
+
     pub fn urls(self) -> urls::ExtractUrls {
         urls::ExtractUrls::new(self.base_req)
     }
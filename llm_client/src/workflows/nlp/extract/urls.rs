@@ -1,6 +1,7 @@
 use crate::{
     components::{
         cascade::{step::StepConfig, CascadeFlow},
+        chunking::ChunkingConfig,
         instruct_prompt::{InstructPrompt, InstructPromptTrait},
     },
     primitives::*,
@@ -19,6 +20,8 @@ pub struct ExtractUrls {
     pub instruct_prompt: InstructPrompt,
     pub criteria: Option<String>,
     pub results: Vec<String>,
+    /// When set, long supporting material is split into chunks, URLs are extracted from each, and the results are merged.
+    pub chunking: Option<ChunkingConfig>,
 }
 
 impl ExtractUrls {
@@ -28,14 +31,62 @@ impl ExtractUrls {
             base_req,
             criteria: None,
             results: Vec::new(),
+            chunking: None,
         }
     }
 
+    /// Splits long supporting material into chunks and runs the extraction on each. The URLs from all chunks are merged without duplicates.
+    pub fn chunking(&mut self, chunking: ChunkingConfig) -> &mut Self {
+        self.chunking = Some(chunking);
+        self
+    }
+
     pub async fn run_return_urls(&mut self) -> Result<Option<Vec<Url>>> {
         Ok(self.run_return_result().await?.results)
     }
 
     pub async fn run_return_result(&mut self) -> Result<ExtractUrlResult> {
+        if let Some(chunking) = &self.chunking {
+            if let Some(supporting_material) = self.instruct_prompt.build_supporting_material() {
+                let chunks = chunking.chunk(&self.base_req, &supporting_material)?;
+                if chunks.len() > 1 {
+                    return self.run_chunks(chunks).await;
+                }
+            }
+        }
+        self.run_single().await
+    }
+
+    async fn run_chunks(&mut self, chunks: Vec<String>) -> Result<ExtractUrlResult> {
+        let chunk_count = chunks.len();
+        let mut merged: Option<ExtractUrlResult> = None;
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            if extract_urls(chunk.clone()).is_empty() {
+                continue;
+            }
+            crate::info!("ExtractUrls: running chunk {} of {chunk_count}", i + 1);
+            let mut worker = self.clone();
+            worker.chunking = None;
+            worker.results.clear();
+            worker.set_supporting_material(chunk);
+            let result = worker.run_single().await?;
+            match &mut merged {
+                Some(merged) => merged.merge(result),
+                None => merged = Some(result),
+            }
+        }
+        let merged = merged.ok_or_else(|| anyhow::anyhow!("No URLs found in the instructions"))?;
+        self.criteria = Some(merged.criteria.clone());
+        self.results = merged
+            .results
+            .iter()
+            .flatten()
+            .map(|url| url.to_string())
+            .collect();
+        Ok(merged)
+    }
+
+    async fn run_single(&mut self) -> Result<ExtractUrlResult> {
         let flow = self.run_backend().await?;
         if self.results.is_empty() {
             Ok(ExtractUrlResult::new(
@@ -244,6 +295,8 @@ pub struct ExtractUrlResult {
     pub criteria: String,
     pub duration: std::time::Duration,
     pub workflow: CascadeFlow,
+    /// The workflows of the remaining chunks, when the supporting material was chunked. `workflow` is the first chunk's.
+    pub chunk_workflows: Vec<CascadeFlow>,
 }
 
 impl ExtractUrlResult {
@@ -253,7 +306,22 @@ impl ExtractUrlResult {
             criteria: criteria.to_owned(),
             duration: flow.duration,
             workflow: flow,
+            chunk_workflows: Vec::new(),
+        }
+    }
+
+    /// Adds the URLs of another chunk's result, skipping duplicates.
+    fn merge(&mut self, other: ExtractUrlResult) {
+        if let Some(other_results) = other.results {
+            let results = self.results.get_or_insert_with(Vec::new);
+            for url in other_results {
+                if !results.contains(&url) {
+                    results.push(url);
+                }
+            }
         }
+        self.duration += other.duration;
+        self.chunk_workflows.push(other.workflow);
     }
 }
 
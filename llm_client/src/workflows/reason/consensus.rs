@@ -0,0 +1,244 @@
+use super::ReasonResult;
+use std::collections::HashMap;
+
+/// How a `Decision` turns its votes into a winner.
+///
+/// "None" votes, from optional decisions, are a result of their own and can win like any other.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ConsensusStrategy {
+    /// A result wins once it has more than half of `best_of_n_votes`. Voting stops as soon as one does.
+    /// Primitives that collect all of their votes, like `FloatPrimitive`, collect `best_of_n_votes` and pick the winner with `tally_votes`.
+    #[default]
+    StrictMajority,
+    /// Collects `min_votes` votes, then the result with the most votes wins, with ties going to the lowest index.
+    Plurality { min_votes: u8 },
+    /// Every one of `best_of_n_votes` must agree. Stops with no consensus at the first disagreement.
+    Unanimous,
+    /// Collects `best_of_n_votes`, then the result with the most votes wins,
+    /// if its share of the votes is at least `threshold`, from 0.0 to 1.0.
+    MinConfidence { threshold: f32 },
+    /// Collects `best_of_n_votes`, then the result with the highest total weight wins.
+    /// The confidence is the winner's share of the total weight.
+    Weighted { weight: VoteWeight },
+}
+
+/// The weight of a single vote in [`ConsensusStrategy::Weighted`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum VoteWeight {
+    /// The probability of the answer's tokens, as the exponent of their mean logprob.
+    /// The `Decision` requests token probabilities with `RequestConfig::logprobs`.
+    /// Votes from backends that don't return token probabilities weigh 1.0.
+    Logprob,
+    /// A weight per model id. Models that aren't listed weigh 1.0.
+    Model(HashMap<String, f32>),
+}
+
+impl VoteWeight {
+    pub(super) fn of(&self, reason_result: &ReasonResult) -> f32 {
+        self.weigh(reason_result.logprob, &reason_result.model_id)
+    }
+
+    fn weigh(&self, logprob: Option<f32>, model_id: &str) -> f32 {
+        match self {
+            VoteWeight::Logprob => logprob.map(f32::exp).unwrap_or(1.0),
+            VoteWeight::Model(weights) => weights.get(model_id).copied().unwrap_or(1.0),
+        }
+    }
+}
+
+/// The result with the highest total weight, and its share of the total weight.
+/// Ties go to none, then to the lowest index.
+pub(super) fn weighted_winner(
+    weighted_votes: impl IntoIterator<Item = (Option<u32>, f32)>,
+) -> Option<(Option<u32>, f32)> {
+    let mut weights: HashMap<Option<u32>, f32> = HashMap::new();
+    for (result_index, weight) in weighted_votes {
+        *weights.entry(result_index).or_insert(0.0) += weight;
+    }
+    let total_weight: f32 = weights.values().sum();
+    let (winner_index, winner_weight) = weights
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))?;
+    let confidence = if total_weight > 0.0 {
+        winner_weight / total_weight
+    } else {
+        0.0
+    };
+    Some((winner_index, confidence))
+}
+
+/// What a `Decision` should do after a vote.
+#[derive(Debug, PartialEq)]
+pub(super) enum ConsensusCheck {
+    Continue,
+    Tally,
+    NoConsensus,
+}
+
+impl ConsensusStrategy {
+    /// The most votes the strategy collects before a winner is picked.
+    pub(super) fn votes_to_collect(&self, best_of_n_votes: u8) -> u16 {
+        match self {
+            ConsensusStrategy::Plurality { min_votes } => u16::from((*min_votes).max(1)),
+            _ => u16::from(best_of_n_votes.max(1)),
+        }
+    }
+
+    /// The votes a result needs to win early, for strategies that stop at a majority.
+    pub(super) fn votes_required_to_win(
+        &self,
+        best_of_n_votes: u8,
+        collect_all_votes: bool,
+    ) -> Option<u16> {
+        match self {
+            ConsensusStrategy::StrictMajority if !collect_all_votes => {
+                let best_of_n_votes = u16::from(best_of_n_votes);
+                Some((best_of_n_votes + (best_of_n_votes % 2)) / 2)
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks the votes so far. `votes` holds the votes per result index, and `none_votes` the votes for none.
+    pub(super) fn check(
+        &self,
+        votes: &HashMap<u32, u16>,
+        none_votes: u16,
+        total_votes: u16,
+        best_of_n_votes: u8,
+        collect_all_votes: bool,
+    ) -> ConsensusCheck {
+        if let Some(votes_required_to_win) =
+            self.votes_required_to_win(best_of_n_votes, collect_all_votes)
+        {
+            let leader_votes = votes.values().copied().max().unwrap_or(0);
+            return if leader_votes.max(none_votes) >= votes_required_to_win {
+                ConsensusCheck::Tally
+            } else {
+                ConsensusCheck::Continue
+            };
+        }
+        let collected = total_votes >= self.votes_to_collect(best_of_n_votes);
+        match self {
+            ConsensusStrategy::Unanimous if votes.len() + usize::from(none_votes > 0) > 1 => {
+                ConsensusCheck::NoConsensus
+            }
+            _ if collected => ConsensusCheck::Tally,
+            _ => ConsensusCheck::Continue,
+        }
+    }
+
+    /// Whether the votes need token probabilities from the backend.
+    pub(super) fn needs_logprobs(&self) -> bool {
+        matches!(
+            self,
+            ConsensusStrategy::Weighted {
+                weight: VoteWeight::Logprob
+            }
+        )
+    }
+
+    /// The minimum confidence the winner needs, if any.
+    pub(super) fn min_confidence(&self) -> Option<f32> {
+        match self {
+            ConsensusStrategy::MinConfidence { threshold } => Some(*threshold),
+            _ => None,
+        }
+    }
+}
+
+/// Why a `Decision` returned without a winner. Returned inside the `anyhow::Error`, so match it with `downcast_ref`.
+///
+/// ```ignore
+/// match decision.return_result().await {
+///     Err(e) if matches!(e.downcast_ref(), Some(DecisionError::NoConsensus { .. })) => { /* ask a human */ }
+///     res => { /* ... */ }
+/// }
+/// ```
+#[derive(Debug, thiserror::Error)]
+pub enum DecisionError {
+    #[error("Decision: no consensus with {strategy:?} after {total_votes} votes, the leading result had a confidence of {confidence}")]
+    NoConsensus {
+        strategy: ConsensusStrategy,
+        /// Votes per result index.
+        votes: HashMap<u32, u16>,
+        none_votes: u16,
+        total_votes: u16,
+        confidence: f32,
+    },
+    #[error("Decision: failed to get a valid response after {failed_attempts} attempts")]
+    ExceededRetryCount { failed_attempts: u8 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consensus_check() {
+        let votes = HashMap::from([(1, 2), (2, 1)]);
+        let strict = ConsensusStrategy::StrictMajority;
+        assert_eq!(strict.check(&votes, 0, 3, 3, false), ConsensusCheck::Tally);
+        assert_eq!(
+            strict.check(&votes, 0, 3, 5, false),
+            ConsensusCheck::Continue
+        );
+        assert_eq!(
+            strict.check(&votes, 0, 3, 5, true),
+            ConsensusCheck::Continue
+        );
+
+        let plurality = ConsensusStrategy::Plurality { min_votes: 3 };
+        assert_eq!(
+            plurality.check(&votes, 0, 3, 7, false),
+            ConsensusCheck::Tally
+        );
+
+        let unanimous = ConsensusStrategy::Unanimous;
+        let agreeing = HashMap::from([(1, 2)]);
+        assert_eq!(
+            unanimous.check(&agreeing, 0, 2, 3, false),
+            ConsensusCheck::Continue
+        );
+        assert_eq!(
+            unanimous.check(&agreeing, 1, 3, 3, false),
+            ConsensusCheck::NoConsensus
+        );
+        assert_eq!(
+            unanimous.check(&votes, 0, 3, 3, false),
+            ConsensusCheck::NoConsensus
+        );
+    }
+
+    #[test]
+    fn test_logprob_weighted_tally() {
+        let weight = VoteWeight::Logprob;
+        assert_eq!(weight.weigh(Some(0.0), "model"), 1.0);
+        assert!((weight.weigh(Some(0.5f32.ln()), "model") - 0.5).abs() < f32::EPSILON);
+        assert_eq!(weight.weigh(None, "model"), 1.0);
+
+        // One confident vote outweighs two unsure ones.
+        let votes = [
+            (Some(1), weight.weigh(Some(0.2f32.ln()), "model")),
+            (Some(1), weight.weigh(Some(0.2f32.ln()), "model")),
+            (Some(2), weight.weigh(Some(0.9f32.ln()), "model")),
+        ];
+        let (winner_index, confidence) = weighted_winner(votes).unwrap();
+        assert_eq!(winner_index, Some(2));
+        assert!((confidence - 0.9 / 1.3).abs() < 1e-6);
+
+        let model = VoteWeight::Model(HashMap::from([("large".to_string(), 3.0)]));
+        let votes = [
+            (Some(1), model.weigh(None, "small")),
+            (Some(1), model.weigh(None, "small")),
+            (None, model.weigh(None, "large")),
+        ];
+        assert_eq!(weighted_winner(votes).unwrap().0, None);
+
+        assert_eq!(
+            weighted_winner([(Some(2), 1.0), (Some(1), 1.0)]).unwrap().0,
+            Some(1)
+        );
+        assert!(weighted_winner([]).is_none());
+    }
+}
@@ -0,0 +1,127 @@
+use super::{one_round::ReasonOneRound, PrimitiveTrait, ReasonTrait, SentencesPrimitive};
+use crate::components::{
+    cascade::{grammar_emulation::GrammarEmulation, step::StepConfig, CascadeFlow},
+    instruct_prompt::InstructPrompt,
+};
+use llm_interface::requests::completion::CompletionRequest;
+
+const DEFAULT_CRITIQUE_PROMPT: &str = "Critique the reasoning above. Point out any mistakes, unsupported assumptions, or parts of the request that were missed, then give a revised conclusion with, 'Therefore, the revised conclusion is:...'. With no yapping.";
+
+/// Reasons in several rounds. Drafts reasoning and a conclusion, then critiques them and revises the conclusion
+/// for each of `critique_rounds`, and gives the solution, constrained by the primitive's grammar, after the last revision.
+///
+/// Created with `ReasonOneRound::self_critique`. Implements `DecisionTrait`, so it can be used in a `Decision`.
+pub struct ReasonCritique<P> {
+    pub reasoning_sentences: u8,
+    pub critique_sentences: u8,
+    pub conclusion_sentences: u8,
+    pub critique_rounds: u8,
+    /// The task of each critique round. Rounds past the end of the list start over from the first prompt.
+    pub critique_prompts: Vec<String>,
+    pub result_can_be_none: bool,
+    pub primitive: P,
+    pub base_req: CompletionRequest,
+    pub instruct_prompt: InstructPrompt,
+    /// Used in place of the primitive's grammar on backends without native grammar support.
+    pub grammar_emulation: GrammarEmulation,
+}
+
+impl<P: PrimitiveTrait + ReasonTrait> From<ReasonOneRound<P>> for ReasonCritique<P> {
+    fn from(one_round: ReasonOneRound<P>) -> Self {
+        Self {
+            reasoning_sentences: one_round.reasoning_sentences,
+            critique_sentences: 3,
+            conclusion_sentences: one_round.conclusion_sentences,
+            critique_rounds: 1,
+            critique_prompts: vec![DEFAULT_CRITIQUE_PROMPT.to_owned()],
+            result_can_be_none: one_round.result_can_be_none,
+            primitive: one_round.primitive,
+            base_req: one_round.base_req,
+            instruct_prompt: one_round.instruct_prompt,
+            grammar_emulation: one_round.grammar_emulation,
+        }
+    }
+}
+
+impl<P: PrimitiveTrait + ReasonTrait> ReasonCritique<P> {
+    pub fn critique_sentences(&mut self, critique_sentences: u8) -> &mut Self {
+        self.critique_sentences = critique_sentences;
+        self
+    }
+
+    /// Sets how many times the model critiques its reasoning and revises its conclusion. At least one.
+    pub fn critique_rounds(&mut self, critique_rounds: u8) -> &mut Self {
+        self.critique_rounds = critique_rounds.max(1);
+        self
+    }
+
+    /// Sets the task of each critique round. Each prompt should ask for a revised conclusion,
+    /// starting with 'Therefore, the revised conclusion is:'.
+    pub fn critique_prompts<T: Into<String>>(
+        &mut self,
+        critique_prompts: impl IntoIterator<Item = T>,
+    ) -> &mut Self {
+        self.critique_prompts = critique_prompts.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub(super) fn reason_critique(&mut self) -> crate::Result<CascadeFlow> {
+        let mut flow = CascadeFlow::new("Reason Critique");
+        super::add_guidance_round(&mut flow, false);
+
+        let task = super::reason_task(&mut self.instruct_prompt)?;
+
+        // Draft reasoning
+        flow.new_round(task)
+            .add_inference_step(&super::reasoning_step_config(self.reasoning_sentences));
+
+        // Draft conclusion
+        flow.last_round()?
+            .add_inference_step(&super::conclusion_step_config(
+                &self.primitive,
+                self.result_can_be_none,
+                self.conclusion_sentences,
+            ));
+
+        for round in 0..self.critique_rounds.max(1) {
+            let critique_prompt = match self.critique_prompts.len() {
+                0 => DEFAULT_CRITIQUE_PROMPT.to_owned(),
+                len => self.critique_prompts[round as usize % len].clone(),
+            };
+
+            // Critique
+            let step_config = StepConfig {
+                step_prefix: Some("Critiquing the reasoning so far...".to_string()),
+                stop_word_done: "Therefore, the revised conclusion".to_string(),
+                grammar: SentencesPrimitive::default()
+                    .min_count(1)
+                    .max_count(self.critique_sentences)
+                    .grammar(),
+                ..StepConfig::default()
+            };
+            flow.new_round(critique_prompt)
+                .add_inference_step(&step_config);
+
+            // Revised conclusion
+            let step_config = StepConfig {
+                step_prefix: Some("Therefore, the revised conclusion is:".to_string()),
+                stop_word_done: "Thus, the solution".to_string(),
+                grammar: SentencesPrimitive::default()
+                    .min_count(1)
+                    .max_count(self.conclusion_sentences)
+                    .grammar(),
+                ..StepConfig::default()
+            };
+            flow.last_round()?.add_inference_step(&step_config);
+        }
+
+        super::add_solution_steps(
+            &mut flow,
+            &self.primitive,
+            self.result_can_be_none,
+            &mut self.instruct_prompt,
+            &self.grammar_emulation,
+        )?;
+        Ok(flow)
+    }
+}
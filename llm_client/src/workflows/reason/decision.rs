@@ -1,23 +1,36 @@
-use super::{ReasonResult, ReasonTrait};
+use super::{
+    consensus::{weighted_winner, ConsensusCheck, ConsensusStrategy, DecisionError},
+    ensemble::{is_tied, member_for_attempt, EnsembleMember},
+    sampling::{DynamicTemperature, FixedSampling, SamplingSchedule, VoteContext, VoteSampling},
+    ReasonResult, ReasonTrait,
+};
 use crate::{
-    components::{instruct_prompt::InstructPrompt, InstructPromptTrait},
+    components::{chunking::ChunkingConfig, instruct_prompt::InstructPrompt, InstructPromptTrait},
     primitives::*,
 };
-use llm_interface::requests::{
-    completion::CompletionRequest,
-    req_components::{RequestConfig, RequestConfigTrait},
+use llm_interface::{
+    llms::LlmBackend,
+    requests::{
+        completion::CompletionRequest,
+        req_components::{RequestConfig, RequestConfigTrait},
+    },
 };
-use std::collections::HashMap;
-
-const DYNAMIC_TEMPERATURE_MIN: f32 = 0.11;
-const DYNAMIC_TEMPERATURE_MAX: f32 = 1.89;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
 
 pub struct Decision<D: DecisionTrait> {
     pub base_req: CompletionRequest,
     pub best_of_n_votes: u8,
-    pub dynamic_temperature: bool,
+    /// Decides the temperature, top_p, seed and prompt of each vote. Defaults to [`DynamicTemperature`].
+    pub sampling: Box<dyn SamplingSchedule>,
     pub reason: D,
     pub result_can_be_none: bool,
+    /// How the votes are turned into a winner. Defaults to [`ConsensusStrategy::StrictMajority`].
+    pub consensus: ConsensusStrategy,
+    /// When set, long supporting material is split into chunks, a decision is run on each, and the votes are combined.
+    pub chunking: Option<ChunkingConfig>,
+    /// When not empty, the votes are cast by these backends instead of `base_req`'s. See [`EnsembleMember`].
+    pub ensemble: Vec<EnsembleMember>,
 }
 
 impl<D: DecisionTrait> Decision<D> {
@@ -46,12 +59,12 @@ impl<D: DecisionTrait> Decision<D> {
 
     pub async fn return_result(&mut self) -> crate::Result<DecisionResult> {
         self.result_can_be_none = false;
-        self.run_decision().await
+        self.run().await
     }
 
     pub async fn return_optional_result(&mut self) -> crate::Result<DecisionResult> {
         self.result_can_be_none = true;
-        self.run_decision().await
+        self.run().await
     }
 
     pub fn parse_decision_result(
@@ -67,127 +80,253 @@ impl<D: DecisionTrait> Decision<D> {
         }
     }
 
+    async fn run(&mut self) -> crate::Result<DecisionResult> {
+        if let Some(chunking) = &self.chunking {
+            if let Some(supporting_material) = self
+                .reason
+                .instruct_prompt_mut()
+                .build_supporting_material()
+            {
+                let chunks = chunking.chunk(&self.base_req, &supporting_material)?;
+                if chunks.len() > 1 {
+                    let res = self.run_chunks(chunks).await;
+                    self.reason.set_supporting_material(supporting_material);
+                    return res;
+                }
+            }
+        }
+        self.run_decision().await
+    }
+
+    /// Runs a decision per chunk and adds every chunk's votes to a single tally. The choice with the most votes overall wins.
+    async fn run_chunks(&mut self, chunks: Vec<String>) -> crate::Result<DecisionResult> {
+        let start = std::time::Instant::now();
+        let mut decision_result = DecisionResult::new();
+        let mut none_count: u16 = 0;
+        for chunk in chunks {
+            self.reason.set_supporting_material(chunk);
+            let chunk_result = self.run_decision().await?;
+            let chunk_choice_votes: u16 = chunk_result.votes.values().sum();
+            none_count += chunk_result.total_votes.saturating_sub(chunk_choice_votes);
+            decision_result.total_votes += chunk_result.total_votes;
+            for (choice_index, choice_votes) in chunk_result.votes {
+                *decision_result.votes.entry(choice_index).or_insert(0) += choice_votes;
+            }
+            decision_result
+                .reason_results
+                .extend(chunk_result.reason_results);
+        }
+
+        self.tally_decision(&mut decision_result, none_count)?;
+        decision_result.duration = start.elapsed();
+        tracing::info!("{}", decision_result.to_string());
+        Ok(decision_result)
+    }
+
+    /// Picks the winner from all of the votes with the consensus strategy, or the primitive's `tally_votes`. None wins if it has more votes, when allowed.
+    fn tally_decision(
+        &self,
+        decision_result: &mut DecisionResult,
+        none_count: u16,
+    ) -> crate::Result<()> {
+        if let ConsensusStrategy::Weighted { weight } = &self.consensus {
+            if let Some((winner_index, confidence)) = weighted_winner(
+                decision_result
+                    .reason_results
+                    .iter()
+                    .map(|reason_result| (reason_result.result_index, weight.of(reason_result))),
+            ) {
+                decision_result.winner_index = winner_index;
+                decision_result.winner_votes = match winner_index {
+                    Some(winner_index) => decision_result.votes[&winner_index],
+                    None => none_count,
+                };
+                decision_result.confidence = confidence;
+            }
+            decision_result.winner_primitive_result = match decision_result.winner_index {
+                Some(winner_index) => Some(
+                    self.reason
+                        .primitive()
+                        .result_index_to_string(winner_index)?,
+                ),
+                None => Some("none".to_string()),
+            };
+        } else {
+            if let Some((choice_index, choice_votes)) = self
+                .reason
+                .primitive()
+                .tally_votes(&decision_result.votes, decision_result.total_votes)
+            {
+                decision_result.winner_index = Some(choice_index);
+                decision_result.winner_votes = choice_votes;
+            }
+            match decision_result.winner_index {
+                Some(winner_index)
+                    if !(self.result_can_be_none && none_count > decision_result.winner_votes) =>
+                {
+                    decision_result.winner_primitive_result = Some(
+                        self.reason
+                            .primitive()
+                            .result_index_to_string(winner_index)?,
+                    );
+                }
+                _ => {
+                    decision_result.winner_index = None;
+                    decision_result.winner_votes = none_count;
+                    decision_result.winner_primitive_result = Some("none".to_string());
+                }
+            }
+            if decision_result.total_votes > 0 {
+                decision_result.confidence =
+                    decision_result.winner_votes as f32 / decision_result.total_votes as f32;
+            }
+        }
+        if let Some(threshold) = self.consensus.min_confidence() {
+            if decision_result.confidence < threshold {
+                return Err(self.no_consensus(decision_result, none_count).into());
+            }
+        }
+        Ok(())
+    }
+
+    fn no_consensus(&self, decision_result: &DecisionResult, none_count: u16) -> DecisionError {
+        // Before a tally, the confidence is the leading result's share of the votes.
+        let confidence = if decision_result.winner_primitive_result.is_some() {
+            decision_result.confidence
+        } else if decision_result.total_votes > 0 {
+            let leader_votes = decision_result.votes.values().copied().max().unwrap_or(0);
+            leader_votes.max(none_count) as f32 / decision_result.total_votes as f32
+        } else {
+            0.0
+        };
+        let error = DecisionError::NoConsensus {
+            strategy: self.consensus.clone(),
+            votes: decision_result.votes.clone(),
+            none_votes: none_count,
+            total_votes: decision_result.total_votes,
+            confidence,
+        };
+        tracing::info!("{error}");
+        error
+    }
+
     async fn run_decision(&mut self) -> crate::Result<DecisionResult> {
         let start = std::time::Instant::now();
         let mut decision_result = DecisionResult::new();
         let mut failed_attempts = 0;
         let mut none_count = 0;
-
-        self.set_dynamic_temperature_on_initial(self.dynamic_temperature, self.best_of_n_votes);
+        let collect_all_votes = self.reason.primitive().collect_all_votes();
+        let mut last_attempt_failed = false;
+        // Tie-breakers only wait for a tie when there are regular members to cast the other votes.
+        let tie_breakers = if self.ensemble.iter().any(|member| !member.tie_breaker) {
+            self.ensemble
+                .iter()
+                .filter(|member| member.tie_breaker)
+                .count()
+        } else {
+            0
+        };
+        let mut tie_breaker_votes = 0;
 
         while failed_attempts < self.base_req.config.retry_after_fail_n_times {
-            if failed_attempts >= self.base_req.config.retry_after_fail_n_times {
-                break;
+            let attempt = decision_result.total_votes + u16::from(failed_attempts);
+            let votes_to_collect = self.consensus.votes_to_collect(self.best_of_n_votes);
+            let mut sampling = self.sampling.vote_sampling(&VoteContext {
+                base: VoteSampling::from_config(&self.base_req.config),
+                attempt,
+                total_votes: decision_result.total_votes,
+                failed_attempts,
+                last_attempt_failed,
+                leader_votes: decision_result.votes.values().copied().max().unwrap_or(0),
+                votes_to_collect,
+                votes_required_to_win: self
+                    .consensus
+                    .votes_required_to_win(self.best_of_n_votes, collect_all_votes),
+            });
+            let tied = decision_result.total_votes >= votes_to_collect
+                && is_tied(&decision_result.votes, none_count);
+            let member = member_for_attempt(&self.ensemble, attempt, tied);
+            let tie_breaker_vote = member.is_some_and(|member| member.tie_breaker);
+            *self.reason.base_req_mut() = match member {
+                Some(member) => member.request(&self.base_req),
+                None => self.base_req.clone(),
+            };
+            sampling = sampling.supported_by(&self.reason.base_req_mut().backend.capabilities());
+            sampling.apply_to_config(&mut self.reason.base_req_mut().config);
+            if self.consensus.needs_logprobs() {
+                self.reason.base_req_mut().config.logprobs = true;
             }
-            *self.reason.base_req_mut() = self.base_req.clone();
-            let reason_result = match self
+            let instruct_prompt = sampling.prompt_perturbation.as_ref().map(|perturbation| {
+                let instruct_prompt = self.reason.instruct_prompt_mut().clone();
+                self.reason
+                    .instructions()
+                    .append_content(perturbation.as_str());
+                instruct_prompt
+            });
+            let reason_result = self
                 .reason
                 .return_reason_result(self.result_can_be_none)
-                .await
-            {
-                Ok(reason_result) => reason_result,
-                Err(_) => {
-                    self.set_dynamic_temperature_on_fail(self.dynamic_temperature);
+                .await;
+            if let Some(instruct_prompt) = instruct_prompt {
+                *self.reason.instruct_prompt_mut() = instruct_prompt;
+            }
+            let mut reason_result = match reason_result {
+                Ok(reason_result)
+                    if self
+                        .reason
+                        .primitive()
+                        .parse_reason_result(&reason_result)
+                        .is_ok() =>
+                {
+                    reason_result
+                }
+                _ => {
+                    last_attempt_failed = true;
                     failed_attempts += 1;
                     continue;
                 }
             };
+            last_attempt_failed = false;
+            reason_result.sampling = sampling;
 
-            match self.reason.primitive().parse_reason_result(&reason_result) {
-                Err(_) => {
-                    self.set_dynamic_temperature_on_fail(self.dynamic_temperature);
-                    failed_attempts += 1;
+            decision_result.total_votes += 1;
+            if tie_breaker_vote {
+                tie_breaker_votes += 1;
+            }
+            if let Some(result_index) = reason_result.result_index {
+                *decision_result.votes.entry(result_index).or_insert(0) += 1;
+            } else {
+                none_count += 1;
+            }
+            decision_result.reason_results.push(reason_result);
+
+            match self.consensus.check(
+                &decision_result.votes,
+                none_count,
+                decision_result.total_votes,
+                self.best_of_n_votes,
+                collect_all_votes,
+            ) {
+                // A tied tally waits for a vote from each tie-breaker.
+                ConsensusCheck::Tally
+                    if tie_breaker_votes < tie_breakers
+                        && is_tied(&decision_result.votes, none_count) => {}
+                ConsensusCheck::Tally => {
+                    self.tally_decision(&mut decision_result, none_count)?;
+                    decision_result.duration = start.elapsed();
+                    tracing::info!("{}", decision_result.to_string());
+                    return Ok(decision_result);
                 }
-                Ok(primitive_result) => {
-                    decision_result.total_votes += 1;
-                    if let Some(result_index) = reason_result.result_index {
-                        *decision_result.votes.entry(result_index).or_insert(0) += 1;
-                        for (choice_index, choice_votes) in &mut decision_result.votes {
-                            if *choice_votes > decision_result.winner_votes {
-                                decision_result.winner_votes = *choice_votes;
-                                decision_result.winner_index = Some(*choice_index);
-                            }
-                        }
-                    } else {
-                        none_count += 1;
-                    }
-                    if decision_result.winner_votes
-                        >= (self.best_of_n_votes + (self.best_of_n_votes % 2)) / 2
-                    {
-                        decision_result.confidence = decision_result.winner_votes as f32
-                            / decision_result.total_votes as f32;
-                        decision_result.duration = start.elapsed();
-                        tracing::info!("{}", decision_result.to_string());
-
-                        decision_result.winner_primitive_result =
-                            Some(primitive_result.unwrap().to_string());
-
-                        decision_result.reason_results.push(reason_result);
-
-                        return Ok(decision_result);
-                    } else if none_count >= (self.best_of_n_votes + (self.best_of_n_votes % 2)) / 2
-                    {
-                        decision_result.winner_votes = none_count;
-                        decision_result.confidence =
-                            none_count as f32 / decision_result.total_votes as f32;
-                        decision_result.duration = start.elapsed();
-                        tracing::info!("{}", decision_result.to_string());
-
-                        decision_result.winner_primitive_result = Some("none".to_string());
-
-                        decision_result.reason_results.push(reason_result);
-
-                        return Ok(decision_result);
-                    } else {
-                        self.set_dynamic_temperature_on_success(
-                            self.best_of_n_votes,
-                            &decision_result,
-                        );
-                        decision_result.reason_results.push(reason_result);
-                    }
+                ConsensusCheck::NoConsensus => {
+                    return Err(self.no_consensus(&decision_result, none_count).into());
                 }
+                ConsensusCheck::Continue => (),
             }
         }
-        Err(anyhow::format_err!(
-            "BaseDecider: failed to get a valid response after {}",
-            failed_attempts
-        ))
-    }
-
-    fn set_dynamic_temperature_on_initial(
-        &mut self,
-        dynamic_temperature: bool,
-        best_of_n_votes: u8,
-    ) {
-        if dynamic_temperature && best_of_n_votes > 1 {
-            self.base_req.config.temperature = DYNAMIC_TEMPERATURE_MIN;
-        }
-    }
-
-    fn set_dynamic_temperature_on_success(
-        &mut self,
-        best_of_n_votes: u8,
-        decision_result: &DecisionResult,
-    ) {
-        let votes_required_to_win = (best_of_n_votes + (best_of_n_votes % 2)) / 2;
-        if votes_required_to_win - decision_result.winner_votes == 1 {
-            self.base_req.config.temperature = DYNAMIC_TEMPERATURE_MAX;
-            return;
-        }
-
-        let minimum_votes_remaining = votes_required_to_win - decision_result.winner_votes;
-
-        let maybe_average_votes_remaining =
-            (votes_required_to_win + minimum_votes_remaining) as f32 / 2.0;
-
-        self.base_req.config.temperature = self.base_req.config.temperature
-            + ((DYNAMIC_TEMPERATURE_MAX - self.base_req.config.temperature)
-                / maybe_average_votes_remaining);
-    }
-
-    fn set_dynamic_temperature_on_fail(&mut self, dynamic_temperature: bool) {
-        if dynamic_temperature {
-            self.base_req.config.temperature += DYNAMIC_TEMPERATURE_MIN;
+        if decision_result.total_votes == 0 {
+            Err(DecisionError::ExceededRetryCount { failed_attempts }.into())
+        } else {
+            Err(self.no_consensus(&decision_result, none_count).into())
         }
     }
 
@@ -203,9 +342,50 @@ impl<D: DecisionTrait> Decision<D> {
         self
     }
 
+    /// Sets how the votes are turned into a winner. When no consensus is reached, the decision returns a [`DecisionError::NoConsensus`].
+    pub fn consensus(&mut self, consensus: ConsensusStrategy) -> &mut Self {
+        self.consensus = consensus;
+        self
+    }
+
+    /// Splits long supporting material into chunks and runs the decision on each. The votes of all chunks are combined, and the choice with the most votes wins.
+    pub fn chunking(&mut self, chunking: ChunkingConfig) -> &mut Self {
+        self.chunking = Some(chunking);
+        self
+    }
+
     /// Dynamically scales temperature during the voting process. Starts at a low temperature and increases towards max temperature as the number of votes increases.
+    /// Sets the sampling schedule to [`DynamicTemperature`] when true, or [`FixedSampling`] when false.
     pub fn dynamic_temperature(&mut self, dynamic_temperature: bool) -> &mut Self {
-        self.dynamic_temperature = dynamic_temperature;
+        if dynamic_temperature {
+            self.sampling(DynamicTemperature::default())
+        } else {
+            self.sampling(FixedSampling::default())
+        }
+    }
+
+    /// Adds a backend to vote in the decision, with a share of the votes relative to the other members.
+    /// Once a member is added, only members vote, so add the request's own backend too if it should vote.
+    pub fn add_ensemble_member(&mut self, backend: Arc<LlmBackend>, weight: f32) -> &mut Self {
+        self.ensemble
+            .push(EnsembleMember::new(backend).weight(weight));
+        self
+    }
+
+    /// Adds a backend that only votes once the other members have cast their votes and the leading results are tied.
+    /// A tied tally waits for one vote from each tie-breaker.
+    pub fn add_ensemble_tie_breaker(&mut self, backend: Arc<LlmBackend>, weight: f32) -> &mut Self {
+        self.ensemble.push(
+            EnsembleMember::new(backend)
+                .weight(weight)
+                .tie_breaker(true),
+        );
+        self
+    }
+
+    /// Sets how the temperature, top_p, seed and prompt vary between votes. The settings used are recorded in each `ReasonResult`.
+    pub fn sampling<S: SamplingSchedule + 'static>(&mut self, sampling: S) -> &mut Self {
+        self.sampling = Box::new(sampling);
         self
     }
 }
@@ -228,9 +408,12 @@ pub trait DecisionTrait: Sized + InstructPromptTrait {
         Decision {
             base_req: self.base_req().clone(),
             best_of_n_votes: 3,
-            dynamic_temperature: true,
+            sampling: Box::new(DynamicTemperature::default()),
             reason: self,
             result_can_be_none: false,
+            consensus: ConsensusStrategy::default(),
+            chunking: None,
+            ensemble: Vec::new(),
         }
     }
 }
@@ -252,15 +435,16 @@ impl<D: DecisionTrait> InstructPromptTrait for Decision<D> {
     }
 }
 
-#[derive(Clone)]
+/// Serializes with serde, including every vote's [`ReasonResult`] and its `CascadeFlow`.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DecisionResult {
-    pub votes: HashMap<u32, u8>,
+    pub votes: HashMap<u32, u16>,
     pub confidence: f32,
     pub duration: std::time::Duration,
     pub winner_primitive_result: Option<String>,
     pub reason_results: Vec<ReasonResult>,
-    pub total_votes: u8,
-    pub winner_votes: u8,
+    pub total_votes: u16,
+    pub winner_votes: u16,
     pub winner_index: Option<u32>,
 }
 
@@ -302,6 +486,7 @@ impl std::fmt::Display for DecisionResult {
             } else {
                 writeln!(f, "\x1b[38;5;32mprimitive_result\x1b[0m: None")?;
             };
+            writeln!(f, "\x1b[38;5;31mreason model\x1b[0m: {}", res.model_id)?;
             writeln!(f, "\x1b[38;5;31mreason duration\x1b[0m: {:?}", res.duration)?;
             writeln!(
                 f,
@@ -0,0 +1,147 @@
+use llm_interface::{llms::LlmBackend, requests::completion::CompletionRequest};
+use std::{collections::HashMap, sync::Arc};
+
+/// A backend that casts votes in an ensemble `Decision`.
+///
+/// Regular members share the votes in proportion to their `weight`, in the order they were added.
+/// Tie-breakers only vote once the regular members have cast the consensus strategy's votes and the leading results are tied,
+/// so a local model can do most of the voting, with a hosted model settling close calls.
+#[derive(Clone)]
+pub struct EnsembleMember {
+    pub backend: Arc<LlmBackend>,
+    /// The member's share of the votes, relative to the other members. Defaults to 1.0.
+    pub weight: f32,
+    pub tie_breaker: bool,
+}
+
+impl EnsembleMember {
+    pub fn new(backend: Arc<LlmBackend>) -> Self {
+        Self {
+            backend,
+            weight: 1.0,
+            tie_breaker: false,
+        }
+    }
+
+    pub fn weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn tie_breaker(mut self, tie_breaker: bool) -> Self {
+        self.tie_breaker = tie_breaker;
+        self
+    }
+
+    /// A request on the member's backend, with the decision's request settings.
+    pub(super) fn request(&self, base_req: &CompletionRequest) -> CompletionRequest {
+        let mut req = CompletionRequest::new(Arc::clone(&self.backend));
+        req.config = base_req.config.with_ctx_size_of(&req.config);
+        req
+    }
+}
+
+/// Picks the member for an attempt. Tie-breakers vote when `tied`, or when there are no regular members.
+/// Returns `None` for an empty ensemble.
+pub(super) fn member_for_attempt(
+    members: &[EnsembleMember],
+    attempt: u16,
+    tied: bool,
+) -> Option<&EnsembleMember> {
+    let voters: Vec<(f32, bool)> = members
+        .iter()
+        .map(|member| (member.weight, member.tie_breaker))
+        .collect();
+    voter_index(&voters, attempt, tied).map(|i| &members[i])
+}
+
+/// The index of the voter for an attempt, from each member's weight and whether it's a tie-breaker.
+fn voter_index(members: &[(f32, bool)], attempt: u16, tied: bool) -> Option<usize> {
+    let (tie_breakers, regular): (Vec<_>, Vec<_>) = (0..members.len()).partition(|&i| members[i].1);
+    let voters = if regular.is_empty() || (tied && !tie_breakers.is_empty()) {
+        tie_breakers
+    } else {
+        regular
+    };
+    if voters.is_empty() {
+        return None;
+    }
+    let weights: Vec<f32> = voters.iter().map(|&i| members[i].0).collect();
+    Some(voters[weighted_round_robin(&weights, attempt as usize)])
+}
+
+/// Whether the two leading results, counting none as a result, have the same number of votes.
+pub(super) fn is_tied(votes: &HashMap<u32, u16>, none_votes: u16) -> bool {
+    let mut counts: Vec<u16> = votes.values().copied().collect();
+    if none_votes > 0 {
+        counts.push(none_votes);
+    }
+    counts.sort_unstable_by(|a, b| b.cmp(a));
+    counts.len() > 1 && counts[0] == counts[1]
+}
+
+/// Smooth weighted round robin. Spreads each index's turns evenly, as in 0, 1, 0 for weights 2 and 1.
+fn weighted_round_robin(weights: &[f32], turn: usize) -> usize {
+    let weights: Vec<f32> = weights.iter().map(|weight| weight.max(0.0)).collect();
+    let total: f32 = weights.iter().sum();
+    if total <= 0.0 {
+        return turn % weights.len();
+    }
+    let mut current = vec![0.0; weights.len()];
+    let mut chosen = 0;
+    for _ in 0..=turn {
+        for (current, weight) in current.iter_mut().zip(&weights) {
+            *current += weight;
+        }
+        chosen = current.iter().enumerate().fold(
+            0,
+            |best, (i, value)| if *value > current[best] { i } else { best },
+        );
+        current[chosen] -= total;
+    }
+    chosen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weighted_round_robin() {
+        let turns: Vec<usize> = (0..6)
+            .map(|turn| weighted_round_robin(&[2.0, 1.0], turn))
+            .collect();
+        assert_eq!(turns, vec![0, 1, 0, 0, 1, 0]);
+        let turns: Vec<usize> = (0..4)
+            .map(|turn| weighted_round_robin(&[1.0, 1.0], turn))
+            .collect();
+        assert_eq!(turns, vec![0, 1, 0, 1]);
+        assert_eq!(weighted_round_robin(&[0.0, 0.0], 3), 1);
+    }
+
+    #[test]
+    fn test_is_tied() {
+        assert!(is_tied(&HashMap::from([(1, 2), (2, 2), (3, 1)]), 0));
+        assert!(is_tied(&HashMap::from([(1, 1)]), 1));
+        assert!(!is_tied(&HashMap::from([(1, 2), (2, 1)]), 1));
+        assert!(!is_tied(&HashMap::from([(1, 3)]), 0));
+        assert!(!is_tied(&HashMap::new(), 0));
+    }
+
+    #[test]
+    fn test_tie_breaker_votes_only_on_a_tie() {
+        let members = [(1.0, false), (1.0, false), (1.0, true)];
+        let voters = |tied: bool| -> Vec<Option<usize>> {
+            (0..4)
+                .map(|attempt| voter_index(&members, attempt, tied))
+                .collect()
+        };
+        assert_eq!(voters(false), vec![Some(0), Some(1), Some(0), Some(1)]);
+        assert_eq!(voters(true), vec![Some(2); 4]);
+
+        // Without regular members the tie-breakers vote, and without tie-breakers a tie goes to the regular members.
+        assert_eq!(voter_index(&[(1.0, true)], 0, false), Some(0));
+        assert_eq!(voter_index(&[(1.0, false)], 0, true), Some(0));
+        assert_eq!(voter_index(&[], 0, true), None);
+    }
+}
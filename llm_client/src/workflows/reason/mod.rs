@@ -1,21 +1,67 @@
+pub mod consensus;
+pub mod critique;
 pub mod decision;
+pub mod ensemble;
 pub mod one_round;
+pub mod sampling;
 
 use crate::{
-    components::{cascade::CascadeFlow, instruct_prompt::InstructPrompt},
+    components::{
+        cascade::{grammar_emulation::GrammarEmulation, step::StepConfig, CascadeFlow},
+        instruct_prompt::InstructPrompt,
+        InstructPromptTrait,
+    },
     primitives::*,
 };
-use llm_interface::{llms::LlmBackend, requests::completion::CompletionRequest};
+use critique::ReasonCritique;
+use decision::DecisionTrait;
+use llm_interface::{
+    llms::LlmBackend,
+    requests::{
+        completion::CompletionRequest,
+        req_components::{RequestConfig, RequestConfigTrait},
+    },
+};
 use one_round::ReasonOneRound;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 pub trait ReasonTrait: PrimitiveTrait {
-    fn primitive_to_result_index(&self, content: &str) -> u32;
+    /// The result index of a response. Errors if the response can't be parsed, which a `Decision` counts as a failed attempt.
+    fn primitive_to_result_index(&self, content: &str) -> crate::Result<u32>;
 
     fn result_index_to_primitive(
         &self,
         result_index: Option<u32>,
     ) -> crate::Result<Option<Self::PrimitiveResult>>;
 
+    /// Formats a result as shown in a `DecisionResult`.
+    fn result_to_string(&self, result: Self::PrimitiveResult) -> String;
+
+    /// The result of a result index as shown in a `DecisionResult`.
+    fn result_index_to_string(&self, result_index: u32) -> crate::Result<String> {
+        match self.result_index_to_primitive(Some(result_index))? {
+            Some(result) => Ok(self.result_to_string(result)),
+            None => crate::bail!("Decision: no winner"),
+        }
+    }
+
+    /// When true, a `Decision` collects all of its votes and picks the winner with `tally_votes`,
+    /// instead of stopping once one result has a majority.
+    fn collect_all_votes(&self) -> bool {
+        false
+    }
+
+    /// Picks the winning result index, and its votes, from a decision's votes. Returns `None` if there are no votes.
+    ///
+    /// By default the result with the most votes wins, with ties going to the lowest index.
+    fn tally_votes(&self, votes: &HashMap<u32, u16>, _total_votes: u16) -> Option<(u32, u16)> {
+        votes
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+            .map(|(result_index, votes)| (*result_index, *votes))
+    }
+
     fn parse_reason_result(
         &self,
         reason_result: &ReasonResult,
@@ -47,8 +93,14 @@ impl ReasonWorkflowBuilder {
             conclusion_sentences: 2,
             result_can_be_none: false,
             instruct_prompt: InstructPrompt::default(),
+            grammar_emulation: GrammarEmulation::default(),
         }
     }
+
+    /// A type implementing [`LlmChoice`], usually an enum with `#[derive(LlmPrimitive)]`.
+    pub fn derived<T: LlmChoice>(self) -> ReasonOneRound<DerivedPrimitive<T>> {
+        self.build()
+    }
 }
 
 macro_rules! reason_workflow_primitive_impl {
@@ -66,16 +118,257 @@ macro_rules! reason_workflow_primitive_impl {
 reason_workflow_primitive_impl! {
     boolean => BooleanPrimitive,
     integer => IntegerPrimitive,
-    exact_string => ExactStringPrimitive
+    exact_string => ExactStringPrimitive,
+    multi_choice => MultiChoicePrimitive,
+    float => FloatPrimitive,
+    signed_integer => SignedIntegerPrimitive,
+    date => DatePrimitive,
+    date_time => DateTimePrimitive,
+    duration => DurationPrimitive
+}
+
+/// The user's request, with any supporting material, as the task of a reasoning round.
+fn reason_task(instruct_prompt: &mut InstructPrompt) -> crate::Result<String> {
+    let instructions = instruct_prompt.build_instructions();
+    let supporting_material = instruct_prompt.build_supporting_material();
+
+    Ok(match (instructions, supporting_material) {
+        (Some(instructions), Some(supporting_material)) => {
+            format!("The user provided some supporting material: {supporting_material}\n The user's request is: {instructions}", )
+        }
+        (Some(instructions), None) => {
+            format!("The user's request is: {instructions}",)
+        }
+        (None, Some(supporting_material)) => {
+            format!("The user's request is: {supporting_material}",)
+        }
+        (None, None) => {
+            return Err(anyhow::format_err!(
+                "No instructions or supporting material provided."
+            ));
+        }
+    })
+}
+
+/// The methods and trait impls shared by the reasoning workflows, from the method that builds each workflow's flow.
+macro_rules! reason_workflow_impl {
+    ($($workflow:ident => $build_flow:ident),*) => {
+        $(
+            impl<P: PrimitiveTrait + ReasonTrait> $workflow<P> {
+                pub async fn return_primitive(&mut self) -> crate::Result<P::PrimitiveResult> {
+                    let res = self.return_result().await?;
+                    if let Some(primitive_result) =
+                        self.primitive.result_index_to_primitive(res.result_index)?
+                    {
+                        Ok(primitive_result)
+                    } else {
+                        Err(anyhow::format_err!("No result returned."))
+                    }
+                }
+
+                pub async fn return_optional_primitive(
+                    &mut self,
+                ) -> crate::Result<Option<P::PrimitiveResult>> {
+                    let res = self.return_optional_result().await?;
+                    self.primitive.result_index_to_primitive(res.result_index)
+                }
+
+                pub async fn return_result(&mut self) -> crate::Result<ReasonResult> {
+                    self.result_can_be_none = false;
+                    self.run().await
+                }
+
+                pub async fn return_optional_result(&mut self) -> crate::Result<ReasonResult> {
+                    self.result_can_be_none = true;
+                    self.run().await
+                }
+
+                pub fn reasoning_sentences(&mut self, reasoning_sentences: u8) -> &mut Self {
+                    self.reasoning_sentences = reasoning_sentences;
+                    self
+                }
+
+                pub fn conclusion_sentences(&mut self, conclusion_sentences: u8) -> &mut Self {
+                    self.conclusion_sentences = conclusion_sentences;
+                    self
+                }
+
+                /// Sets how many times to re-prompt backends without native grammar support when the solution fails validation.
+                pub fn grammar_retries(&mut self, max_retries: u8) -> &mut Self {
+                    self.grammar_emulation.max_retries = max_retries;
+                    self
+                }
+
+                async fn run(&mut self) -> crate::Result<ReasonResult> {
+                    let mut flow = match self.$build_flow() {
+                        Ok(flow) => flow,
+                        Err(e) => {
+                            crate::error!("Error creating {} flow: {}", stringify!($build_flow), e);
+                            return Err(e);
+                        }
+                    };
+                    flow.run_all_rounds(&mut self.base_req).await?;
+                    ReasonResult::new(flow, &self.primitive, &self.base_req)
+                }
+            }
+
+            impl<P: PrimitiveTrait> RequestConfigTrait for $workflow<P> {
+                fn config(&mut self) -> &mut RequestConfig {
+                    &mut self.base_req.config
+                }
+
+                fn reset_request(&mut self) {
+                    self.instruct_prompt.reset_instruct_prompt();
+                    self.base_req.reset_completion_request();
+                }
+            }
+
+            impl<P: PrimitiveTrait + ReasonTrait> InstructPromptTrait for $workflow<P> {
+                fn instruct_prompt_mut(&mut self) -> &mut InstructPrompt {
+                    &mut self.instruct_prompt
+                }
+            }
+
+            impl<P: PrimitiveTrait + ReasonTrait> DecisionTrait for $workflow<P> {
+                type ReasonPrimitive = P;
+                fn base_req(&self) -> &CompletionRequest {
+                    &self.base_req
+                }
+
+                fn base_req_mut(&mut self) -> &mut CompletionRequest {
+                    &mut self.base_req
+                }
+
+                fn primitive(&self) -> &Self::ReasonPrimitive {
+                    &self.primitive
+                }
+
+                async fn return_reason_result(
+                    &mut self,
+                    result_can_be_none: bool,
+                ) -> crate::Result<ReasonResult> {
+                    if result_can_be_none {
+                        self.return_optional_result().await
+                    } else {
+                        self.return_result().await
+                    }
+                }
+            }
+        )*
+    }
+}
+
+reason_workflow_impl! {
+    ReasonOneRound => reason_one_round,
+    ReasonCritique => reason_critique
+}
+
+const REASON_INSTRUCTIONS: &str = "A request will be provided. Think out loud about the request. State the arguments before arriving at a conclusion with, 'Therefore, we can conclude:...'";
+
+const NO_YAPPING_GUIDANCE: &str = "'no yapping' refers to a design principle or behavior where the AI model provides direct, concise responses without unnecessary verbosity or filler content. Therefore, we can conclude: The user would like to get straight to the point.";
+
+/// Starts a reasoning flow with a round explaining the format of the answer.
+/// With `with_solution`, the answer finishes with a solution after the conclusion.
+fn add_guidance_round(flow: &mut CascadeFlow, with_solution: bool) {
+    let (task, guidance) = if with_solution {
+        (
+            format!("{REASON_INSTRUCTIONS}, and finish with a solution by saying, 'Thus, the solution...'. With no yapping."),
+            format!("{NO_YAPPING_GUIDANCE} Thus, the solution is to to resolve the request as efficiently as possible."),
+        )
+    } else {
+        (
+            format!("{REASON_INSTRUCTIONS}. With no yapping."),
+            NO_YAPPING_GUIDANCE.to_owned(),
+        )
+    };
+    flow.new_round(task)
+        .add_guidance_step(&StepConfig::default(), guidance);
+}
+
+/// Thinking out loud about the request, up to 'Therefore, we can conclude'.
+fn reasoning_step_config(reasoning_sentences: u8) -> StepConfig {
+    StepConfig {
+        step_prefix: Some("Thinking out loud about the users request...".to_string()),
+        stop_word_done: "Therefore, we can conclude".to_string(),
+        cache_prompt: false, // Clears the cache on the initial request
+        grammar: SentencesPrimitive::default()
+            .min_count(1)
+            .max_count(reasoning_sentences)
+            .grammar(),
+        ..StepConfig::default()
+    }
+}
+
+/// The conclusion of the reasoning, up to 'Thus, the solution'.
+fn conclusion_step_config<P: PrimitiveTrait>(
+    primitive: &P,
+    result_can_be_none: bool,
+    conclusion_sentences: u8,
+) -> StepConfig {
+    StepConfig {
+        step_prefix: Some(format!(
+            "The user requested a conclusion of {}. Therefore, we can conclude:",
+            primitive.solution_description(result_can_be_none),
+        )),
+        stop_word_done: "Thus, the solution".to_string(),
+        grammar: SentencesPrimitive::default()
+            .min_count(1)
+            .max_count(conclusion_sentences)
+            .grammar(),
+        ..StepConfig::default()
+    }
+}
+
+/// Restates the user's instructions, if any, then adds the solution step, constrained by the primitive's grammar, to the last round.
+fn add_solution_steps<P: PrimitiveTrait>(
+    flow: &mut CascadeFlow,
+    primitive: &P,
+    result_can_be_none: bool,
+    instruct_prompt: &mut InstructPrompt,
+    grammar_emulation: &GrammarEmulation,
+) -> crate::Result<()> {
+    // Instructions Restatement
+    if let Some(instructions) = instruct_prompt.build_instructions() {
+        let instructions_restatement =
+            format!("The user's original request was '{}'.", &instructions,);
+        let step_config = StepConfig {
+            step_prefix: None,
+            grammar: SentencesPrimitive::default().grammar(),
+            ..StepConfig::default()
+        };
+        flow.last_round()?
+            .add_guidance_step(&step_config, instructions_restatement);
+    };
+
+    // Solution
+    let solution = format!(
+        "Thus, the {} solution to the user's request is:",
+        primitive.type_description(result_can_be_none),
+    );
+    let step_config = StepConfig {
+        step_prefix: Some(solution),
+        stop_word_no_result: primitive.stop_word_result_is_none(result_can_be_none),
+        grammar: primitive.grammar(),
+        grammar_emulation: grammar_emulation.clone(),
+        ..StepConfig::default()
+    };
+    flow.last_round()?.add_inference_step(&step_config);
+    Ok(())
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ReasonResult {
     pub primitive_result: Option<String>,
     pub duration: std::time::Duration,
     pub workflow: CascadeFlow,
     pub result_index: Option<u32>,
     pub temperature: f32,
+    /// The sampling settings of the request, including any prompt perturbation from a `Decision`'s sampling schedule.
+    pub sampling: sampling::VoteSampling,
+    /// The mean logprob of the answer's tokens, when the backend returns token probabilities.
+    pub logprob: Option<f32>,
+    /// The model that gave the answer.
+    pub model_id: String,
 }
 
 impl ReasonResult {
@@ -87,13 +380,17 @@ impl ReasonResult {
         let primitive_result = flow.primitive_result();
         let result_index = primitive_result
             .as_ref()
-            .map(|primitive_result| primitive.primitive_to_result_index(primitive_result));
+            .map(|primitive_result| primitive.primitive_to_result_index(primitive_result))
+            .transpose()?;
         Ok(ReasonResult {
             primitive_result,
             duration: flow.duration,
+            logprob: flow.primitive_logprob(),
+            model_id: base_req.backend.model_id().to_owned(),
             workflow: flow,
             result_index,
             temperature: base_req.config.temperature,
+            sampling: sampling::VoteSampling::from_config(&base_req.config),
         })
     }
 }
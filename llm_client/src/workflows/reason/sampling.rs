@@ -0,0 +1,300 @@
+use llm_interface::{
+    llms::capabilities::BackendCapabilities, requests::req_components::RequestConfig,
+};
+use serde::{Deserialize, Serialize};
+
+const DYNAMIC_TEMPERATURE_MIN: f32 = 0.11;
+const DYNAMIC_TEMPERATURE_MAX: f32 = 1.89;
+
+/// The sampling settings used for one vote of a `Decision`. Recorded in each `ReasonResult`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VoteSampling {
+    pub temperature: f32,
+    pub top_p: Option<f32>,
+    pub seed: Option<u64>,
+    /// Text added to the instructions for this vote only.
+    pub prompt_perturbation: Option<String>,
+}
+
+impl VoteSampling {
+    pub fn from_config(config: &RequestConfig) -> Self {
+        Self {
+            temperature: config.temperature,
+            top_p: config.top_p,
+            seed: config.seed,
+            prompt_perturbation: None,
+        }
+    }
+
+    /// Drops a seed the backend doesn't support, so the request is valid and the recorded sampling is what was sent.
+    pub(super) fn supported_by(mut self, capabilities: &BackendCapabilities) -> Self {
+        if !capabilities.sampler_params.seed {
+            self.seed = None;
+        }
+        self
+    }
+
+    pub(super) fn apply_to_config(&self, config: &mut RequestConfig) {
+        config.temperature = self.temperature;
+        config.top_p = self.top_p;
+        config.seed = self.seed;
+    }
+}
+
+/// The state of a `Decision` before a vote.
+#[derive(Debug, Clone)]
+pub struct VoteContext {
+    /// The request's own sampling settings.
+    pub base: VoteSampling,
+    /// Attempts so far, including the ones that failed to give a valid vote. `0` for the first vote.
+    pub attempt: u16,
+    pub total_votes: u16,
+    pub failed_attempts: u8,
+    /// Whether the previous attempt failed to give a valid vote.
+    pub last_attempt_failed: bool,
+    /// The votes for the leading result.
+    pub leader_votes: u16,
+    /// The most votes the consensus strategy collects.
+    pub votes_to_collect: u16,
+    /// The votes a result needs to win early, for strategies that stop at a majority.
+    pub votes_required_to_win: Option<u16>,
+}
+
+/// Decides the sampling settings for each vote of a `Decision`.
+///
+/// Set with `Decision::sampling`. A schedule is used for every decision the `Decision` runs,
+/// so stateful schedules should reset when `VoteContext::attempt` is `0`.
+pub trait SamplingSchedule: std::fmt::Debug + Send + Sync {
+    fn vote_sampling(&mut self, context: &VoteContext) -> VoteSampling;
+}
+
+/// Every vote uses the request's settings.
+///
+/// When `seed` is set, each attempt uses `seed + attempt`, so the votes differ but a run can be repeated.
+/// Backends that don't support a seed get none.
+#[derive(Debug, Clone, Default)]
+pub struct FixedSampling {
+    pub seed: Option<u64>,
+}
+
+impl SamplingSchedule for FixedSampling {
+    fn vote_sampling(&mut self, context: &VoteContext) -> VoteSampling {
+        VoteSampling {
+            seed: attempt_seed(self.seed, context).or(context.base.seed),
+            ..context.base.clone()
+        }
+    }
+}
+
+/// Raises the temperature evenly from `min_temperature` on the first vote to `max_temperature` on the last.
+#[derive(Debug, Clone)]
+pub struct LinearRampSampling {
+    pub min_temperature: f32,
+    pub max_temperature: f32,
+    /// When set, each attempt uses `seed + attempt`.
+    pub seed: Option<u64>,
+}
+
+impl Default for LinearRampSampling {
+    fn default() -> Self {
+        Self {
+            min_temperature: DYNAMIC_TEMPERATURE_MIN,
+            max_temperature: DYNAMIC_TEMPERATURE_MAX,
+            seed: None,
+        }
+    }
+}
+
+impl SamplingSchedule for LinearRampSampling {
+    fn vote_sampling(&mut self, context: &VoteContext) -> VoteSampling {
+        let steps = context.votes_to_collect.saturating_sub(1).max(1) as f32;
+        let progress = (context.total_votes as f32 / steps).min(1.0);
+        VoteSampling {
+            temperature: self.min_temperature
+                + (self.max_temperature - self.min_temperature) * progress,
+            seed: attempt_seed(self.seed, context).or(context.base.seed),
+            ..context.base.clone()
+        }
+    }
+}
+
+/// The default schedule. Starts at a low temperature and raises it as votes fail to agree.
+///
+/// For strategies that stop at a majority, the temperature rises faster the closer the leading result is to winning,
+/// reaching `max_temperature` when it is one vote away. Otherwise it rises evenly with each vote.
+/// Each failed attempt adds `min_temperature`.
+#[derive(Debug, Clone)]
+pub struct DynamicTemperature {
+    pub min_temperature: f32,
+    pub max_temperature: f32,
+    temperature: f32,
+}
+
+impl Default for DynamicTemperature {
+    fn default() -> Self {
+        Self {
+            min_temperature: DYNAMIC_TEMPERATURE_MIN,
+            max_temperature: DYNAMIC_TEMPERATURE_MAX,
+            temperature: DYNAMIC_TEMPERATURE_MIN,
+        }
+    }
+}
+
+impl SamplingSchedule for DynamicTemperature {
+    fn vote_sampling(&mut self, context: &VoteContext) -> VoteSampling {
+        if context.attempt == 0 {
+            self.temperature = if context.votes_to_collect > 1 {
+                self.min_temperature
+            } else {
+                context.base.temperature
+            };
+        } else if context.last_attempt_failed {
+            self.temperature += self.min_temperature;
+        } else if let Some(votes_required_to_win) = context.votes_required_to_win {
+            let minimum_votes_remaining =
+                votes_required_to_win.saturating_sub(context.leader_votes);
+            if minimum_votes_remaining <= 1 {
+                self.temperature = self.max_temperature;
+            } else {
+                let maybe_average_votes_remaining =
+                    (votes_required_to_win + minimum_votes_remaining) as f32 / 2.0;
+                self.temperature +=
+                    (self.max_temperature - self.temperature) / maybe_average_votes_remaining;
+            }
+        } else {
+            self.temperature = self.min_temperature
+                + (self.max_temperature - self.min_temperature) * context.total_votes as f32
+                    / context.votes_to_collect.max(1) as f32;
+        }
+        VoteSampling {
+            temperature: self.temperature,
+            ..context.base.clone()
+        }
+    }
+}
+
+/// Adds a different note to the instructions for each vote after the first, on top of another schedule.
+/// Varying the prompt gives more independent votes than varying the temperature alone.
+#[derive(Debug)]
+pub struct PromptPerturbation {
+    pub perturbations: Vec<String>,
+    pub schedule: Box<dyn SamplingSchedule>,
+}
+
+impl Default for PromptPerturbation {
+    fn default() -> Self {
+        Self {
+            perturbations: [
+                "Take a fresh look at the request before answering.",
+                "Consider the request from a different angle.",
+                "Check each detail of the request carefully.",
+                "Answer as a careful expert would.",
+            ]
+            .into_iter()
+            .map(str::to_owned)
+            .collect(),
+            schedule: Box::new(FixedSampling::default()),
+        }
+    }
+}
+
+impl PromptPerturbation {
+    pub fn new<S: SamplingSchedule + 'static>(schedule: S) -> Self {
+        Self {
+            schedule: Box::new(schedule),
+            ..Default::default()
+        }
+    }
+
+    pub fn perturbations<T: Into<String>>(
+        &mut self,
+        perturbations: impl IntoIterator<Item = T>,
+    ) -> &mut Self {
+        self.perturbations = perturbations.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+impl SamplingSchedule for PromptPerturbation {
+    fn vote_sampling(&mut self, context: &VoteContext) -> VoteSampling {
+        let mut sampling = self.schedule.vote_sampling(context);
+        if context.attempt > 0 && !self.perturbations.is_empty() {
+            let index = (context.attempt as usize - 1) % self.perturbations.len();
+            sampling.prompt_perturbation = Some(self.perturbations[index].clone());
+        }
+        sampling
+    }
+}
+
+fn attempt_seed(seed: Option<u64>, context: &VoteContext) -> Option<u64> {
+    seed.map(|seed| seed.wrapping_add(context.attempt as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(attempt: u16, total_votes: u16, leader_votes: u16) -> VoteContext {
+        VoteContext {
+            base: VoteSampling {
+                temperature: 0.7,
+                ..Default::default()
+            },
+            attempt,
+            total_votes,
+            failed_attempts: (attempt - total_votes) as u8,
+            last_attempt_failed: false,
+            leader_votes,
+            votes_to_collect: 5,
+            votes_required_to_win: Some(3),
+        }
+    }
+
+    #[test]
+    fn test_sampling_schedules() {
+        let mut fixed = FixedSampling { seed: Some(10) };
+        let sampling = fixed.vote_sampling(&context(2, 2, 1));
+        assert_eq!((sampling.temperature, sampling.seed), (0.7, Some(12)));
+        let openai = sampling
+            .clone()
+            .supported_by(&BackendCapabilities::openai());
+        assert_eq!(openai.seed, Some(12));
+        let anthropic = sampling.supported_by(&BackendCapabilities::anthropic());
+        assert_eq!(anthropic.seed, None);
+
+        let mut ramp = LinearRampSampling {
+            min_temperature: 0.0,
+            max_temperature: 1.0,
+            seed: None,
+        };
+        assert_eq!(ramp.vote_sampling(&context(0, 0, 0)).temperature, 0.0);
+        assert_eq!(ramp.vote_sampling(&context(2, 2, 1)).temperature, 0.5);
+        assert_eq!(ramp.vote_sampling(&context(4, 4, 2)).temperature, 1.0);
+
+        let mut dynamic = DynamicTemperature::default();
+        assert_eq!(
+            dynamic.vote_sampling(&context(0, 0, 0)).temperature,
+            DYNAMIC_TEMPERATURE_MIN
+        );
+        assert_eq!(
+            dynamic.vote_sampling(&context(2, 2, 2)).temperature,
+            DYNAMIC_TEMPERATURE_MAX
+        );
+
+        let mut perturbation = PromptPerturbation::new(FixedSampling::default());
+        perturbation.perturbations(["a", "b"]);
+        assert_eq!(
+            perturbation
+                .vote_sampling(&context(0, 0, 0))
+                .prompt_perturbation,
+            None
+        );
+        assert_eq!(
+            perturbation
+                .vote_sampling(&context(3, 3, 1))
+                .prompt_perturbation
+                .as_deref(),
+            Some("a")
+        );
+    }
+}
@@ -55,6 +55,42 @@ mod basic_primitive_unit_tests {
         exact_string_optional_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
         Ok(())
     }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn regex() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        regex_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn regex_optional() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        regex_optional_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn list() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        list_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn derived() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        derived_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
 }
 
 pub(super) async fn run(llm_client: &LlmClient, test_level: &TestLevel) -> crate::Result<()> {
@@ -201,6 +237,69 @@ pub(super) async fn exact_string_optional_integration_tester(
     Ok(())
 }
 
+pub(super) async fn regex_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client.basic_primitive().regex(r"[A-Z]{3}-\d{4}")?;
+    gen.primitive.description("a SKU, like ABC-1234");
+    gen.instructions()
+        .set_content("What is the SKU of the blue widget?");
+    gen.supporting_material()
+        .set_content("Red widget: RED-0001. Blue widget: BLU-0042. Green widget: GRN-0107.");
+    let res = gen.return_primitive().await?;
+    print_results(&gen.base_req.prompt, &None::<String>, &Some(res.clone()));
+    assert_eq!(res, "BLU-0042");
+    Ok(())
+}
+
+pub(super) async fn regex_optional_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client.basic_primitive().regex(r"[A-Z]{2}")?;
+    gen.primitive
+        .description("an ISO 3166-1 alpha-2 country code");
+    gen.instructions()
+        .set_content("Which country is the company headquartered in?");
+    gen.supporting_material()
+        .set_content("The company sells garden furniture through its website.");
+    let res = gen.return_optional_primitive().await?;
+    print_results(&gen.base_req.prompt, &None::<String>, &Some(res.clone()));
+    assert_eq!(res, None);
+    Ok(())
+}
+
+pub(super) async fn list_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client
+        .basic_primitive()
+        .list::<llm_client::primitives::IntegerPrimitive>();
+    gen.primitive.min_count(3).max_count(3);
+    gen.primitive.item.upper_bound(100);
+    gen.instructions()
+        .set_content("List the first three even numbers greater than zero.");
+    let res = gen.return_primitive().await?;
+    print_results(&gen.base_req.prompt, &None::<String>, &Some(res.clone()));
+    assert_eq!(res, vec![2, 4, 6]);
+    gen.reset_request();
+
+    let mut gen = llm_client
+        .basic_primitive()
+        .list::<llm_client::primitives::ExactStringPrimitive>();
+    gen.primitive.unique(true);
+    gen.primitive
+        .item
+        .add_strings_to_allowed(&["apple", "carrot", "banana", "potato"]);
+    gen.instructions().set_content("Which of these are fruits?");
+    let res = gen.return_primitive().await?;
+    print_results(&gen.base_req.prompt, &None::<String>, &Some(res.clone()));
+    assert!(res.contains(&"apple".to_owned()) && res.contains(&"banana".to_owned()));
+    Ok(())
+}
+
 // fn sentences_primitive_test_questions() -> Vec<String> {
 //     vec![
 //         "Do scientist believe the moon is made of cheese?".to_string(),
@@ -264,3 +363,46 @@ pub(super) async fn exact_string_optional_integration_tester(
 //         gen.reset_request();
 //     }
 // }
+
+#[derive(Debug, PartialEq, llm_client::primitives::LlmPrimitive)]
+pub(super) enum Sentiment {
+    /// The text is favorable.
+    Positive,
+    /// The text is neither favorable nor unfavorable.
+    Neutral,
+    /// The text is unfavorable.
+    Negative,
+}
+
+#[derive(Debug, PartialEq, llm_client::primitives::LlmPrimitive)]
+struct Review {
+    /// The reviewed product.
+    product: String,
+    /// From 1 to 5.
+    stars: u8,
+    sentiment: Sentiment,
+}
+
+pub(super) async fn derived_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client.basic_primitive().derived::<Sentiment>();
+    gen.instructions().set_content(
+        "What is the sentiment of: 'This blender is the best purchase I made all year.'",
+    );
+    let res = gen.return_primitive().await?;
+    print_results(&gen.base_req.prompt, &None::<String>, &Some(&res));
+    assert_eq!(res, Sentiment::Positive);
+    gen.reset_request();
+
+    let mut gen = llm_client.basic_primitive().derived::<Review>();
+    gen.instructions().set_content(
+        "Extract the review: 'Two stars. The kettle leaks and the lid broke in a week.'",
+    );
+    let res = gen.return_primitive().await?;
+    print_results(&gen.base_req.prompt, &None::<String>, &Some(&res));
+    assert_eq!(res.stars, 2);
+    assert_eq!(res.sentiment, Sentiment::Negative);
+    Ok(())
+}
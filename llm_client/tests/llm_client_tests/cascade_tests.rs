@@ -0,0 +1,115 @@
+use super::*;
+use llm_client::{
+    components::cascade::{step::StepConfig, CascadeFlow},
+    primitives::{BooleanPrimitive, IntegerPrimitive},
+};
+
+mod cascade_unit_tests {
+    use super::*;
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn conditional() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        conditional_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn repeat() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        repeat_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+}
+
+pub(super) async fn conditional_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut base_req = CompletionRequest::new(llm_client.backend.clone());
+    let boolean = StepConfig {
+        step_prefix: Some("The answer is:".to_string()),
+        grammar: BooleanPrimitive::default().grammar(),
+        ..StepConfig::default()
+    };
+
+    let mut flow = CascadeFlow::new("Conditional");
+    flow.new_round("Is the sky blue on a clear day? Answer true or false.")
+        .add_inference_step(&boolean);
+    // Only runs when the first answer was true.
+    flow.new_round("Is the sky blue on a cloudy day? Answer true or false.")
+        .run_if(|rounds| rounds[0].step_result(0).as_deref() == Some("true"))
+        .add_inference_step(&boolean);
+    // Never runs.
+    flow.new_round("Is grass blue? Answer true or false.")
+        .run_if(|_| false)
+        .add_inference_step(&boolean);
+    flow.run_all_rounds(&mut base_req).await?;
+    println!("{flow}");
+
+    assert_eq!(flow.rounds[0].step_result(0).as_deref(), Some("true"));
+    assert!(!flow.rounds[1].skipped);
+    assert!(flow.rounds[2].skipped);
+    assert!(flow.rounds[2].resolved_steps.is_empty());
+    // Skipped rounds are passed over for the result.
+    assert_eq!(flow.primitive_result(), flow.rounds[1].step_result(0));
+
+    let mut base_req = CompletionRequest::new(llm_client.backend.clone());
+    let mut flow = CascadeFlow::new("Conditional step");
+    let round = flow.new_round("Is the sky blue on a clear day? Answer true or false.");
+    round.add_inference_step(&boolean);
+    round.add_inference_step_if(
+        &StepConfig {
+            step_prefix: Some("Confidence from 1 to 10:".to_string()),
+            grammar: IntegerPrimitive::default()
+                .lower_bound(1)
+                .upper_bound(10)
+                .grammar(),
+            ..StepConfig::default()
+        },
+        |round| round.step_result(0).as_deref() == Some("false"),
+    );
+    flow.run_all_rounds(&mut base_req).await?;
+    assert_eq!(flow.rounds[0].resolved_steps.len(), 1);
+    Ok(())
+}
+
+pub(super) async fn repeat_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut base_req = CompletionRequest::new(llm_client.backend.clone());
+    let mut flow = CascadeFlow::new("Repeat");
+    flow.new_round("Count up from 1, one number at a time.")
+        .repeat_until(10, |round| round.resolved_steps.len() >= 3)
+        .add_inference_step(&StepConfig {
+            grammar: IntegerPrimitive::default()
+                .lower_bound(1)
+                .upper_bound(9)
+                .grammar(),
+            ..StepConfig::default()
+        });
+    flow.run_all_rounds(&mut base_req).await?;
+    println!("{flow}");
+    assert_eq!(flow.rounds[0].resolved_steps.len(), 3);
+
+    let mut base_req = CompletionRequest::new(llm_client.backend.clone());
+    let mut flow = CascadeFlow::new("Early exit");
+    flow.new_round(
+        "Is the sky blue on a clear day? Answer true or false, and stop once you say true.",
+    )
+    .repeat_until(5, |_| false)
+    .exit_when(|round| round.step_result(round.resolved_steps.len() - 1).as_deref() == Some("true"))
+    .add_inference_step(&StepConfig {
+        grammar: BooleanPrimitive::default().grammar(),
+        ..StepConfig::default()
+    });
+    flow.run_all_rounds(&mut base_req).await?;
+    println!("{flow}");
+    assert!(flow.rounds[0].resolved_steps.len() < 5);
+    assert!(flow.rounds[0].unresolved_steps.is_empty());
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use super::*;
+
+mod classify_unit_tests {
+    use super::*;
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn label_hierarchy() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        label_hierarchy_integration_tester(&llm_client).await?;
+        Ok(())
+    }
+}
+
+pub(super) async fn label_hierarchy_integration_tester(
+    llm_client: &LlmClient,
+) -> crate::Result<()> {
+    let tags = TagSystem::create_from_json(
+        r#"{
+            "aquatic": {"fresh water": ["lake", "river", "wetland"], "marine": ["ocean", "reef"]},
+            "terrestrial": {"forest": null, "desert": null, "mountain": null},
+            "host-associated": ["animal", "plant"]
+        }"#,
+    )?;
+    let cases = [
+        ("A green turtle on a log in a mountain lake.", "aquatic"),
+        ("A cactus in the dry sand dunes.", "terrestrial"),
+    ];
+    for (content, expected_root) in cases {
+        let result = llm_client
+            .nlp()
+            .classify()
+            .labels(content, &tags)
+            .best_of_n_votes(3)
+            .run()
+            .await?;
+        println!("{result}");
+        assert_eq!(result.labels().first().copied(), Some(expected_root));
+        for label in &result.path {
+            assert!(label.confidence > 0.0 && label.confidence <= 1.0);
+        }
+    }
+    Ok(())
+}
@@ -55,6 +55,87 @@ mod decision_unit_tests {
         exact_string_optional_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
         Ok(())
     }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn multi_choice() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        multi_choice_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn float() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        float_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn signed_integer() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        signed_integer_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn date() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        date_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn duration() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        duration_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn derived() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        derived_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn consensus() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        consensus_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn sampling() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        sampling_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn ensemble() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        ensemble_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
 }
 
 pub(super) async fn run(llm_client: &LlmClient, test_level: &TestLevel) -> crate::Result<()> {
@@ -206,3 +287,173 @@ pub(super) async fn exact_string_optional_integration_tester(
     tests.check_results();
     Ok(())
 }
+
+pub(super) async fn multi_choice_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client.reason().multi_choice().decision();
+    gen.reason
+        .primitive
+        .add_strings_to_allowed(&["apple", "carrot", "banana", "potato"])?;
+    gen.instructions()
+        .set_content("Which of these are fruits? Select all that apply.");
+    let result = gen.return_result().await?;
+    let res = gen.parse_decision_result(&result)?;
+    print_results(&gen.reason.base_req.prompt, &Some(result), &res);
+    assert_eq!(res, Some(vec!["apple".to_owned(), "banana".to_owned()]));
+    Ok(())
+}
+
+pub(super) async fn float_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client.reason().float().decision();
+    gen.reason.primitive.lower_bound(0.0).upper_bound(1.0);
+    gen.instructions()
+        .set_content("What fraction of a pizza is left after eating one of four equal slices?");
+    let result = gen.return_result().await?;
+    let res = gen.parse_decision_result(&result)?;
+    print_results(&gen.reason.base_req.prompt, &Some(result), &res);
+    assert_eq!(res, Some(0.75));
+    Ok(())
+}
+
+pub(super) async fn signed_integer_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client.reason().signed_integer().decision();
+    gen.reason.primitive.lower_bound(-100).upper_bound(100);
+    gen.instructions().set_content(
+        "The temperature was 5 degrees in the morning and -3 degrees at night. By how many degrees did it change?",
+    );
+    let result = gen.return_result().await?;
+    let res = gen.parse_decision_result(&result)?;
+    print_results(&gen.reason.base_req.prompt, &Some(result), &res);
+    assert_eq!(res, Some(-8));
+    Ok(())
+}
+
+pub(super) async fn date_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client.reason().date().decision();
+    gen.instructions()
+        .set_content("What is the contract start date?");
+    gen.supporting_material().set_content(
+        "This agreement is signed on March 3rd, 2024 and takes effect on the first day of the following month.",
+    );
+    let result = gen.return_result().await?;
+    let res = gen.parse_decision_result(&result)?;
+    print_results(&gen.reason.base_req.prompt, &Some(result), &res);
+    assert_eq!(res, llm_client::chrono::NaiveDate::from_ymd_opt(2024, 4, 1));
+    Ok(())
+}
+
+pub(super) async fn duration_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client.reason().duration().decision();
+    gen.instructions()
+        .set_content("How long is the meeting, if it starts at 9:15 and ends at 10:45?");
+    let result = gen.return_result().await?;
+    let res = gen.parse_decision_result(&result)?;
+    print_results(&gen.reason.base_req.prompt, &Some(result), &res);
+    assert_eq!(res, Some(llm_client::chrono::TimeDelta::minutes(90)));
+    Ok(())
+}
+
+pub(super) async fn derived_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    use super::basic_primitive_tests::Sentiment;
+    let mut gen = llm_client.reason().derived::<Sentiment>().decision();
+    gen.instructions().set_content(
+        "What is the sentiment of: 'The hotel was fine. Nothing special, nothing bad.'",
+    );
+    let result = gen.return_result().await?;
+    let res = gen.parse_decision_result(&result)?;
+    print_results(&gen.reason.base_req.prompt, &Some(result), &res);
+    assert_eq!(res, Some(Sentiment::Neutral));
+    Ok(())
+}
+
+pub(super) async fn consensus_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client.reason().boolean().decision();
+    gen.consensus(ConsensusStrategy::Unanimous);
+    gen.instructions()
+        .set_content("Is the sky blue on a clear day?");
+    let result = gen.return_result().await?;
+    let res = gen.parse_decision_result(&result)?;
+    print_results(&gen.reason.base_req.prompt, &Some(result), &res);
+    assert_eq!(res, Some(true));
+    gen.reset_request();
+
+    // A confidence above 1.0 can never be reached.
+    gen.consensus(ConsensusStrategy::MinConfidence { threshold: 1.1 });
+    gen.instructions()
+        .set_content("Is the sky blue on a clear day?");
+    let err = gen.return_result().await.err().expect("no consensus");
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(DecisionError::NoConsensus { total_votes: 3, .. })
+    ));
+    Ok(())
+}
+
+pub(super) async fn sampling_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client.reason().boolean().decision();
+    gen.consensus(ConsensusStrategy::Plurality { min_votes: 3 })
+        .sampling(PromptPerturbation::new(LinearRampSampling {
+            seed: Some(42),
+            ..Default::default()
+        }));
+    gen.instructions()
+        .set_content("Is the sky blue on a clear day?");
+    let result = gen.return_result().await?;
+    let res = gen.parse_decision_result(&result)?;
+    print_results(&gen.reason.base_req.prompt, &Some(result.clone()), &res);
+    assert_eq!(res, Some(true));
+    assert_eq!(result.reason_results.len(), 3);
+    let first = &result.reason_results[0].sampling;
+    let last = &result.reason_results[2].sampling;
+    assert!(first.temperature < last.temperature);
+    assert_eq!(first.prompt_perturbation, None);
+    assert!(last.prompt_perturbation.is_some());
+    assert_ne!(first.seed, last.seed);
+    Ok(())
+}
+
+pub(super) async fn ensemble_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client.reason().boolean().decision();
+    gen.consensus(ConsensusStrategy::Plurality { min_votes: 3 })
+        .add_ensemble_member(llm_client.backend.clone(), 2.0)
+        .add_ensemble_member(llm_client.backend.clone(), 1.0);
+    gen.instructions()
+        .set_content("Is the sky blue on a clear day?");
+    let result = gen.return_result().await?;
+    let res = gen.parse_decision_result(&result)?;
+    print_results(&gen.reason.base_req.prompt, &Some(result.clone()), &res);
+    assert_eq!(res, Some(true));
+    assert_eq!(result.reason_results.len(), 3);
+    let model_id = llm_client.backend.model_id();
+    assert!(result
+        .reason_results
+        .iter()
+        .all(|reason_result| reason_result.model_id == model_id));
+    Ok(())
+}
@@ -10,6 +10,38 @@ mod extract_unit_tests {
         extract_urls_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
         Ok(())
     }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn extract_entities() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        extract_entities_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn extract_urls_chunked() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        let mut gen = llm_client.nlp().extract().urls();
+        gen.instructions()
+            .set_content("Extract the URLs of documentation sites.");
+        let page = "<p>Filler text about nothing in particular.</p>".repeat(100);
+        gen.supporting_material().set_content(format!(
+            "<div><p>See https://docs.rs for docs.</p>{page}<p>And https://doc.rust-lang.org too.</p></div>"
+        ));
+        gen.chunking(
+            ChunkingConfig::new(ChunkBy::HtmlBlocks)
+                .max_chunk_tokens(256)
+                .overlap_tokens(32),
+        );
+        let result = gen.run_return_result().await?;
+        println!("{result}");
+        assert!(!result.chunk_workflows.is_empty());
+        Ok(())
+    }
 }
 
 pub(super) async fn extract_urls_integration_tester(
@@ -42,39 +74,34 @@ pub(super) async fn extract_urls_integration_tester(
     Ok(())
 }
 
-// #[tokio::test]
-// #[serial]
-// async fn extract_entities_test() {
-//     let llm_client = get_tiny_llm().await.unwrap();
-//     let tests: Vec<ExtractEntitiesTest> = TestSetsLoader::default()
-//         .optional(true)
-//         .test_level_zero()
-//         .extract_entities()
-//         .unwrap();
+pub(super) async fn extract_entities_integration_tester(
+    llm_client: &LlmClient,
+    test_level: &TestLevel,
+) -> crate::Result<()> {
+    let tests = TestSetsLoader::new()
+        .optional(true)
+        .test_level_enum(test_level)
+        .extract_entities()?;
+
+    let mut test_results = Vec::new();
+    for test in tests {
+        let mut gen = llm_client.nlp().extract().entities();
+        gen.schema(EntitySchema::person().field("role", "their job, title or achievement"))
+            .schema(EntitySchema::organization());
+        gen.supporting_material()
+            .set_content(&test.supporting_material);
+        let result = gen.run_return_result().await.unwrap();
+        print_results(
+            &gen.base_req.prompt,
+            &Some(result.clone()),
+            &Some(result.results.clone()),
+        );
+        test_results.push((test, result));
+    }
+
+    test_results.into_iter().for_each(|(test, result)| {
+        test.check_result(result);
+    });
 
-//     let mut test_results: Vec<(
-//         ExtractEntitiesTest,
-//         llm_client::workflows::nlp::extract::_entities::ExtractEntitiesResult,
-//     )> = Vec::new();
-//     for test in tests {
-//         let mut gen = llm_client.nlp().extract().entities().entity_type("topic");
-//         gen.supporting_material()
-//             .set_content(&test.supporting_material);
-//         let result = gen.run_return_result().await.unwrap();
-//         match (PRINT_PROMPT, PRINT_RESULT) {
-//             (true, true) => {
-//                 println!("{}", gen.base_req.instruct_prompt.prompt);
-//                 println!("{result}");
-//             }
-//             (true, false) => {
-//                 println!("{}", gen.base_req.instruct_prompt.prompt);
-//             }
-//             (false, true) => println!("{result}"),
-//             (false, false) => (),
-//         }
-//         test_results.push((test, result));
-//     }
-//     test_results.into_iter().for_each(|(test, result)| {
-//         test.check_result(result);
-//     });
-// }
+    Ok(())
+}
@@ -1,12 +1,16 @@
 mod api_backends;
 mod basic_completion_tests;
 mod basic_primitive_tests;
+mod cascade_tests;
+mod classify_tests;
 mod decision_tests;
 mod extract_tests;
 mod llama_cpp;
 #[cfg(feature = "mistral_rs_backend")]
 mod mistral_rs;
+mod raw_completion_tests;
 mod reason_tests;
+mod tokens_tests;
 
 use llm_client::prelude::*;
 use llm_testing::*;
@@ -0,0 +1,25 @@
+use super::*;
+
+mod raw_completion_unit_tests {
+    use super::*;
+
+    #[cfg(feature = "llama_cpp_backend")]
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    pub async fn test_llama() -> crate::Result<()> {
+        let llm_client = llama_cpp_tiny_llm().await?;
+        raw_completion_integration_tester(&llm_client).await?;
+        Ok(())
+    }
+}
+
+pub(super) async fn raw_completion_integration_tester(llm_client: &LlmClient) -> crate::Result<()> {
+    let mut gen = llm_client.raw_completion();
+    gen.prompt("The three primary colors are red,");
+    gen.max_tokens(20);
+    let res = gen.run().await?;
+    println!("Response:\n {}\n", res.content);
+    assert!(!res.content.is_empty());
+    Ok(())
+}
@@ -54,6 +54,24 @@ mod reason_unit_tests {
         exact_string_optional_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
         Ok(())
     }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn critique() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        critique_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn replay() -> crate::Result<()> {
+        let llm_client = default_tiny_llm().await?;
+        replay_integration_tester(&llm_client, &TestLevel::IntegrationTest).await?;
+        Ok(())
+    }
 }
 
 pub(super) async fn run(llm_client: &LlmClient, test_level: &TestLevel) -> crate::Result<()> {
@@ -205,3 +223,66 @@ pub(super) async fn exact_string_optional_integration_tester(
     tests.check_results();
     Ok(())
 }
+
+pub(super) async fn critique_integration_tester(
+    llm_client: &LlmClient,
+    test_level: &TestLevel,
+) -> crate::Result<()> {
+    let mut gen = llm_client.reason().boolean().self_critique();
+    gen.critique_rounds(2).critique_prompts([
+        "Critique the reasoning above, then give a revised conclusion with, 'Therefore, the revised conclusion is:...'.",
+        "Check the revised conclusion against the request, then give a final conclusion with, 'Therefore, the revised conclusion is:...'.",
+    ]);
+
+    let mut tests = TestSetsLoader::new()
+        .test_level_enum(test_level)
+        .boolean()?;
+    for case in &mut tests.cases {
+        gen.instructions().set_content(&case.question);
+
+        let result = gen.return_result().await.unwrap();
+        // The rules round, the draft round, and a round per critique.
+        assert_eq!(result.workflow.rounds.len(), 4);
+        let res = gen.primitive.parse_reason_result(&result).unwrap();
+        print_results(&gen.base_req.prompt, &Some(result), &Some(res));
+        case.result = res;
+        gen.reset_request();
+    }
+    tests.check_results();
+
+    let mut gen = llm_client.reason().boolean().self_critique().decision();
+    gen.instructions()
+        .set_content("Is the sky blue on a clear day?");
+    assert!(gen.return_primitive().await?);
+    Ok(())
+}
+
+pub(super) async fn replay_integration_tester(
+    llm_client: &LlmClient,
+    _test_level: &TestLevel,
+) -> crate::Result<()> {
+    use llm_client::components::cascade::step::CascadeStep;
+
+    let mut gen = llm_client.reason().boolean();
+    gen.instructions()
+        .set_content("Is the sky blue on a clear day?");
+    let result = gen.return_result().await?;
+
+    let transcript = serde_json::to_string(&result)?;
+    let saved: llm_client::workflows::reason::ReasonResult = serde_json::from_str(&transcript)?;
+    assert_eq!(saved.primitive_result, result.primitive_result);
+    assert!(saved
+        .workflow
+        .rounds
+        .iter()
+        .all(|round| round.resolved_steps.iter().all(|step| match step {
+            CascadeStep::Inference(step) => step.duration.is_some() && step.config.is_some(),
+            CascadeStep::Guidance(_) => true,
+        })));
+
+    let replay = saved.workflow.replay(llm_client.backend.clone()).await?;
+    println!("{}", replay.replayed);
+    assert_eq!(replay.replayed.rounds.len(), saved.workflow.rounds.len());
+    assert!(!replay.primitive_result_changed());
+    Ok(())
+}
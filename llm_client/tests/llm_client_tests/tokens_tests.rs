@@ -0,0 +1,38 @@
+use super::*;
+
+mod tokens_unit_tests {
+    use super::*;
+
+    #[cfg(feature = "llama_cpp_backend")]
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    pub async fn test_llama() -> crate::Result<()> {
+        let llm_client = llama_cpp_tiny_llm().await?;
+        tokens_integration_tester(&llm_client).await?;
+        Ok(())
+    }
+}
+
+pub(super) async fn tokens_integration_tester(llm_client: &LlmClient) -> crate::Result<()> {
+    let text = "The quick brown fox jumps over the lazy dog. ".repeat(50);
+    let text_tokens = llm_client.count_text_tokens(&text);
+    assert!(text_tokens > 100);
+
+    let truncated = llm_client.truncate_to_tokens(&text, 10)?;
+    assert!(llm_client.count_text_tokens(&truncated) <= 11);
+
+    let chunks = llm_client.text_splitter(50).split(&text)?;
+    assert!(chunks.len() > 1);
+    for chunk in &chunks {
+        assert!(llm_client.count_text_tokens(chunk) <= 50);
+    }
+
+    let mut gen = llm_client.basic_completion();
+    gen.prompt().add_user_message().unwrap().set_content(&text);
+    assert!(llm_client.fits_in_context(gen.prompt(), 100)?);
+    let available = llm_client.available_prompt_tokens(100);
+    assert!(available > llm_client.count_tokens(gen.prompt())?);
+    assert!(!llm_client.fits_in_context(gen.prompt(), available)?);
+    Ok(())
+}
@@ -0,0 +1,277 @@
+//! `#[derive(LlmPrimitive)]` for `llm_client`. See `llm_client::primitives::LlmPrimitive`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, Ident};
+
+/// Implements `llm_client::primitives::LlmPrimitive`.
+///
+/// Enums must have only unit variants. Each variant is one allowed answer, described by its doc comment.
+/// The enum also gets `LlmChoice`, so it can be used in a `Decision`, and `Display` and `FromStr` implementations using the same labels.
+/// Structs must have named fields, each of a type implementing `LlmPrimitive`, described by their doc comments.
+///
+/// `#[llm(rename = "label")]` on a variant or field changes the label the model writes.
+#[proc_macro_derive(LlmPrimitive, attributes(llm))]
+pub fn derive_llm_primitive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "LlmPrimitive can't be derived for generic types",
+        ));
+    }
+    match &input.data {
+        Data::Enum(data) => {
+            let mut variants = Vec::with_capacity(data.variants.len());
+            for variant in &data.variants {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(syn::Error::new_spanned(
+                        variant,
+                        "LlmPrimitive can only be derived for enums with unit variants",
+                    ));
+                }
+                let label = rename(&variant.attrs)?.unwrap_or_else(|| variant.ident.to_string());
+                variants.push((&variant.ident, label, doc_comment(&variant.attrs)));
+            }
+            if variants.is_empty() {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "LlmPrimitive can't be derived for enums without variants",
+                ));
+            }
+            Ok(expand_enum(&input.ident, &variants))
+        }
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => {
+                let mut field_info = Vec::with_capacity(fields.named.len());
+                for field in &fields.named {
+                    let ident = field.ident.as_ref().expect("named field");
+                    let label = rename(&field.attrs)?
+                        .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_owned());
+                    field_info.push((ident, &field.ty, label, doc_comment(&field.attrs)));
+                }
+                if field_info.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        &input.ident,
+                        "LlmPrimitive can't be derived for structs without fields",
+                    ));
+                }
+                Ok(expand_struct(&input.ident, &field_info))
+            }
+            _ => Err(syn::Error::new_spanned(
+                &input.ident,
+                "LlmPrimitive can only be derived for structs with named fields",
+            )),
+        },
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            &input.ident,
+            "LlmPrimitive can't be derived for unions",
+        )),
+    }
+}
+
+fn expand_enum(name: &Ident, variants: &[(&Ident, String, Option<String>)]) -> TokenStream2 {
+    let type_description = type_description(name);
+    let idents: Vec<_> = variants.iter().map(|(ident, _, _)| *ident).collect();
+    let labels: Vec<_> = variants.iter().map(|(_, label, _)| label).collect();
+    let descriptions: Vec<_> = variants
+        .iter()
+        .map(|(_, _, description)| match description {
+            Some(description) => quote!(Some(#description)),
+            None => quote!(None),
+        })
+        .collect();
+    let indexes: Vec<u32> = (0..variants.len() as u32).collect();
+
+    quote! {
+        impl ::llm_client::primitives::LlmPrimitive for #name {
+            fn gbnf() -> String {
+                ::llm_client::primitives::derived::enum_gbnf(&[#(#labels),*])
+            }
+
+            fn type_description() -> &'static str {
+                #type_description
+            }
+
+            fn solution_description() -> String {
+                ::llm_client::primitives::derived::enum_solution_description(&[#((#labels, #descriptions)),*])
+            }
+
+            fn parse_llm(content: &str) -> ::std::result::Result<Self, String> {
+                match ::llm_client::primitives::derived::enum_parse(content, &[#(#labels),*])? {
+                    #(#indexes => Ok(Self::#idents),)*
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        impl ::llm_client::primitives::LlmChoice for #name {
+            fn result_index(&self) -> u32 {
+                match self {
+                    #(Self::#idents => #indexes,)*
+                }
+            }
+
+            fn from_result_index(result_index: u32) -> Option<Self> {
+                match result_index {
+                    #(#indexes => Some(Self::#idents),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl ::std::fmt::Display for #name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(match self {
+                    #(Self::#idents => #labels,)*
+                })
+            }
+        }
+
+        impl ::std::str::FromStr for #name {
+            type Err = String;
+
+            fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+                <Self as ::llm_client::primitives::LlmPrimitive>::parse_llm(s)
+            }
+        }
+    }
+}
+
+fn expand_struct(
+    name: &Ident,
+    fields: &[(&Ident, &syn::Type, String, Option<String>)],
+) -> TokenStream2 {
+    let type_description = type_description(name);
+    let idents: Vec<_> = fields.iter().map(|(ident, _, _, _)| *ident).collect();
+    let types: Vec<_> = fields.iter().map(|(_, ty, _, _)| *ty).collect();
+    let labels: Vec<_> = fields.iter().map(|(_, _, label, _)| label).collect();
+    let descriptions: Vec<_> = fields
+        .iter()
+        .map(|(_, _, _, description)| match description {
+            Some(description) => quote!(Some(#description)),
+            None => quote!(None),
+        })
+        .collect();
+    let positions: Vec<usize> = (0..fields.len()).collect();
+
+    quote! {
+        impl ::llm_client::primitives::LlmPrimitive for #name {
+            fn gbnf() -> String {
+                ::llm_client::primitives::derived::struct_gbnf(&[
+                    #((#labels, <#types as ::llm_client::primitives::LlmPrimitive>::gbnf())),*
+                ])
+            }
+
+            fn type_description() -> &'static str {
+                #type_description
+            }
+
+            fn solution_description() -> String {
+                ::llm_client::primitives::derived::struct_solution_description(&[
+                    #((#labels, #descriptions, <#types as ::llm_client::primitives::LlmPrimitive>::solution_description())),*
+                ])
+            }
+
+            fn parse_llm(content: &str) -> ::std::result::Result<Self, String> {
+                let values = ::llm_client::primitives::derived::struct_split(content, &[#(#labels),*])?;
+                Ok(Self {
+                    #(#idents: <#types as ::llm_client::primitives::LlmPrimitive>::parse_llm(values[#positions])?,)*
+                })
+            }
+        }
+    }
+}
+
+/// `#[llm(rename = "...")]`, if present.
+fn rename(attrs: &[Attribute]) -> syn::Result<Option<String>> {
+    let mut rename = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("llm")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                rename = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(rename)
+}
+
+/// The `///` lines, joined with spaces.
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(doc),
+                        ..
+                    }),
+                ..
+            }) => Some(doc.value().trim().to_owned()),
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join(" "))
+    }
+}
+
+/// `TicketPriority` becomes "ticket priority".
+fn type_description(name: &Ident) -> String {
+    let mut description = String::new();
+    for (i, c) in name.to_string().chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            description.push(' ');
+        }
+        description.extend(c.to_lowercase());
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let input: DeriveInput = syn::parse_quote! {
+            enum TicketPriority {
+                /// Needs a fix
+                /// today.
+                #[llm(rename = "urgent")]
+                High,
+                Low,
+            }
+        };
+        let expanded = expand(&input).unwrap().to_string();
+        assert!(expanded.contains("\"ticket priority\""));
+        assert!(expanded.contains("(\"urgent\" , Some (\"Needs a fix today.\"))"));
+        assert!(expanded.contains("(\"Low\" , None)"));
+
+        let input: DeriveInput = syn::parse_quote! {
+            enum Shape {
+                Circle(f64),
+            }
+        };
+        assert!(expand(&input).is_err());
+        let input: DeriveInput = syn::parse_quote! {
+            struct Point(u8, u8);
+        };
+        assert!(expand(&input).is_err());
+    }
+}
@@ -31,4 +31,8 @@ impl LlmInterface {
     pub fn perplexity() -> llms::api::perplexity::builder::PerplexityBackendBuilder {
         llms::api::perplexity::builder::PerplexityBackendBuilder::default()
     }
+
+    pub fn router() -> llms::router::builder::RouterBackendBuilder {
+        llms::router::builder::RouterBackendBuilder::default()
+    }
 }
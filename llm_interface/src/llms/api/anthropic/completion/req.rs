@@ -36,7 +36,7 @@ pub struct AnthropicCompletionRequest {
     ///
     /// A system prompt is a way of providing context and instructions to Claude, such as specifying a particular goal or role. See our [guide to system prompts](https://docs.anthropic.com/claude/docs/system-prompts).
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub system: Option<String>,
+    pub system: Option<SystemPrompt>,
 
     /// Amount of randomness injected into the response.
     ///
@@ -48,6 +48,16 @@ pub struct AnthropicCompletionRequest {
     /// min: 0.0, max: 1.0, default: None
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
+
+    /// Only sample from the top K options for each subsequent token.
+    ///
+    /// Used to remove "long tail" low probability responses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+
+    /// An object describing metadata about the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
 }
 
 impl AnthropicCompletionRequest {
@@ -67,7 +77,7 @@ impl AnthropicCompletionRequest {
                     match role.as_str() {
                         "user" | "assistant" => messages.push(CompletionRequestMessage {
                             role: role.to_string(),
-                            content: content.to_string(),
+                            content: MessageContent::Text(content.to_string()),
                         }),
                         "system" => system_prompt = Some(content.to_string()),
                         _ => {
@@ -87,21 +97,58 @@ impl AnthropicCompletionRequest {
             }
         }
 
+        if let Some(prefix) = &req.generation_prefix {
+            add_assistant_prefill(&mut messages, prefix);
+        }
+
         let stop = req.stop_sequences.to_vec();
         let stop_sequences = if stop.is_empty() { None } else { Some(stop) };
 
+        let system = if req.config.cache_prompt {
+            set_cache_breakpoints(&mut messages);
+            system_prompt.map(|text| {
+                SystemPrompt::Blocks(vec![TextBlock {
+                    r#type: "text".to_string(),
+                    text,
+                    cache_control: Some(CacheControl::ephemeral()),
+                }])
+            })
+        } else {
+            system_prompt.map(SystemPrompt::Text)
+        };
+
         Ok(AnthropicCompletionRequest {
             model: req.backend.model_id().to_owned(),
             messages,
             max_tokens: req.config.actual_request_tokens.unwrap(),
             stop_sequences,
-            system: system_prompt,
+            system,
             temperature: temperature(req.config.temperature)?,
             top_p: top_p(req.config.top_p)?,
+            top_k: req.config.top_k,
+            metadata: req.config.user_id.as_ref().map(|user_id| Metadata {
+                user_id: Some(user_id.to_owned()),
+            }),
         })
     }
 }
 
+/// Marks the last user message as a cache breakpoint, so everything up to and including it,
+/// such as supporting material, is cached. The system prompt is marked separately.
+fn set_cache_breakpoints(messages: &mut [CompletionRequestMessage]) {
+    if let Some(message) = messages.iter_mut().rev().find(|m| m.role == "user") {
+        let text = match &message.content {
+            MessageContent::Text(text) => text.to_owned(),
+            MessageContent::Blocks(_) => return,
+        };
+        message.content = MessageContent::Blocks(vec![TextBlock {
+            r#type: "text".to_string(),
+            text,
+            cache_control: Some(CacheControl::ephemeral()),
+        }]);
+    }
+}
+
 /// Convert the native temperature from 0.0 to 2.0 to 0.0 to 1.0
 fn temperature(value: f32) -> crate::Result<f32, CompletionError> {
     if (0.0..=2.0).contains(&value) {
@@ -131,5 +178,116 @@ fn top_p(value: Option<f32>) -> crate::Result<Option<f32>, CompletionError> {
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct CompletionRequestMessage {
     pub role: String,
-    pub content: String,
+    pub content: MessageContent,
+}
+
+/// Message content as either a plain string, or content blocks which can carry `cache_control`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Blocks(Vec<TextBlock>),
+}
+
+/// The system prompt as either a plain string, or content blocks which can carry `cache_control`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum SystemPrompt {
+    Text(String),
+    Blocks(Vec<TextBlock>),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TextBlock {
+    /// Always "text".
+    pub r#type: String,
+    pub text: String,
+    /// Marks the end of a cacheable prefix of the prompt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CacheControl {
+    /// Currently the only type is "ephemeral".
+    pub r#type: String,
+}
+
+impl CacheControl {
+    pub fn ephemeral() -> Self {
+        Self {
+            r#type: "ephemeral".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Metadata {
+    /// An external identifier for the user who is associated with the request.
+    ///
+    /// This should be a uuid, hash value, or other opaque identifier. Anthropic may use this id to help detect abuse. Do not include any identifying information such as name, email address, or phone number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+/// Anthropic continues from a trailing assistant message, so the generation prefix is sent as one.
+/// The message can't end in whitespace, and consecutive assistant messages are joined.
+fn add_assistant_prefill(messages: &mut Vec<CompletionRequestMessage>, prefix: &str) {
+    let prefix = prefix.trim_end();
+    if prefix.is_empty() {
+        return;
+    }
+    match messages.last_mut() {
+        Some(CompletionRequestMessage {
+            role,
+            content: MessageContent::Text(text),
+        }) if role == "assistant" => *text = format!("{} {}", text.trim_end(), prefix),
+        _ => messages.push(CompletionRequestMessage {
+            role: "assistant".to_string(),
+            content: MessageContent::Text(prefix.to_string()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_breakpoint_on_last_user_message() {
+        let mut messages = vec![
+            CompletionRequestMessage {
+                role: "user".to_string(),
+                content: MessageContent::Text("supporting material".to_string()),
+            },
+            CompletionRequestMessage {
+                role: "assistant".to_string(),
+                content: MessageContent::Text("response".to_string()),
+            },
+        ];
+        set_cache_breakpoints(&mut messages);
+        let json = serde_json::to_value(&messages).unwrap();
+        assert_eq!(
+            json[0]["content"][0]["cache_control"]["type"],
+            serde_json::json!("ephemeral")
+        );
+        assert_eq!(json[1]["content"], serde_json::json!("response"));
+    }
+
+    #[test]
+    fn test_assistant_prefill() {
+        let mut messages = vec![CompletionRequestMessage {
+            role: "user".to_string(),
+            content: MessageContent::Text("question".to_string()),
+        }];
+        add_assistant_prefill(&mut messages, "Step 1: ");
+        let json = serde_json::to_value(&messages).unwrap();
+        assert_eq!(json[1]["role"], serde_json::json!("assistant"));
+        assert_eq!(json[1]["content"], serde_json::json!("Step 1:"));
+
+        add_assistant_prefill(&mut messages, "Step 2:");
+        let json = serde_json::to_value(&messages).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(json[1]["content"], serde_json::json!("Step 1: Step 2:"));
+    }
 }
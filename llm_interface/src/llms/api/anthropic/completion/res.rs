@@ -38,12 +38,13 @@ impl CompletionResponse {
         if res.content.len() > 1 {
             return Err(CompletionError::ReponseContentEmpty);
         }
-        let content = res
-            .content
-            .first()
-            .ok_or_else(|| CompletionError::ReponseContentEmpty)?
-            .text
-            .to_owned();
+        let content = Self::strip_generation_prefix(
+            req,
+            &res.content
+                .first()
+                .ok_or_else(|| CompletionError::ReponseContentEmpty)?
+                .text,
+        );
 
         Ok(Self {
             id: res.id.to_owned(),
@@ -55,6 +56,7 @@ impl CompletionResponse {
             generation_settings: GenerationSettings::new_from_anthropic(req, &res),
             timing_usage: TimingUsage::new_from_generic(req.start_time),
             token_usage: TokenUsage::new_from_anthropic(&res),
+            served_by: None,
         })
     }
 }
@@ -107,6 +109,12 @@ pub struct CompletionUsage {
     pub input_tokens: u32,
     /// The number of output tokens which were used.
     pub output_tokens: u32,
+    /// The number of input tokens used to create the cache entry.
+    #[serde(default)]
+    pub cache_creation_input_tokens: Option<u32>,
+    /// The number of input tokens read from the cache.
+    #[serde(default)]
+    pub cache_read_input_tokens: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
@@ -1,14 +1,16 @@
 use super::{
     client::ApiClient,
     config::{ApiConfig, ApiConfigTrait},
-    openai::completion::OpenAiCompletionRequest,
+    openai::completion::{
+        OpenAiCompletionRequest, OpenAiRawCompletionRequest, OpenAiRawCompletionResponse,
+    },
 };
 use crate::requests::completion::{
     error::CompletionError, request::CompletionRequest, response::CompletionResponse,
 };
 use llm_devices::logging::LoggingConfig;
 use llm_utils::models::api_model::ApiLlmModel;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
 use secrecy::{ExposeSecret, Secret};
 
 pub struct GenericApiBackend {
@@ -31,6 +33,19 @@ impl GenericApiBackend {
         &self,
         request: &CompletionRequest,
     ) -> crate::Result<CompletionResponse, CompletionError> {
+        if request.raw_prompt.is_some() {
+            return match self
+                .client
+                .post::<_, OpenAiRawCompletionResponse>(
+                    &self.client.config.raw_completion_path,
+                    OpenAiRawCompletionRequest::new(request)?,
+                )
+                .await
+            {
+                Err(e) => Err(CompletionError::ClientError(e)),
+                Ok(res) => Ok(CompletionResponse::new_from_openai(request, res.into())?),
+            };
+        }
         match self
             .client
             .post(
@@ -50,6 +65,14 @@ pub struct GenericApiConfig {
     pub api_config: ApiConfig,
     pub logging_config: LoggingConfig,
     pub completion_path: String,
+    /// The path for raw text completions. Defaults to `/completions`.
+    pub raw_completion_path: String,
+    /// Use https rather than http. Local servers like vLLM and LM Studio usually need http.
+    pub use_https: bool,
+    /// A prefix added before every path, e.g. `/v1`.
+    pub base_path: Option<String>,
+    /// Headers sent with every request, e.g. for proxies like LiteLLM.
+    pub custom_headers: std::collections::HashMap<String, String>,
 }
 
 impl Default for GenericApiConfig {
@@ -66,6 +89,10 @@ impl Default for GenericApiConfig {
                 ..Default::default()
             },
             completion_path: "/chat/completions".to_string(),
+            raw_completion_path: "/completions".to_string(),
+            use_https: true,
+            base_path: None,
+            custom_headers: Default::default(),
         }
     }
 }
@@ -79,11 +106,42 @@ impl GenericApiConfig {
         self.completion_path = path.into();
         self
     }
+
+    pub fn raw_completion_path<S: Into<String>>(mut self, path: S) -> Self {
+        self.raw_completion_path = path.into();
+        self
+    }
+
+    pub fn use_https(mut self, use_https: bool) -> Self {
+        self.use_https = use_https;
+        self
+    }
+
+    pub fn base_path<S: Into<String>>(mut self, base_path: S) -> Self {
+        self.base_path = Some(base_path.into());
+        self
+    }
+
+    pub fn header<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.custom_headers.insert(name.into(), value.into());
+        self
+    }
 }
 
 impl ApiConfigTrait for GenericApiConfig {
     fn headers(&self) -> HeaderMap {
         let mut headers = HeaderMap::new();
+        for (name, value) in &self.custom_headers {
+            match (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                (Ok(header_name), Ok(header_value)) => {
+                    headers.insert(header_name, header_value);
+                }
+                _ => crate::error!("Failed to create header from custom header {name}"),
+            }
+        }
         if let Some(api_key) = self.api_key() {
             if let Ok(header_value) =
                 HeaderValue::from_str(&format!("Bearer {}", api_key.expose_secret()))
@@ -98,10 +156,17 @@ impl ApiConfigTrait for GenericApiConfig {
     }
 
     fn url(&self, path: &str) -> String {
+        let scheme = if self.use_https { "https" } else { "http" };
+        let host = self.api_config.host.trim_end_matches('/');
+        let base_path = self
+            .base_path
+            .as_deref()
+            .map(|p| p.trim_end_matches('/'))
+            .unwrap_or_default();
         if let Some(port) = &self.api_config.port {
-            format!("https://{}:{}{}", self.api_config.host, port, path)
+            format!("{}://{}:{}{}{}", scheme, host, port, base_path, path)
         } else {
-            format!("https://{}:{}", self.api_config.host, path)
+            format!("{}://{}{}{}", scheme, host, base_path, path)
         }
     }
 
@@ -109,3 +174,37 @@ impl ApiConfigTrait for GenericApiConfig {
         &self.api_config.api_key
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_without_port() {
+        let mut config = GenericApiConfig::default();
+        config.api_config.host = "api.perplexity.ai".to_string();
+        assert_eq!(
+            config.url("/chat/completions"),
+            "https://api.perplexity.ai/chat/completions"
+        );
+    }
+
+    #[test]
+    fn test_url_with_port_and_base_path() {
+        let mut config = GenericApiConfig::default()
+            .use_https(false)
+            .base_path("/v1");
+        config.api_config.host = "localhost".to_string();
+        config.api_config.port = Some("8000".to_string());
+        assert_eq!(
+            config.url("/chat/completions"),
+            "http://localhost:8000/v1/chat/completions"
+        );
+    }
+
+    #[test]
+    fn test_custom_headers() {
+        let config = GenericApiConfig::default().header("x-litellm-tag", "batch");
+        assert_eq!(config.headers().get("x-litellm-tag").unwrap(), "batch");
+    }
+}
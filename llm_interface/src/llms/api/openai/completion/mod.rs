@@ -1,4 +1,6 @@
+mod raw;
 mod req;
 mod res;
+pub use raw::{OpenAiRawCompletionRequest, OpenAiRawCompletionResponse};
 pub use req::OpenAiCompletionRequest;
 pub use res::OpenAiCompletionResponse;
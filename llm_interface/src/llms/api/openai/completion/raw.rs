@@ -0,0 +1,165 @@
+use super::{
+    req::Stop,
+    res::{ChatChoice, ChatCompletionResponseMessage, CompletionUsage, FinishReason, Role},
+    OpenAiCompletionResponse,
+};
+use crate::requests::completion::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A request to the legacy `/completions` endpoint, which takes an untemplated prompt.
+#[derive(Clone, Serialize, Default, Debug, Deserialize, PartialEq)]
+pub struct OpenAiRawCompletionRequest {
+    /// ID of the model to use.
+    pub model: String,
+
+    /// The prompt as a string, or as an array of token ids.
+    pub prompt: RawCompletionPrompt,
+
+    /// The text that comes after the completion, for fill-in-the-middle models.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+
+    /// Modify the likelihood of specified tokens appearing in the completion.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logit_bias: Option<HashMap<String, serde_json::Value>>,
+
+    /// The maximum number of [tokens](https://platform.openai.com/tokenizer) that can be generated in the completion.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+
+    /// min: 0.0, max: 2.0, default: None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    /// min: -2.0, max: 2.0, default: None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+
+    /// min: -2.0, max: 2.0, default: None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+
+    /// Up to 4 sequences where the API will stop generating further tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Stop>,
+
+    /// min: 0.0, max: 1.0, default: None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+
+    /// Best effort deterministic sampling for repeated requests with the same seed. default: None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum RawCompletionPrompt {
+    String(String),
+    Tokens(Vec<u32>),
+}
+
+impl Default for RawCompletionPrompt {
+    fn default() -> Self {
+        RawCompletionPrompt::String(String::new())
+    }
+}
+
+impl OpenAiRawCompletionRequest {
+    pub fn new(req: &CompletionRequest) -> crate::Result<Self, CompletionError> {
+        let (prompt, suffix) = match &req.raw_prompt {
+            Some(RawPrompt::Text(text)) => (RawCompletionPrompt::String(text.to_owned()), None),
+            Some(RawPrompt::Tokens(tokens)) => (RawCompletionPrompt::Tokens(tokens.clone()), None),
+            Some(RawPrompt::Infill { prefix, suffix }) => (
+                RawCompletionPrompt::String(prefix.to_owned()),
+                Some(suffix.to_owned()),
+            ),
+            None => {
+                return Err(CompletionError::RequestBuilderError(
+                    "Raw completion requests require a raw_prompt".to_string(),
+                ))
+            }
+        };
+
+        Ok(OpenAiRawCompletionRequest {
+            model: req.backend.model_id().to_owned(),
+            prompt,
+            suffix,
+            logit_bias: req
+                .logit_bias
+                .as_ref()
+                .and_then(|lb| lb.built_openai_bias.clone()),
+            max_tokens: req.config.actual_request_tokens,
+            temperature: Some(req.config.temperature),
+            frequency_penalty: req.config.frequency_penalty,
+            presence_penalty: Some(req.config.presence_penalty),
+            stop: Stop::new(&req.stop_sequences)?,
+            top_p: req.config.top_p,
+            seed: req.config.seed,
+        })
+    }
+}
+
+/// A response from the legacy `/completions` endpoint.
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct OpenAiRawCompletionResponse {
+    pub id: String,
+    pub choices: Vec<RawCompletionChoice>,
+    pub created: u32,
+    pub model: String,
+    pub usage: Option<CompletionUsage>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct RawCompletionChoice {
+    pub index: u32,
+    pub text: String,
+    pub finish_reason: Option<FinishReason>,
+}
+
+/// The raw response is converted to the chat response shape, so both share `CompletionResponse::new_from_openai`.
+impl From<OpenAiRawCompletionResponse> for OpenAiCompletionResponse {
+    fn from(res: OpenAiRawCompletionResponse) -> Self {
+        OpenAiCompletionResponse {
+            id: res.id,
+            choices: res
+                .choices
+                .into_iter()
+                .map(|choice| ChatChoice {
+                    index: choice.index,
+                    message: ChatCompletionResponseMessage {
+                        content: Some(choice.text),
+                        role: Role::Assistant,
+                    },
+                    finish_reason: choice.finish_reason,
+                    logprobs: None,
+                })
+                .collect(),
+            created: res.created,
+            model: res.model,
+            usage: res.usage,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_response_conversion() {
+        let res: OpenAiRawCompletionResponse = serde_json::from_value(serde_json::json!({
+            "id": "cmpl-1",
+            "object": "text_completion",
+            "created": 1,
+            "model": "gpt-3.5-turbo-instruct",
+            "choices": [{"index": 0, "text": " world", "finish_reason": "length", "logprobs": null}],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+        }))
+        .unwrap();
+        let res: OpenAiCompletionResponse = res.into();
+        assert_eq!(res.choices[0].message.content.as_deref(), Some(" world"));
+        assert_eq!(res.choices[0].finish_reason, Some(FinishReason::Length));
+    }
+}
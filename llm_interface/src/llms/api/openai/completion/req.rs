@@ -57,6 +57,10 @@ pub struct OpenAiCompletionRequest {
     /// min: 0.0, max: 1.0, default: None
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
+
+    /// If specified, the system will make a best effort to sample deterministically, such that repeated requests with the same `seed` and parameters should return the same result.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
 }
 
 impl OpenAiCompletionRequest {
@@ -70,6 +74,17 @@ impl OpenAiCompletionRequest {
             }
             Err(e) => return Err(CompletionError::RequestBuilderError(e.to_string())),
         }
+        if let Some(prefix) = req
+            .generation_prefix
+            .as_deref()
+            .map(str::trim_end)
+            .filter(|p| !p.is_empty())
+        {
+            messages.push(CompletionRequestMessage {
+                role: "assistant".to_string(),
+                content: prefix.to_string(),
+            });
+        }
 
         Ok(OpenAiCompletionRequest {
             messages,
@@ -79,13 +94,14 @@ impl OpenAiCompletionRequest {
                 .as_ref()
                 .and_then(|lb| lb.built_openai_bias.clone()),
             frequency_penalty: req.config.frequency_penalty,
-            logprobs: None,
+            logprobs: req.config.logprobs.then_some(true),
             top_logprobs: None,
             max_tokens: req.config.actual_request_tokens,
             presence_penalty: Some(req.config.presence_penalty),
             stop: Stop::new(&req.stop_sequences)?,
             temperature: Some(req.config.temperature),
             top_p: req.config.top_p,
+            seed: req.config.seed,
         })
     }
 }
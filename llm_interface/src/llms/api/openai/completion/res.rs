@@ -34,13 +34,17 @@ impl CompletionResponse {
         Ok(Self {
             id: res.id.to_owned(),
             index: None,
-            content: choice.message.content.as_ref().unwrap().to_owned(),
+            content: Self::strip_generation_prefix(req, choice.message.content.as_ref().unwrap()),
             finish_reason,
-            completion_probabilities: None,
+            completion_probabilities: choice
+                .logprobs
+                .as_ref()
+                .and_then(ChatChoiceLogprobs::to_inference_probabilities),
             truncated: false,
             generation_settings: GenerationSettings::new_from_openai(req, &res),
             timing_usage: TimingUsage::new_from_generic(req.start_time),
             token_usage: TokenUsage::new_from_generic(&res),
+            served_by: None,
         })
     }
 }
@@ -110,6 +114,36 @@ pub struct ChatChoiceLogprobs {
     pub content: Option<Vec<ChatCompletionTokenLogprob>>,
 }
 
+impl ChatChoiceLogprobs {
+    /// Each token with its own log probability, followed by any `top_logprobs` alternatives.
+    fn to_inference_probabilities(&self) -> Option<Vec<InferenceProbabilities>> {
+        let content = self.content.as_ref()?;
+        Some(
+            content
+                .iter()
+                .map(|token| InferenceProbabilities {
+                    content: Some(token.token.clone()),
+                    top_probs: std::iter::once(TopProbabilities {
+                        token: token.token.clone(),
+                        prob: token.logprob,
+                    })
+                    .chain(
+                        token
+                            .top_logprobs
+                            .iter()
+                            .filter(|top| top.token != token.token)
+                            .map(|top| TopProbabilities {
+                                token: top.token.clone(),
+                                prob: top.logprob,
+                            }),
+                    )
+                    .collect(),
+                })
+                .collect(),
+        )
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct ChatCompletionTokenLogprob {
     /// The token.
@@ -142,3 +176,28 @@ pub enum Role {
     Tool,
     Function,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completion_probabilities() {
+        let logprobs: ChatChoiceLogprobs = serde_json::from_str(
+            r#"{"content":[{"token":"yes","logprob":-0.25,"bytes":null,"top_logprobs":[{"token":"yes","logprob":-0.25,"bytes":null},{"token":"no","logprob":-1.5,"bytes":null}]}]}"#,
+        )
+        .unwrap();
+        let probs = logprobs.to_inference_probabilities().unwrap();
+        assert_eq!(probs.len(), 1);
+        assert_eq!(probs[0].content.as_deref(), Some("yes"));
+        let top: Vec<(&str, f32)> = probs[0]
+            .top_probs
+            .iter()
+            .map(|top| (top.token.as_str(), top.prob))
+            .collect();
+        assert_eq!(top, vec![("yes", -0.25), ("no", -1.5)]);
+
+        let logprobs: ChatChoiceLogprobs = serde_json::from_str(r#"{"content":null}"#).unwrap();
+        assert!(logprobs.to_inference_probabilities().is_none());
+    }
+}
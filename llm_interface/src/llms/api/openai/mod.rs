@@ -8,7 +8,9 @@ use super::{
 use crate::requests::completion::{
     error::CompletionError, request::CompletionRequest, response::CompletionResponse,
 };
-use completion::OpenAiCompletionRequest;
+use completion::{
+    OpenAiCompletionRequest, OpenAiRawCompletionRequest, OpenAiRawCompletionResponse,
+};
 use llm_devices::logging::LoggingConfig;
 use llm_utils::models::api_model::ApiLlmModel;
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
@@ -20,6 +22,10 @@ pub const OPENAI_API_HOST: &str = "api.openai.com/v1";
 pub const OPENAI_ORGANIZATION_HEADER: &str = "OpenAI-Organization";
 /// Project header
 pub const OPENAI_PROJECT_HEADER: &str = "OpenAI-Project";
+/// Azure OpenAI authenticates with this header instead of a bearer token
+pub const AZURE_API_KEY_HEADER: &str = "api-key";
+/// Default Azure OpenAI `api-version` query param
+pub const AZURE_OPENAI_API_VERSION: &str = "2024-06-01";
 
 pub struct OpenAiBackend {
     pub(crate) client: ApiClient<OpenAiConfig>,
@@ -40,6 +46,19 @@ impl OpenAiBackend {
         &self,
         request: &CompletionRequest,
     ) -> crate::Result<CompletionResponse, CompletionError> {
+        if request.raw_prompt.is_some() {
+            return match self
+                .client
+                .post::<_, OpenAiRawCompletionResponse>(
+                    "/completions",
+                    OpenAiRawCompletionRequest::new(request)?,
+                )
+                .await
+            {
+                Err(e) => Err(CompletionError::ClientError(e)),
+                Ok(res) => Ok(CompletionResponse::new_from_openai(request, res.into())?),
+            };
+        }
         match self
             .client
             .post("/chat/completions", OpenAiCompletionRequest::new(request)?)
@@ -57,6 +76,18 @@ pub struct OpenAiConfig {
     pub logging_config: LoggingConfig,
     pub org_id: String,
     pub project_id: String,
+    /// Set to send requests to an Azure OpenAI deployment rather than api.openai.com.
+    pub azure: Option<AzureConfig>,
+    /// An `api-version` set before the deployment, applied when the deployment is set.
+    azure_api_version: Option<String>,
+}
+
+/// Azure OpenAI routes requests by deployment name rather than model id.
+/// The host is the resource endpoint, e.g. `my-resource.openai.azure.com`.
+#[derive(Clone, Debug)]
+pub struct AzureConfig {
+    pub deployment: String,
+    pub api_version: String,
 }
 
 impl Default for OpenAiConfig {
@@ -74,6 +105,8 @@ impl Default for OpenAiConfig {
             },
             org_id: Default::default(),
             project_id: Default::default(),
+            azure: None,
+            azure_api_version: None,
         }
     }
 }
@@ -94,6 +127,43 @@ impl OpenAiConfig {
         self.project_id = project_id.into();
         self
     }
+
+    /// Use an Azure OpenAI deployment. The API key is loaded from `AZURE_OPENAI_API_KEY` unless another env var is set.
+    pub fn with_azure_deployment<S: Into<String>>(mut self, deployment: S) -> Self {
+        self.set_azure_deployment(deployment);
+        self
+    }
+
+    /// Non default Azure OpenAI `api-version`
+    pub fn with_azure_api_version<S: Into<String>>(mut self, api_version: S) -> Self {
+        self.set_azure_api_version(api_version);
+        self
+    }
+
+    pub fn set_azure_deployment<S: Into<String>>(&mut self, deployment: S) {
+        if self.api_config.api_key_env_var == "OPENAI_API_KEY" {
+            self.api_config.api_key_env_var = "AZURE_OPENAI_API_KEY".to_string();
+        }
+        match &mut self.azure {
+            Some(azure) => azure.deployment = deployment.into(),
+            None => {
+                self.azure = Some(AzureConfig {
+                    deployment: deployment.into(),
+                    api_version: self
+                        .azure_api_version
+                        .take()
+                        .unwrap_or_else(|| AZURE_OPENAI_API_VERSION.to_string()),
+                })
+            }
+        }
+    }
+
+    pub fn set_azure_api_version<S: Into<String>>(&mut self, api_version: S) {
+        match &mut self.azure {
+            Some(azure) => azure.api_version = api_version.into(),
+            None => self.azure_api_version = Some(api_version.into()),
+        }
+    }
 }
 
 impl ApiConfigTrait for OpenAiConfig {
@@ -115,7 +185,13 @@ impl ApiConfigTrait for OpenAiConfig {
             }
         }
         if let Some(api_key) = self.api_key() {
-            if let Ok(header_value) =
+            if self.azure.is_some() {
+                if let Ok(header_value) = HeaderValue::from_str(api_key.expose_secret()) {
+                    headers.insert(AZURE_API_KEY_HEADER, header_value);
+                } else {
+                    crate::error!("Failed to create header value from api-key value");
+                }
+            } else if let Ok(header_value) =
                 HeaderValue::from_str(&format!("Bearer {}", api_key.expose_secret()))
             {
                 headers.insert(AUTHORIZATION, header_value);
@@ -128,10 +204,55 @@ impl ApiConfigTrait for OpenAiConfig {
     }
 
     fn url(&self, path: &str) -> String {
-        format!("https://{}{}", self.api_config.host, path)
+        let host = self.api_config.host.trim_end_matches('/');
+        match &self.azure {
+            Some(azure) => format!(
+                "https://{}/openai/deployments/{}{}?api-version={}",
+                host, azure.deployment, path, azure.api_version
+            ),
+            None => format!("https://{}{}", host, path),
+        }
     }
 
     fn api_key(&self) -> &Option<Secret<String>> {
         &self.api_config.api_key
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_url() {
+        let config = OpenAiConfig::default();
+        assert_eq!(
+            config.url("/chat/completions"),
+            "https://api.openai.com/v1/chat/completions"
+        );
+    }
+
+    #[test]
+    fn test_azure_url() {
+        let mut config = OpenAiConfig::default().with_azure_deployment("gpt-4o-mini");
+        config.api_config.host = "my-resource.openai.azure.com".to_string();
+        assert_eq!(
+            config.url("/chat/completions"),
+            format!("https://my-resource.openai.azure.com/openai/deployments/gpt-4o-mini/chat/completions?api-version={AZURE_OPENAI_API_VERSION}")
+        );
+        assert_eq!(config.api_config.api_key_env_var, "AZURE_OPENAI_API_KEY");
+    }
+
+    #[test]
+    fn test_azure_api_version_before_deployment() {
+        let config = OpenAiConfig::default()
+            .with_azure_api_version("2024-10-21")
+            .with_azure_deployment("gpt-4o-mini");
+        assert_eq!(config.azure.unwrap().api_version, "2024-10-21");
+
+        let config = OpenAiConfig::default()
+            .with_azure_deployment("gpt-4o-mini")
+            .with_azure_api_version("2024-10-21");
+        assert_eq!(config.azure.unwrap().api_version, "2024-10-21");
+    }
+}
@@ -0,0 +1,300 @@
+use crate::requests::completion::request::{CompletionRequest, RawPrompt};
+
+/// What a backend supports through this crate. Used by [`CompletionRequest::request`] to reject settings a backend would otherwise silently drop.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackendCapabilities {
+    /// GBNF grammars via `CompletionRequest::grammar_string`.
+    pub grammar: bool,
+    /// Token biasing via `CompletionRequest::logit_bias`.
+    pub logit_bias: bool,
+    /// Token probabilities returned in `CompletionResponse::completion_probabilities`.
+    pub logprobs: bool,
+    /// Streamed responses.
+    pub streaming: bool,
+    /// Tool or function calling.
+    pub tools: bool,
+    /// Image inputs.
+    pub vision: bool,
+    /// Re-use of a cached prompt via `RequestConfig::cache_prompt`. Backends without it ignore the setting, as it never changes the output.
+    pub prompt_cache: bool,
+    /// Untemplated text or token prompts via `CompletionRequest::raw_prompt`.
+    pub raw_completion: bool,
+    /// Fill-in-the-middle prompts via `RawPrompt::Infill`.
+    pub infill: bool,
+    /// The maximum number of stop sequences per request. `None` for no limit.
+    pub max_stop_sequences: Option<usize>,
+    pub sampler_params: SamplerParams,
+}
+
+/// The sampler settings from `RequestConfig` a backend supports.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SamplerParams {
+    pub temperature: bool,
+    pub top_p: bool,
+    pub top_k: bool,
+    pub frequency_penalty: bool,
+    pub presence_penalty: bool,
+    pub seed: bool,
+}
+
+impl SamplerParams {
+    pub fn all() -> Self {
+        Self {
+            temperature: true,
+            top_p: true,
+            top_k: true,
+            frequency_penalty: true,
+            presence_penalty: true,
+            seed: true,
+        }
+    }
+}
+
+impl BackendCapabilities {
+    #[cfg(feature = "llama_cpp_backend")]
+    pub fn llama_cpp() -> Self {
+        Self {
+            grammar: true,
+            logit_bias: true,
+            logprobs: true,
+            streaming: false,
+            tools: false,
+            vision: false,
+            prompt_cache: true,
+            raw_completion: true,
+            infill: true,
+            max_stop_sequences: None,
+            sampler_params: SamplerParams::all(),
+        }
+    }
+
+    #[cfg(feature = "mistral_rs_backend")]
+    pub fn mistral_rs() -> Self {
+        Self {
+            grammar: false,
+            logit_bias: false,
+            logprobs: false,
+            streaming: false,
+            tools: false,
+            vision: false,
+            prompt_cache: false,
+            raw_completion: false,
+            infill: false,
+            // The request builder doesn't send stop sequences, so they're not limited. The caller checks for them in the response.
+            max_stop_sequences: None,
+            sampler_params: SamplerParams {
+                seed: false,
+                ..SamplerParams::all()
+            },
+        }
+    }
+
+    pub fn openai() -> Self {
+        Self {
+            grammar: false,
+            logit_bias: true,
+            logprobs: true,
+            streaming: false,
+            tools: false,
+            vision: false,
+            prompt_cache: false,
+            raw_completion: true,
+            infill: true,
+            max_stop_sequences: Some(4),
+            sampler_params: SamplerParams {
+                top_k: false,
+                ..SamplerParams::all()
+            },
+        }
+    }
+
+    pub fn anthropic() -> Self {
+        Self {
+            grammar: false,
+            logit_bias: false,
+            logprobs: false,
+            streaming: false,
+            tools: false,
+            vision: false,
+            prompt_cache: true,
+            raw_completion: false,
+            infill: false,
+            max_stop_sequences: None,
+            sampler_params: SamplerParams {
+                temperature: true,
+                top_p: true,
+                top_k: true,
+                frequency_penalty: false,
+                presence_penalty: false,
+                seed: false,
+            },
+        }
+    }
+
+    /// OpenAI compatible APIs. Assumes the same limits as OpenAI.
+    pub fn generic_api() -> Self {
+        Self::openai()
+    }
+
+    /// Checks the settings of a request against the capabilities.
+    pub fn validate(
+        &self,
+        request: &CompletionRequest,
+        backend_name: &str,
+    ) -> crate::Result<(), CapabilityError> {
+        let unsupported = |feature: &'static str| CapabilityError::Unsupported {
+            backend: backend_name.to_owned(),
+            feature,
+        };
+        if request.grammar_string.is_some() && !self.grammar {
+            return Err(unsupported("grammar"));
+        }
+        if request.logit_bias.is_some() && !self.logit_bias {
+            return Err(unsupported("logit_bias"));
+        }
+        match &request.raw_prompt {
+            Some(RawPrompt::Infill { .. }) if !self.infill => return Err(unsupported("infill")),
+            Some(_) if !self.raw_completion => return Err(unsupported("raw_completion")),
+            _ => (),
+        }
+        if let Some(max) = self.max_stop_sequences {
+            let requested = request.stop_sequences.sequences.len();
+            if requested > max {
+                return Err(CapabilityError::TooManyStopSequences {
+                    backend: backend_name.to_owned(),
+                    max,
+                    requested,
+                });
+            }
+        }
+        let sampler = &self.sampler_params;
+        if request.config.top_p.is_some() && !sampler.top_p {
+            return Err(unsupported("top_p"));
+        }
+        if request.config.top_k.is_some() && !sampler.top_k {
+            return Err(unsupported("top_k"));
+        }
+        if request.config.seed.is_some() && !sampler.seed {
+            return Err(unsupported("seed"));
+        }
+        if request.config.frequency_penalty.is_some() && !sampler.frequency_penalty {
+            return Err(unsupported("frequency_penalty"));
+        }
+        if request.config.presence_penalty != 0.0 && !sampler.presence_penalty {
+            return Err(unsupported("presence_penalty"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CapabilityError {
+    #[error("{backend} does not support {feature}")]
+    Unsupported {
+        backend: String,
+        feature: &'static str,
+    },
+    #[error("{backend} supports at most {max} stop sequences, but {requested} were requested")]
+    TooManyStopSequences {
+        backend: String,
+        max: usize,
+        requested: usize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llms::{
+        api::generic_openai::{GenericApiBackend, GenericApiConfig},
+        LlmBackend,
+    };
+    use crate::requests::constraints::stop_sequence::StoppingSequence;
+    use llm_utils::models::api_model::ApiLlmModel;
+
+    fn request() -> CompletionRequest {
+        let backend =
+            GenericApiBackend::new(GenericApiConfig::default(), ApiLlmModel::gpt_4_o_mini())
+                .unwrap();
+        CompletionRequest::new(std::sync::Arc::new(LlmBackend::GenericApi(backend)))
+    }
+
+    fn with_stop_sequences(count: usize) -> CompletionRequest {
+        let mut req = request();
+        for i in 0..count {
+            req.stop_sequences
+                .sequences
+                .push(StoppingSequence::InferenceDone(format!("Done {i}.")));
+        }
+        req
+    }
+
+    #[test]
+    fn test_validate_stop_sequences() {
+        let req = with_stop_sequences(5);
+        assert!(matches!(
+            BackendCapabilities::openai().validate(&req, "openai"),
+            Err(CapabilityError::TooManyStopSequences {
+                max: 4,
+                requested: 5,
+                ..
+            })
+        ));
+        assert!(BackendCapabilities::openai()
+            .validate(&with_stop_sequences(4), "openai")
+            .is_ok());
+        assert!(BackendCapabilities::anthropic()
+            .validate(&req, "anthropic")
+            .is_ok());
+        #[cfg(feature = "mistral_rs_backend")]
+        assert!(BackendCapabilities::mistral_rs()
+            .validate(&req, "mistral_rs")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_validate_unsupported() {
+        let mut req = request();
+        assert!(BackendCapabilities::openai()
+            .validate(&req, "openai")
+            .is_ok());
+
+        req.grammar_string = Some("root ::= \"yes\"".to_owned());
+        assert!(matches!(
+            BackendCapabilities::openai().validate(&req, "openai"),
+            Err(CapabilityError::Unsupported {
+                feature: "grammar",
+                ..
+            })
+        ));
+        req.grammar_string = None;
+
+        req.raw_prompt = Some(RawPrompt::Infill {
+            prefix: "fn main() {".to_owned(),
+            suffix: "}".to_owned(),
+        });
+        assert!(BackendCapabilities::openai()
+            .validate(&req, "openai")
+            .is_ok());
+        assert!(matches!(
+            BackendCapabilities::anthropic().validate(&req, "anthropic"),
+            Err(CapabilityError::Unsupported {
+                feature: "infill",
+                ..
+            })
+        ));
+        req.raw_prompt = None;
+
+        req.config.top_k = Some(40);
+        assert!(matches!(
+            BackendCapabilities::openai().validate(&req, "openai"),
+            Err(CapabilityError::Unsupported {
+                feature: "top_k",
+                ..
+            })
+        ));
+        assert!(BackendCapabilities::anthropic()
+            .validate(&req, "anthropic")
+            .is_ok());
+    }
+}
@@ -0,0 +1,116 @@
+use llm_utils::models::local_model::LocalLlmModel;
+use std::path::PathBuf;
+
+/// Replaces the chat template embedded in the GGUF file. Useful when the embedded template is missing or wrong,
+/// which shows up as stray role names or special tokens in responses.
+///
+/// The template is Jinja, rendered with `messages`, `add_generation_prompt`, `bos_token` and `eos_token`,
+/// the same as Hugging Face `tokenizer_config.json` templates. BOS and EOS tokens are still read from the GGUF file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChatTemplateOverride {
+    Jinja(String),
+    /// A file containing a Jinja template.
+    File(PathBuf),
+    Builtin(BuiltinChatTemplate),
+}
+
+impl ChatTemplateOverride {
+    pub fn template_string(&self) -> crate::Result<String> {
+        match self {
+            ChatTemplateOverride::Jinja(template) => Ok(template.to_owned()),
+            ChatTemplateOverride::File(path) => std::fs::read_to_string(path).map_err(|e| {
+                crate::anyhow!("Failed to read chat template {}: {e}", path.display())
+            }),
+            ChatTemplateOverride::Builtin(builtin) => Ok(builtin.template().to_owned()),
+        }
+    }
+
+    pub(crate) fn apply(&self, model: &mut LocalLlmModel) -> crate::Result<()> {
+        let template = self.template_string()?;
+        if template.trim().is_empty() {
+            crate::bail!("Chat template override is empty");
+        }
+        crate::info!("Overriding the GGUF chat template with {:?}", self);
+        model.chat_template.chat_template = template;
+        Ok(())
+    }
+}
+
+/// Chat templates for common model families.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuiltinChatTemplate {
+    /// `<|im_start|>` and `<|im_end|>`. Used by Qwen, Hermes and many fine-tunes.
+    ChatMl,
+    Llama3,
+    /// Mistral and Mixtral instruct. System messages are prepended to the next user message.
+    Mistral,
+    /// Gemma has no system role, so system messages are sent as user turns.
+    Gemma,
+    Phi3,
+}
+
+impl BuiltinChatTemplate {
+    pub fn template(&self) -> &'static str {
+        match self {
+            BuiltinChatTemplate::ChatMl => CHATML_TEMPLATE,
+            BuiltinChatTemplate::Llama3 => LLAMA3_TEMPLATE,
+            BuiltinChatTemplate::Mistral => MISTRAL_TEMPLATE,
+            BuiltinChatTemplate::Gemma => GEMMA_TEMPLATE,
+            BuiltinChatTemplate::Phi3 => PHI3_TEMPLATE,
+        }
+    }
+}
+
+impl std::str::FromStr for BuiltinChatTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.trim().to_lowercase().replace(['-', '_'], "").as_str() {
+            "chatml" => Ok(Self::ChatMl),
+            "llama3" => Ok(Self::Llama3),
+            "mistral" => Ok(Self::Mistral),
+            "gemma" => Ok(Self::Gemma),
+            "phi3" | "phi" => Ok(Self::Phi3),
+            _ => crate::bail!("Unknown chat template: {s}"),
+        }
+    }
+}
+
+const CHATML_TEMPLATE: &str = "{% for message in messages %}{{ '<|im_start|>' + message['role'] + '\n' + message['content'] + '<|im_end|>' + '\n' }}{% endfor %}{% if add_generation_prompt %}{{ '<|im_start|>assistant\n' }}{% endif %}";
+
+const LLAMA3_TEMPLATE: &str = "{{ bos_token }}{% for message in messages %}{{ '<|start_header_id|>' + message['role'] + '<|end_header_id|>\n\n' + message['content'] | trim + '<|eot_id|>' }}{% endfor %}{% if add_generation_prompt %}{{ '<|start_header_id|>assistant<|end_header_id|>\n\n' }}{% endif %}";
+
+const MISTRAL_TEMPLATE: &str = "{{ bos_token }}{% set ns = namespace(system='') %}{% for message in messages %}{% if message['role'] == 'system' %}{% set ns.system = message['content'] + '\n\n' %}{% elif message['role'] == 'user' %}{{ '[INST] ' + ns.system + message['content'] + ' [/INST]' }}{% set ns.system = '' %}{% elif message['role'] == 'assistant' %}{{ message['content'] + eos_token }}{% endif %}{% endfor %}";
+
+const GEMMA_TEMPLATE: &str = "{{ bos_token }}{% for message in messages %}{% if message['role'] == 'assistant' %}{% set role = 'model' %}{% else %}{% set role = 'user' %}{% endif %}{{ '<start_of_turn>' + role + '\n' + message['content'] | trim + '<end_of_turn>\n' }}{% endfor %}{% if add_generation_prompt %}{{ '<start_of_turn>model\n' }}{% endif %}";
+
+const PHI3_TEMPLATE: &str = "{% for message in messages %}{{ '<|' + message['role'] + '|>\n' + message['content'] + '<|end|>\n' }}{% endfor %}{% if add_generation_prompt %}{{ '<|assistant|>\n' }}{% endif %}";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_from_str() {
+        assert_eq!(
+            "llama-3".parse::<BuiltinChatTemplate>().unwrap(),
+            BuiltinChatTemplate::Llama3
+        );
+        assert_eq!(
+            "ChatML".parse::<BuiltinChatTemplate>().unwrap(),
+            BuiltinChatTemplate::ChatMl
+        );
+        assert!("vicuna".parse::<BuiltinChatTemplate>().is_err());
+    }
+
+    #[test]
+    fn test_template_from_file() {
+        let path = std::env::temp_dir().join("llm_interface_chat_template_test.jinja");
+        std::fs::write(&path, CHATML_TEMPLATE).unwrap();
+        let template = ChatTemplateOverride::File(path.clone())
+            .template_string()
+            .unwrap();
+        assert_eq!(template, CHATML_TEMPLATE);
+        std::fs::remove_file(path).unwrap();
+    }
+}
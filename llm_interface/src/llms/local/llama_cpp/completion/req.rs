@@ -3,9 +3,16 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Default, Debug, Deserialize, PartialEq)]
 pub struct LlamaCppCompletionRequest {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub prompt: Vec<u32>,
     #[serde(skip)]
     pub prompt_string: Option<String>,
+    /// The text before the cursor for the `/infill` endpoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_prefix: Option<String>,
+    /// The text after the cursor for the `/infill` endpoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_suffix: Option<String>,
     /// A formatted "Grammar" as a string.
     /// See: https://github.com/richardanaya/gbnf/blob/main/gbnf/src/lib.rs
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -46,25 +53,60 @@ pub struct LlamaCppCompletionRequest {
     /// min: 0.0, max: 1.0, default: None
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
+    /// Limit the next token selection to the K most probable tokens. default: None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// The sampler's RNG seed. default: None (random)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// The number of most likely tokens to return the probabilities of for each generated token. default: 0
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n_probs: Option<u8>,
 }
 
 impl LlamaCppCompletionRequest {
+    /// The candidates returned per token when `RequestConfig::logprobs` is set.
+    /// The server lists the most likely candidates rather than the sampled token, so a few are requested to usually include it.
+    pub const N_PROBS: u8 = 5;
+
     pub fn new(req: &CompletionRequest) -> crate::Result<Self, CompletionError> {
         let cache_prompt = if req.config.cache_prompt {
             Some(true)
         } else {
             None
         };
-        Ok(Self {
-            prompt: req
-                .prompt
-                .get_built_prompt_as_tokens()
-                .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?,
-            prompt_string: Some(
+        let (prompt, prompt_string, input_prefix, input_suffix) = match &req.raw_prompt {
+            None => (
                 req.prompt
-                    .get_built_prompt_string()
+                    .get_built_prompt_as_tokens()
                     .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?,
+                Some(
+                    req.prompt
+                        .get_built_prompt_string()
+                        .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?,
+                ),
+                None,
+                None,
             ),
+            Some(RawPrompt::Text(text)) => (
+                req.backend.tokenizer().tokenize(text),
+                Some(text.to_owned()),
+                None,
+                None,
+            ),
+            Some(RawPrompt::Tokens(tokens)) => (tokens.clone(), None, None, None),
+            Some(RawPrompt::Infill { prefix, suffix }) => (
+                Vec::new(),
+                None,
+                Some(prefix.to_owned()),
+                Some(suffix.to_owned()),
+            ),
+        };
+        Ok(Self {
+            prompt,
+            prompt_string,
+            input_prefix,
+            input_suffix,
             grammar: req.grammar_string.clone(),
             cache_prompt,
             logit_bias: req
@@ -78,6 +120,18 @@ impl LlamaCppCompletionRequest {
             stop: Some(req.stop_sequences.to_vec()),
             temperature: Some(req.config.temperature),
             top_p: req.config.top_p,
+            top_k: req.config.top_k,
+            seed: req.config.seed,
+            n_probs: req.config.logprobs.then_some(Self::N_PROBS),
         })
     }
+
+    /// The server endpoint for the request. Fill-in-the-middle prompts use `/infill`.
+    pub fn path(&self) -> &'static str {
+        if self.input_prefix.is_some() || self.input_suffix.is_some() {
+            "/infill"
+        } else {
+            "/completion"
+        }
+    }
 }
@@ -31,11 +31,17 @@ impl CompletionResponse {
             index: None,
             content: res.content.to_owned(),
             finish_reason,
-            completion_probabilities: None,
+            completion_probabilities: res.completion_probabilities.as_ref().map(|probs| {
+                probs
+                    .iter()
+                    .map(LlamaCompletionProbabilities::to_inference_probabilities)
+                    .collect()
+            }),
             truncated: res.truncated,
             generation_settings: GenerationSettings::new_from_llama(&res),
             timing_usage: TimingUsage::new_from_llama(&res, req.start_time),
             token_usage: TokenUsage::new_from_llama(&res),
+            served_by: None,
         })
     }
 }
@@ -55,6 +61,40 @@ pub struct LlamaCppCompletionResponse {
     pub tokens_cached: u16,
     pub tokens_evaluated: u16,
     pub truncated: bool,
+    /// Returned when the request sets `n_probs`.
+    #[serde(default)]
+    pub completion_probabilities: Option<Vec<LlamaCompletionProbabilities>>,
+}
+
+/// A generated token and the most likely candidates for its position.
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct LlamaCompletionProbabilities {
+    pub content: String,
+    pub probs: Vec<LlamaTokenProbability>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct LlamaTokenProbability {
+    pub tok_str: String,
+    /// The probability of the token, between 0.0 and 1.0.
+    pub prob: f32,
+}
+
+impl LlamaCompletionProbabilities {
+    /// Converts the server's probabilities to log probabilities.
+    fn to_inference_probabilities(&self) -> InferenceProbabilities {
+        InferenceProbabilities {
+            content: Some(self.content.clone()),
+            top_probs: self
+                .probs
+                .iter()
+                .map(|prob| TopProbabilities {
+                    token: prob.tok_str.clone(),
+                    prob: prob.prob.ln(),
+                })
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
@@ -88,3 +128,20 @@ pub enum Stop {
     String(String),           // nullable: true
     StringArray(Vec<String>), // minItems: 1; maxItems: 4
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completion_probabilities() {
+        let probs: LlamaCompletionProbabilities = serde_json::from_str(
+            r#"{"content":" yes","probs":[{"tok_str":" yes","prob":1.0},{"tok_str":" no","prob":0.5}]}"#,
+        )
+        .unwrap();
+        let probs = probs.to_inference_probabilities();
+        assert_eq!(probs.content.as_deref(), Some(" yes"));
+        assert_eq!(probs.top_probs[0].prob, 0.0);
+        assert!((probs.top_probs[1].prob - 0.5f32.ln()).abs() < f32::EPSILON);
+    }
+}
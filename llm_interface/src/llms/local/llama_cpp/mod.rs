@@ -67,11 +67,8 @@ impl LlamaCppBackend {
         &self,
         request: &CompletionRequest,
     ) -> crate::Result<CompletionResponse, CompletionError> {
-        match self
-            .client
-            .post("/completion", LlamaCppCompletionRequest::new(request)?)
-            .await
-        {
+        let llama_request = LlamaCppCompletionRequest::new(request)?;
+        match self.client.post(llama_request.path(), llama_request).await {
             Err(e) => Err(CompletionError::ClientError(e)),
             Ok(res) => Ok(CompletionResponse::new_from_llama(request, res)?),
         }
@@ -14,7 +14,7 @@ pub fn new(
         frequency_penalty: request.config.frequency_penalty,
         presence_penalty: Some(request.config.presence_penalty),
         max_len: request.config.actual_request_tokens.map(|val| val as usize),
-        top_k: None,
+        top_k: request.config.top_k.map(|val| val as usize),
         top_p: request.config.top_p.map(|val| val as f64),
         min_p: None,
         top_n_logprobs: 0,
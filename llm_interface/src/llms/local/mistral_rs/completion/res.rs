@@ -32,6 +32,7 @@ impl CompletionResponse {
             generation_settings: GenerationSettings::new_from_mistral(req, &res),
             timing_usage: TimingUsage::new_from_mistral(&res, req.start_time),
             token_usage: TokenUsage::new_from_mistral(&res),
+            served_by: None,
         })
     }
 }
@@ -1,3 +1,4 @@
+use chat_template::{BuiltinChatTemplate, ChatTemplateOverride};
 #[cfg(any(target_os = "linux", target_os = "windows"))]
 use llm_devices::devices::CudaConfig;
 use llm_devices::devices::DeviceConfig;
@@ -7,6 +8,7 @@ use llm_utils::models::local_model::{
     gguf::GgufLoader, metadata::llm::DEFAULT_CONTEXT_LENGTH, LocalLlmModel,
 };
 
+pub mod chat_template;
 #[cfg(feature = "llama_cpp_backend")]
 pub mod llama_cpp;
 #[cfg(feature = "mistral_rs_backend")]
@@ -17,6 +19,8 @@ pub struct LocalLlmConfig {
     pub batch_size: u64,
     pub inference_ctx_size: u64,
     pub device_config: DeviceConfig,
+    /// Replaces the chat template from the GGUF file when set.
+    pub chat_template: Option<ChatTemplateOverride>,
 }
 
 impl Default for LocalLlmConfig {
@@ -25,13 +29,14 @@ impl Default for LocalLlmConfig {
             batch_size: 512,
             inference_ctx_size: DEFAULT_CONTEXT_LENGTH,
             device_config: DeviceConfig::default(),
+            chat_template: None,
         }
     }
 }
 
 impl LocalLlmConfig {
     pub fn load_model(&mut self, mut llm_loader: GgufLoader) -> crate::Result<LocalLlmModel> {
-        let model = if llm_loader.gguf_local_loader.local_quant_file_path.is_none()
+        let mut model = if llm_loader.gguf_local_loader.local_quant_file_path.is_none()
             || llm_loader.gguf_hf_loader.hf_quant_file_url.is_none()
         {
             self.load_preset_model(llm_loader)?
@@ -44,6 +49,10 @@ impl LocalLlmConfig {
             self.inference_ctx_size = model.model_metadata.context_length();
         };
 
+        if let Some(chat_template) = &self.chat_template {
+            chat_template.apply(&mut model)?;
+        }
+
         self.device_config.layer_count = Some(model.model_metadata.layers.count_blocks());
         self.device_config.average_layer_size_bytes = Some(
             model
@@ -323,6 +332,38 @@ pub trait LlmLocalTrait {
         self
     }
 
+    /// Replaces the chat template embedded in the GGUF file with a Jinja template string.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - A Jinja chat template, in the format of Hugging Face `tokenizer_config.json` templates.
+    fn chat_template<T: AsRef<str>>(mut self, template: T) -> Self
+    where
+        Self: Sized,
+    {
+        self.config().chat_template =
+            Some(ChatTemplateOverride::Jinja(template.as_ref().to_owned()));
+        self
+    }
+
+    /// Replaces the chat template embedded in the GGUF file with a Jinja template read from a file when the model is loaded.
+    fn chat_template_file<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self
+    where
+        Self: Sized,
+    {
+        self.config().chat_template = Some(ChatTemplateOverride::File(path.into()));
+        self
+    }
+
+    /// Replaces the chat template embedded in the GGUF file with a built-in template.
+    fn builtin_chat_template(mut self, template: BuiltinChatTemplate) -> Self
+    where
+        Self: Sized,
+    {
+        self.config().chat_template = Some(ChatTemplateOverride::Builtin(template));
+        self
+    }
+
     #[cfg(any(target_os = "linux", target_os = "windows"))]
     /// Sets the CUDA configuration for GPU inference.
     ///
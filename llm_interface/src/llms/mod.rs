@@ -4,10 +4,13 @@ use crate::requests::{
     },
     constraints::logit_bias::LogitBias,
 };
+use capabilities::BackendCapabilities;
 use llm_utils::prompting::LlmPrompt;
 pub mod api;
+pub mod capabilities;
 #[cfg(any(feature = "llama_cpp_backend", feature = "mistral_rs_backend"))]
 pub mod local;
+pub mod router;
 
 pub enum LlmBackend {
     #[cfg(feature = "llama_cpp_backend")]
@@ -17,6 +20,7 @@ pub enum LlmBackend {
     OpenAi(api::openai::OpenAiBackend),
     Anthropic(api::anthropic::AnthropicBackend),
     GenericApi(api::generic_openai::GenericApiBackend),
+    Router(router::RouterBackend),
 }
 
 impl LlmBackend {
@@ -32,6 +36,7 @@ impl LlmBackend {
             LlmBackend::OpenAi(b) => b.completion_request(request).await,
             LlmBackend::Anthropic(b) => b.completion_request(request).await,
             LlmBackend::GenericApi(b) => b.completion_request(request).await,
+            LlmBackend::Router(b) => b.completion_request(request).await,
         }
     }
 
@@ -64,6 +69,7 @@ impl LlmBackend {
             LlmBackend::OpenAi(b) => LlmPrompt::new_openai_prompt(&b.model),
             LlmBackend::Anthropic(b) => LlmPrompt::new_openai_prompt(&b.model),
             LlmBackend::GenericApi(b) => LlmPrompt::new_openai_prompt(&b.model),
+            LlmBackend::Router(b) => b.primary().new_prompt(),
         }
     }
 
@@ -76,6 +82,7 @@ impl LlmBackend {
             LlmBackend::OpenAi(b) => &b.model.model_base.model_id,
             LlmBackend::Anthropic(b) => &b.model.model_base.model_id,
             LlmBackend::GenericApi(b) => &b.model.model_base.model_id,
+            LlmBackend::Router(b) => b.primary().model_id(),
         }
     }
 
@@ -88,6 +95,12 @@ impl LlmBackend {
             LlmBackend::OpenAi(b) => b.model.model_base.model_ctx_size,
             LlmBackend::Anthropic(b) => b.model.model_base.model_ctx_size,
             LlmBackend::GenericApi(b) => b.model.model_base.model_ctx_size,
+            LlmBackend::Router(b) => b
+                .backends
+                .iter()
+                .map(|m| m.backend.model_ctx_size())
+                .max()
+                .unwrap_or_default(),
         }
     }
 
@@ -100,6 +113,12 @@ impl LlmBackend {
             LlmBackend::OpenAi(b) => b.model.model_base.inference_ctx_size,
             LlmBackend::Anthropic(b) => b.model.model_base.inference_ctx_size,
             LlmBackend::GenericApi(b) => b.model.model_base.inference_ctx_size,
+            LlmBackend::Router(b) => b
+                .backends
+                .iter()
+                .map(|m| m.backend.inference_ctx_size())
+                .max()
+                .unwrap_or_default(),
         }
     }
 
@@ -112,6 +131,7 @@ impl LlmBackend {
             LlmBackend::OpenAi(b) => &b.model.model_base.tokenizer,
             LlmBackend::Anthropic(b) => &b.model.model_base.tokenizer,
             LlmBackend::GenericApi(b) => &b.model.model_base.tokenizer,
+            LlmBackend::Router(b) => b.primary().tokenizer(),
         }
     }
 
@@ -121,32 +141,77 @@ impl LlmBackend {
                 #[cfg(feature = "llama_cpp_backend")]
                 LlmBackend::LlamaCpp(_) => logit_bias.build_llama(self.tokenizer())?,
                 #[cfg(feature = "mistral_rs_backend")]
-                LlmBackend::MistralRs(_) => logit_bias.build_llama(self.tokenizer())?,
+                LlmBackend::MistralRs(_) => crate::bail!("mistral_rs does not support logit bias"),
                 LlmBackend::OpenAi(_) => logit_bias.build_openai(self.tokenizer())?,
-                LlmBackend::Anthropic(_) => unreachable!("Anthropic does not support logit bias"),
+                LlmBackend::Anthropic(_) => crate::bail!("Anthropic does not support logit bias"),
                 LlmBackend::GenericApi(_) => logit_bias.build_openai(self.tokenizer())?,
+                // Built for each member backend when the request is routed.
+                LlmBackend::Router(_) => (),
             };
         }
         Ok(())
     }
 
-    pub fn bos_token(&self) -> &str {
+    pub fn capabilities(&self) -> BackendCapabilities {
         match self {
             #[cfg(feature = "llama_cpp_backend")]
-            LlmBackend::LlamaCpp(b) => &b.model.chat_template.bos_token,
+            LlmBackend::LlamaCpp(_) => BackendCapabilities::llama_cpp(),
             #[cfg(feature = "mistral_rs_backend")]
-            LlmBackend::MistralRs(b) => &b.model.chat_template.bos_token,
-            _ => unimplemented!("Chat template not supported for this backend"),
+            LlmBackend::MistralRs(_) => BackendCapabilities::mistral_rs(),
+            LlmBackend::OpenAi(_) => BackendCapabilities::openai(),
+            LlmBackend::Anthropic(_) => BackendCapabilities::anthropic(),
+            LlmBackend::GenericApi(_) => BackendCapabilities::generic_api(),
+            // The primary's. Requests are validated against each member when they're routed to it.
+            LlmBackend::Router(b) => b.primary().capabilities(),
         }
     }
 
-    pub fn eos_token(&self) -> &str {
+    /// True for backends running a model on this machine, which can have their prompt cache pre-filled with [`LlmBackend::set_cache`].
+    pub fn is_local(&self) -> bool {
         match self {
             #[cfg(feature = "llama_cpp_backend")]
-            LlmBackend::LlamaCpp(b) => &b.model.chat_template.eos_token,
+            LlmBackend::LlamaCpp(_) => true,
             #[cfg(feature = "mistral_rs_backend")]
-            LlmBackend::MistralRs(b) => &b.model.chat_template.eos_token,
-            _ => unimplemented!("Chat template not supported for this backend"),
+            LlmBackend::MistralRs(_) => true,
+            LlmBackend::OpenAi(_) | LlmBackend::Anthropic(_) | LlmBackend::GenericApi(_) => false,
+            LlmBackend::Router(b) => b.primary().is_local(),
+        }
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(_) => "llama_cpp",
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(_) => "mistral_rs",
+            LlmBackend::OpenAi(_) => "openai",
+            LlmBackend::Anthropic(_) => "anthropic",
+            LlmBackend::GenericApi(_) => "generic_api",
+            LlmBackend::Router(_) => "router",
+        }
+    }
+
+    /// The BOS token of the model's chat template. `None` for API backends, which don't expose a chat template.
+    pub fn bos_token(&self) -> Option<&str> {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => Some(&b.model.chat_template.bos_token),
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(b) => Some(&b.model.chat_template.bos_token),
+            LlmBackend::OpenAi(_) | LlmBackend::Anthropic(_) | LlmBackend::GenericApi(_) => None,
+            LlmBackend::Router(b) => b.primary().bos_token(),
+        }
+    }
+
+    /// The EOS token of the model's chat template. `None` for API backends, which don't expose a chat template.
+    pub fn eos_token(&self) -> Option<&str> {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => Some(&b.model.chat_template.eos_token),
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(b) => Some(&b.model.chat_template.eos_token),
+            LlmBackend::OpenAi(_) | LlmBackend::Anthropic(_) | LlmBackend::GenericApi(_) => None,
+            LlmBackend::Router(b) => b.primary().eos_token(),
         }
     }
 
@@ -187,6 +252,13 @@ impl LlmBackend {
         }
     }
 
+    pub fn router(&self) -> crate::Result<&router::RouterBackend> {
+        match self {
+            LlmBackend::Router(b) => Ok(b),
+            _ => crate::bail!("Backend is not router"),
+        }
+    }
+
     pub fn shutdown(&self) {
         match self {
             #[cfg(feature = "llama_cpp_backend")]
@@ -196,6 +268,7 @@ impl LlmBackend {
             LlmBackend::OpenAi(_) => (),
             LlmBackend::Anthropic(_) => (),
             LlmBackend::GenericApi(_) => (),
+            LlmBackend::Router(b) => b.backends.iter().for_each(|m| m.backend.shutdown()),
         }
     }
 }
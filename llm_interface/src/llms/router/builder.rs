@@ -0,0 +1,97 @@
+use super::{RoutedBackend, RouterBackend, RoutingStrategy, DEFAULT_UNHEALTHY_COOLDOWN};
+use crate::llms::LlmBackend;
+
+// Everything here can be implemented for any struct.
+pub struct RouterBackendBuilder {
+    pub backends: Vec<RoutedBackend>,
+    pub strategy: RoutingStrategy,
+    pub unhealthy_cooldown: std::time::Duration,
+}
+
+impl Default for RouterBackendBuilder {
+    fn default() -> Self {
+        Self {
+            backends: Vec::new(),
+            strategy: RoutingStrategy::default(),
+            unhealthy_cooldown: DEFAULT_UNHEALTHY_COOLDOWN,
+        }
+    }
+}
+
+impl RouterBackendBuilder {
+    pub fn init(self) -> crate::Result<std::sync::Arc<LlmBackend>> {
+        Ok(std::sync::Arc::new(LlmBackend::Router(RouterBackend::new(
+            self.backends,
+            self.strategy,
+            self.unhealthy_cooldown,
+        )?)))
+    }
+}
+
+impl RouterBackendTrait for RouterBackendBuilder {
+    fn router_builder(&mut self) -> &mut RouterBackendBuilder {
+        self
+    }
+}
+
+pub trait RouterBackendTrait {
+    fn router_builder(&mut self) -> &mut RouterBackendBuilder;
+
+    /// Adds a backend to the end of the router's ordered list. The first backend added is the primary.
+    fn add_backend(mut self, backend: std::sync::Arc<LlmBackend>) -> Self
+    where
+        Self: Sized,
+    {
+        self.router_builder()
+            .backends
+            .push(RoutedBackend::new(backend, 0.0));
+        self
+    }
+
+    /// Adds a backend with a relative cost used by the cheapest-that-fits strategy.
+    fn add_backend_with_cost(mut self, backend: std::sync::Arc<LlmBackend>, cost: f32) -> Self
+    where
+        Self: Sized,
+    {
+        self.router_builder()
+            .backends
+            .push(RoutedBackend::new(backend, cost));
+        self
+    }
+
+    /// Use the first backend that has not recently failed. This is the default.
+    fn first_healthy(mut self) -> Self
+    where
+        Self: Sized,
+    {
+        self.router_builder().strategy = RoutingStrategy::FirstHealthy;
+        self
+    }
+
+    /// Use the cheapest backend whose context size fits the request.
+    fn cheapest_that_fits(mut self) -> Self
+    where
+        Self: Sized,
+    {
+        self.router_builder().strategy = RoutingStrategy::CheapestThatFits;
+        self
+    }
+
+    /// Spread requests across the backends in turn.
+    fn round_robin(mut self) -> Self
+    where
+        Self: Sized,
+    {
+        self.router_builder().strategy = RoutingStrategy::RoundRobin;
+        self
+    }
+
+    /// How long a failed backend is moved to the back of the list. Defaults to 30 seconds.
+    fn unhealthy_cooldown(mut self, cooldown: std::time::Duration) -> Self
+    where
+        Self: Sized,
+    {
+        self.router_builder().unhealthy_cooldown = cooldown;
+        self
+    }
+}
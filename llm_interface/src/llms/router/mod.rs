@@ -0,0 +1,404 @@
+pub mod builder;
+
+use super::LlmBackend;
+use crate::requests::completion::{
+    error::CompletionError,
+    request::{CompletionRequest, RawPrompt},
+    response::CompletionResponse,
+    response::ServedBy,
+};
+use llm_utils::prompting::LlmPrompt;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// How long a backend is skipped after it fails, before it is tried again.
+pub const DEFAULT_UNHEALTHY_COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RoutingStrategy {
+    /// Try the backends in the order they were added, skipping any that recently failed.
+    #[default]
+    FirstHealthy,
+    /// Try the backend with the lowest cost whose `model_ctx_size` fits the request first.
+    /// Backends that do not fit are never tried.
+    CheapestThatFits,
+    /// Rotate the starting backend on every request to spread the load.
+    RoundRobin,
+}
+
+/// A member of a [`RouterBackend`].
+pub struct RoutedBackend {
+    pub backend: Arc<LlmBackend>,
+    /// Relative cost used by [`RoutingStrategy::CheapestThatFits`]. Any unit works as long as it is consistent
+    /// across members, e.g. dollars per million tokens. Defaults to `0.0`.
+    pub cost: f32,
+    health: BackendHealth,
+}
+
+impl RoutedBackend {
+    pub fn new(backend: Arc<LlmBackend>, cost: f32) -> Self {
+        Self {
+            backend,
+            cost,
+            health: BackendHealth::default(),
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.health.is_healthy()
+    }
+}
+
+/// Whether a member recently failed, and until when it is skipped.
+#[derive(Default)]
+struct BackendHealth {
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+impl BackendHealth {
+    fn is_healthy(&self) -> bool {
+        match *self
+            .unhealthy_until
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+        {
+            Some(until) => Instant::now() >= until,
+            None => true,
+        }
+    }
+
+    fn mark_unhealthy(&self, cooldown: Duration) {
+        *self
+            .unhealthy_until
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(Instant::now() + cooldown);
+    }
+
+    fn mark_healthy(&self) {
+        *self
+            .unhealthy_until
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// A composite backend that sends each request to one of an ordered list of backends,
+/// failing over to the next one on retryable errors.
+///
+/// The prompt, tokenizer and model id of the router are those of the first (primary) backend.
+/// When a request is served by another member, the prompt's messages are rebuilt in that member's prompt format.
+pub struct RouterBackend {
+    pub backends: Vec<RoutedBackend>,
+    pub strategy: RoutingStrategy,
+    pub unhealthy_cooldown: Duration,
+    next_index: AtomicUsize,
+}
+
+impl RouterBackend {
+    pub fn new(
+        backends: Vec<RoutedBackend>,
+        strategy: RoutingStrategy,
+        unhealthy_cooldown: Duration,
+    ) -> crate::Result<Self> {
+        if backends.is_empty() {
+            crate::bail!("RouterBackend requires at least one backend");
+        }
+        if backends
+            .iter()
+            .any(|b| matches!(*b.backend, LlmBackend::Router(_)))
+        {
+            crate::bail!("RouterBackend members can not be routers themselves");
+        }
+        Ok(Self {
+            backends,
+            strategy,
+            unhealthy_cooldown,
+            next_index: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn primary(&self) -> &Arc<LlmBackend> {
+        &self.backends[0].backend
+    }
+
+    pub(crate) async fn completion_request(
+        &self,
+        request: &CompletionRequest,
+    ) -> crate::Result<CompletionResponse, CompletionError> {
+        let total_prompt_tokens = request.total_prompt_tokens()?;
+        let candidates = self.route(total_prompt_tokens + request.config.safety_tokens);
+        if candidates.is_empty() {
+            return Err(CompletionError::RequestBuilderError(format!(
+                "No backend in the router has a context size that fits {total_prompt_tokens} prompt tokens"
+            )));
+        }
+
+        let mut failed_over_from: Vec<(usize, String)> = Vec::new();
+        let mut last_error = None;
+        for index in candidates {
+            let member = &self.backends[index];
+            // A request that can't be built for this backend, e.g. its context is too small, skips it without marking it unhealthy.
+            let member_request = match self.member_request(index, request) {
+                Ok(member_request) => member_request,
+                Err(e) => {
+                    crate::warn!("Router backend {index} skipped: {e}");
+                    failed_over_from.push((index, e.to_string()));
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+            match member.backend.completion_request(&member_request).await {
+                Ok(mut res) => {
+                    member.health.mark_healthy();
+                    res.served_by = Some(ServedBy {
+                        backend_index: index,
+                        model_id: member.backend.model_id().to_owned(),
+                        failed_over_from,
+                    });
+                    return Ok(res);
+                }
+                Err(e) if e.is_retryable() => {
+                    crate::warn!(
+                        "Router backend {index} ({}) failed, failing over: {e}",
+                        member.backend.model_id()
+                    );
+                    member.health.mark_unhealthy(self.unhealthy_cooldown);
+                    failed_over_from.push((index, e.to_string()));
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.expect("at least one candidate was tried"))
+    }
+
+    /// Returns the indexes of the backends to try, in order.
+    fn route(&self, required_ctx_tokens: u64) -> Vec<usize> {
+        let members: Vec<RouteCandidate> = self
+            .backends
+            .iter()
+            .map(|member| RouteCandidate {
+                model_ctx_size: member.backend.model_ctx_size(),
+                cost: member.cost,
+                healthy: member.is_healthy(),
+            })
+            .collect();
+        let start = match self.strategy {
+            RoutingStrategy::RoundRobin => self.next_index.fetch_add(1, Ordering::Relaxed),
+            _ => 0,
+        };
+        route_order(self.strategy, &members, required_ctx_tokens, start)
+    }
+
+    /// Copies the request for a member backend, with the member's context sizes and prompt format.
+    fn member_request(
+        &self,
+        index: usize,
+        request: &CompletionRequest,
+    ) -> crate::Result<CompletionRequest, CompletionError> {
+        let backend = &self.backends[index].backend;
+        let mut member_request = request.clone();
+        member_request.backend = Arc::clone(backend);
+        if let Some(RawPrompt::Tokens(tokens)) = &request.raw_prompt {
+            // Token ids only mean the same text to a backend with the primary's tokenizer.
+            let primary = self.primary();
+            if !Arc::ptr_eq(primary.tokenizer(), backend.tokenizer())
+                && primary.model_id() != backend.model_id()
+            {
+                let text = primary
+                    .tokenizer()
+                    .detokenize_many(tokens)
+                    .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?;
+                member_request.raw_prompt =
+                    Some(RawPrompt::Tokens(backend.tokenizer().tokenize(&text)));
+            }
+        }
+        if index != 0 {
+            member_request.prompt = rebuild_prompt(backend, &request.prompt)
+                .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?;
+            if let Some(generation_prefix) = &request.generation_prefix {
+                member_request.set_generation_prefix(generation_prefix);
+            }
+        }
+        backend
+            .capabilities()
+            .validate(&member_request, backend.backend_name())?;
+        backend
+            .build_logit_bias(&mut member_request.logit_bias)
+            .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?;
+
+        let total_prompt_tokens = member_request.total_prompt_tokens()?;
+        let config = &mut member_request.config;
+        config.model_ctx_size = backend.model_ctx_size();
+        config.inference_ctx_size = backend.inference_ctx_size();
+        // If the max tokens were filled in from the router's context size rather than requested by the user, recalculate them for this backend.
+        if request.config.response_tokens_filled_in {
+            config.requested_response_tokens = None;
+        }
+        config.set_max_tokens_for_request(total_prompt_tokens)?;
+        Ok(member_request)
+    }
+}
+
+fn rebuild_prompt(backend: &LlmBackend, prompt: &LlmPrompt) -> crate::Result<LlmPrompt> {
+    let mut new_prompt = backend.new_prompt();
+    for message in prompt.get_built_prompt_hashmap()? {
+        let content = message
+            .get("content")
+            .ok_or_else(|| crate::anyhow!("Content not found"))?;
+        match message.get("role").map(|r| r.as_str()) {
+            Some("system") => new_prompt.add_system_message()?.set_content(content),
+            Some("user") => new_prompt.add_user_message()?.set_content(content),
+            Some("assistant") => new_prompt.add_assistant_message()?.set_content(content),
+            role => crate::bail!("Role {:?} not supported", role),
+        };
+    }
+    Ok(new_prompt)
+}
+
+/// What `route_order` needs to know about a member.
+struct RouteCandidate {
+    model_ctx_size: u64,
+    cost: f32,
+    healthy: bool,
+}
+
+/// The order to try the members in. `start` is the request count, used by [`RoutingStrategy::RoundRobin`].
+fn route_order(
+    strategy: RoutingStrategy,
+    members: &[RouteCandidate],
+    required_ctx_tokens: u64,
+    start: usize,
+) -> Vec<usize> {
+    let count = members.len();
+    let mut order: Vec<usize> = match strategy {
+        RoutingStrategy::FirstHealthy => (0..count).collect(),
+        RoutingStrategy::RoundRobin => (0..count).map(|i| (start + i) % count).collect(),
+        RoutingStrategy::CheapestThatFits => {
+            let mut fits: Vec<usize> = (0..count)
+                .filter(|&i| members[i].model_ctx_size >= required_ctx_tokens)
+                .collect();
+            fits.sort_by(|&a, &b| {
+                members[a]
+                    .cost
+                    .partial_cmp(&members[b].cost)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            fits
+        }
+    };
+    // Unhealthy backends are tried last rather than never, so a request still has a chance when every backend has failed recently.
+    order.sort_by_key(|&i| !members[i].healthy);
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llms::api::generic_openai::{GenericApiBackend, GenericApiConfig};
+    use llm_utils::models::api_model::ApiLlmModel;
+
+    fn member(model_ctx_size: u64, cost: f32, healthy: bool) -> RouteCandidate {
+        RouteCandidate {
+            model_ctx_size,
+            cost,
+            healthy,
+        }
+    }
+
+    #[test]
+    fn test_route_order() {
+        let members = [
+            member(4096, 3.0, true),
+            member(8192, 1.0, false),
+            member(2048, 0.5, true),
+        ];
+        assert_eq!(
+            route_order(RoutingStrategy::FirstHealthy, &members, 1000, 0),
+            vec![0, 2, 1]
+        );
+        assert_eq!(
+            route_order(RoutingStrategy::RoundRobin, &members, 1000, 4),
+            vec![2, 0, 1]
+        );
+        assert_eq!(
+            route_order(RoutingStrategy::CheapestThatFits, &members, 1000, 0),
+            vec![2, 0, 1]
+        );
+        // Backends too small for the request are never tried.
+        assert_eq!(
+            route_order(RoutingStrategy::CheapestThatFits, &members, 3000, 0),
+            vec![0, 1]
+        );
+        assert!(route_order(RoutingStrategy::CheapestThatFits, &members, 10000, 0).is_empty());
+    }
+
+    #[test]
+    fn test_backend_health_cooldown() {
+        let health = BackendHealth::default();
+        assert!(health.is_healthy());
+        health.mark_unhealthy(Duration::from_secs(60));
+        assert!(!health.is_healthy());
+        health.mark_healthy();
+        assert!(health.is_healthy());
+        health.mark_unhealthy(Duration::ZERO);
+        assert!(health.is_healthy());
+    }
+
+    fn generic_api_backend(model_ctx_size: Option<u64>) -> Arc<LlmBackend> {
+        let mut model = ApiLlmModel::gpt_4_o_mini();
+        if let Some(model_ctx_size) = model_ctx_size {
+            model.model_base.model_ctx_size = model_ctx_size;
+            model.model_base.inference_ctx_size = model_ctx_size / 2;
+        }
+        let backend = GenericApiBackend::new(GenericApiConfig::default(), model).unwrap();
+        Arc::new(LlmBackend::GenericApi(backend))
+    }
+
+    #[test]
+    fn test_member_request_max_tokens() {
+        let router = RouterBackend::new(
+            vec![
+                RoutedBackend::new(generic_api_backend(Some(2048)), 1.0),
+                RoutedBackend::new(generic_api_backend(None), 1.0),
+            ],
+            RoutingStrategy::FirstHealthy,
+            DEFAULT_UNHEALTHY_COOLDOWN,
+        )
+        .unwrap();
+        let request = || {
+            let mut req = CompletionRequest::new(Arc::clone(router.primary()));
+            req.prompt.add_user_message().unwrap().set_content("Hello");
+            req
+        };
+
+        // Filled in from the primary's context size, so recalculated for the larger member.
+        let mut filled_in = request();
+        let prompt_tokens = filled_in.total_prompt_tokens().unwrap();
+        filled_in
+            .config
+            .set_max_tokens_for_request(prompt_tokens)
+            .unwrap();
+        let primary_max_tokens = filled_in.config.requested_response_tokens.unwrap();
+        let member = router.member_request(1, &filled_in).unwrap();
+        assert!(member.config.requested_response_tokens.unwrap() > primary_max_tokens);
+
+        // Set by the user to the same value, so kept.
+        let mut user_set = request();
+        user_set.config.requested_response_tokens = Some(primary_max_tokens);
+        user_set
+            .config
+            .set_max_tokens_for_request(prompt_tokens)
+            .unwrap();
+        let member = router.member_request(1, &user_set).unwrap();
+        assert_eq!(
+            member.config.requested_response_tokens,
+            Some(primary_max_tokens)
+        );
+    }
+}
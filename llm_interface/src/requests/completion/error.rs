@@ -11,6 +11,8 @@ pub enum CompletionError {
     RequestTokenLimitError(#[from] llm_utils::prompting::token_count::RequestTokenLimitError),
     #[error("StopReasonUnsupported: {0}")]
     StopReasonUnsupported(String),
+    #[error("CapabilityError: {0}")]
+    CapabilityError(#[from] crate::llms::capabilities::CapabilityError),
     #[error("ExceededRetryCount")]
     ExceededRetryCount {
         message: String,
@@ -30,3 +32,16 @@ pub enum CompletionError {
     )]
     NonMatchingStopSequence(String),
 }
+
+impl CompletionError {
+    /// Whether the same request might succeed on another attempt or another backend.
+    /// Errors in building the request, an unsupported stop reason, or a setting the backend doesn't support will fail the same way on every attempt.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self,
+            CompletionError::RequestBuilderError(_)
+                | CompletionError::StopReasonUnsupported(_)
+                | CompletionError::CapabilityError(_)
+        )
+    }
+}
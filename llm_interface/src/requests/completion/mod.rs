@@ -2,7 +2,9 @@ pub mod error;
 pub mod request;
 pub mod response;
 
-pub use super::res_components::{GenerationSettings, TimingUsage, TokenUsage};
+pub use super::res_components::{
+    GenerationSettings, InferenceProbabilities, TimingUsage, TokenUsage, TopProbabilities,
+};
 pub use error::CompletionError;
-pub use request::CompletionRequest;
-pub use response::{CompletionFinishReason, CompletionResponse};
+pub use request::{CompletionRequest, RawPrompt};
+pub use response::{CompletionFinishReason, CompletionResponse, ServedBy};
@@ -15,6 +15,11 @@ pub struct CompletionRequest {
     pub grammar_string: Option<String>,
     pub logit_bias: Option<LogitBias>,
     pub prompt: LlmPrompt,
+    /// The start of the assistant's response, which the model continues from.
+    /// Set with [`CompletionRequest::set_generation_prefix`] so it's applied to both local and API backends.
+    pub generation_prefix: Option<String>,
+    /// An untemplated prompt for base and fill-in-the-middle models. When set, it is sent instead of `prompt`.
+    pub raw_prompt: Option<RawPrompt>,
     pub config: RequestConfig,
     pub backend: std::sync::Arc<LlmBackend>,
     pub llm_interface_errors: Vec<CompletionError>,
@@ -28,6 +33,8 @@ impl Clone for CompletionRequest {
             grammar_string: self.grammar_string.clone(),
             logit_bias: self.logit_bias.clone(),
             prompt: self.prompt.clone(),
+            generation_prefix: self.generation_prefix.clone(),
+            raw_prompt: self.raw_prompt.clone(),
             config: self.config.clone(),
             backend: std::sync::Arc::clone(&self.backend),
             llm_interface_errors: Vec::new(),
@@ -43,6 +50,8 @@ impl CompletionRequest {
             logit_bias: None,
             config: RequestConfig::new(backend.model_ctx_size(), backend.inference_ctx_size()),
             prompt: backend.new_prompt(),
+            generation_prefix: None,
+            raw_prompt: None,
             grammar_string: None,
             backend: std::sync::Arc::clone(&backend),
             llm_interface_errors: Vec::new(),
@@ -51,21 +60,80 @@ impl CompletionRequest {
 
     pub fn reset_completion_request(&mut self) {
         self.prompt.reset_prompt();
+        self.generation_prefix = None;
+        self.raw_prompt = None;
         self.stop_sequences.sequences.clear();
         self.grammar_string = None;
         self.logit_bias = None;
     }
 
+    /// Sets the start of the assistant's response. Local backends add it to the end of the chat template.
+    /// API backends send it as a trailing assistant message, and strip it from the returned content if the model repeats it.
+    pub fn set_generation_prefix<T: AsRef<str>>(&mut self, generation_prefix: T) {
+        self.prompt
+            .set_generation_prefix(generation_prefix.as_ref());
+        self.generation_prefix = Some(generation_prefix.as_ref().to_owned());
+    }
+
+    pub fn clear_generation_prefix(&mut self) {
+        self.prompt.clear_generation_prefix();
+        self.generation_prefix = None;
+    }
+
+    /// The number of tokens sent to the backend, from the raw prompt if one is set.
+    pub fn total_prompt_tokens(&self) -> crate::Result<u64, CompletionError> {
+        match &self.raw_prompt {
+            Some(raw_prompt) => Ok(raw_prompt.count_tokens(self.backend.tokenizer())),
+            None => self
+                .prompt
+                .get_total_prompt_tokens()
+                .map_err(|e| CompletionError::RequestBuilderError(e.to_string())),
+        }
+    }
+
+    /// Renders the prompt exactly as it would be sent, without sending it.
+    /// Local backends return the templated prompt string. API backends return the messages as JSON.
+    /// Fill-in-the-middle prompts return the prefix and suffix fields of the request as JSON.
+    pub fn render_prompt(&self) -> crate::Result<String> {
+        match &self.raw_prompt {
+            Some(RawPrompt::Text(text)) => Ok(text.to_owned()),
+            Some(RawPrompt::Tokens(tokens)) => Ok(format!("{tokens:?}")),
+            // The fields of the fill-in-the-middle request: llama.cpp's `/infill` endpoint, or a raw completion with a suffix.
+            Some(RawPrompt::Infill { prefix, suffix }) => {
+                let payload = if self.backend.is_local() {
+                    serde_json::json!({ "input_prefix": prefix, "input_suffix": suffix })
+                } else {
+                    serde_json::json!({ "prompt": prefix, "suffix": suffix })
+                };
+                Ok(serde_json::to_string_pretty(&payload)?)
+            }
+            None if self.backend.is_local() => Ok(self.prompt.get_built_prompt_string()?),
+            None => {
+                let mut messages = self.prompt.get_built_prompt_hashmap()?;
+                if let Some(prefix) = &self.generation_prefix {
+                    messages.push(std::collections::HashMap::from([
+                        ("role".to_string(), "assistant".to_string()),
+                        ("content".to_string(), prefix.to_owned()),
+                    ]));
+                }
+                Ok(serde_json::to_string_pretty(&messages)?)
+            }
+        }
+    }
+
     pub async fn request(&mut self) -> crate::Result<CompletionResponse, CompletionError> {
         self.llm_interface_errors.clear();
         self.start_time = std::time::Instant::now();
+        // A router validates the request against each member it's sent to, as the members can support different settings.
+        if !matches!(*self.backend, LlmBackend::Router(_)) {
+            self.backend
+                .capabilities()
+                .validate(self, self.backend.backend_name())?;
+        }
         self.backend
             .build_logit_bias(&mut self.logit_bias)
             .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?;
-        let total_prompt_tokens = self
-            .prompt
-            .get_total_prompt_tokens()
-            .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?;
+        let total_prompt_tokens = self.total_prompt_tokens()?;
 
         self.config
             .set_max_tokens_for_request(total_prompt_tokens)
@@ -91,6 +159,7 @@ impl CompletionRequest {
                     match e {
                         CompletionError::RequestBuilderError { .. }
                         | CompletionError::StopReasonUnsupported { .. }
+                        | CompletionError::CapabilityError { .. }
                         | CompletionError::ClientError { .. } => {
                             return Err(e);
                         }
@@ -188,3 +257,28 @@ impl std::fmt::Display for CompletionRequest {
         write!(f, "  config: {}", self.config)
     }
 }
+
+/// A prompt sent as-is, without a chat template. Used for base models and fill-in-the-middle code models.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RawPrompt {
+    Text(String),
+    /// Pre-tokenized text, using the backend's tokenizer.
+    Tokens(Vec<u32>),
+    /// Fill-in-the-middle. The model generates the text between `prefix` and `suffix`.
+    Infill {
+        prefix: String,
+        suffix: String,
+    },
+}
+
+impl RawPrompt {
+    pub fn count_tokens(&self, tokenizer: &llm_utils::tokenizer::LlmTokenizer) -> u64 {
+        match self {
+            RawPrompt::Text(text) => tokenizer.count_tokens(text) as u64,
+            RawPrompt::Tokens(tokens) => tokens.len() as u64,
+            RawPrompt::Infill { prefix, suffix } => {
+                (tokenizer.count_tokens(prefix) + tokenizer.count_tokens(suffix)) as u64
+            }
+        }
+    }
+}
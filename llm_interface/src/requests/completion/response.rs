@@ -1,4 +1,5 @@
 use crate::requests::{
+    completion::request::CompletionRequest,
     constraints::stop_sequence::StoppingSequence,
     res_components::{GenerationSettings, InferenceProbabilities, TimingUsage, TokenUsage},
 };
@@ -17,6 +18,40 @@ pub struct CompletionResponse {
     pub generation_settings: GenerationSettings,
     pub timing_usage: TimingUsage,
     pub token_usage: TokenUsage,
+    /// When the request was sent through a router backend, the member backend that generated the completion.
+    pub served_by: Option<ServedBy>,
+}
+
+/// Identifies the member of a router backend that served a request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServedBy {
+    /// The index of the backend in the router's ordered list of backends.
+    pub backend_index: usize,
+    /// The model id of the backend.
+    pub model_id: String,
+    /// Errors from backends that were tried, and failed over, before this one.
+    pub failed_over_from: Vec<(usize, String)>,
+}
+
+impl CompletionResponse {
+    /// API backends receive the generation prefix as a trailing assistant message. Some models continue from it,
+    /// and some repeat it, so it's removed from the start of the content if present.
+    pub(crate) fn strip_generation_prefix(req: &CompletionRequest, content: &str) -> String {
+        match req
+            .generation_prefix
+            .as_deref()
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+        {
+            Some(prefix) => content
+                .trim_start()
+                .strip_prefix(prefix)
+                .unwrap_or(content)
+                .trim_start()
+                .to_owned(),
+            None => content.to_owned(),
+        }
+    }
 }
 
 impl std::fmt::Display for CompletionResponse {
@@ -27,7 +62,16 @@ impl std::fmt::Display for CompletionResponse {
         writeln!(f, "    finish_reason: {}", self.finish_reason)?;
         write!(f, "    generation_settings: {}", self.generation_settings)?;
         write!(f, "    timing_usage: {}", self.timing_usage)?;
-        write!(f, "    token_usage: {}", self.token_usage)
+        write!(f, "    token_usage: {}", self.token_usage)?;
+        if let Some(served_by) = &self.served_by {
+            writeln!(f)?;
+            write!(
+                f,
+                "    served_by: {} (backend index {})",
+                served_by.model_id, served_by.backend_index
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -3,8 +3,9 @@ use llm_utils::prompting::token_count::{
     RequestTokenLimitError,
     TokenState,
 };
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RequestConfig {
     /// Total token limit for input and output combined.
     ///
@@ -51,6 +52,9 @@ pub struct RequestConfig {
     /// [RequestConfig::requested_response_tokens] but may be different to ensure the request stays
     /// within the model's limits.
     pub(crate) actual_request_tokens: Option<u64>,
+    /// Set when [RequestConfig::requested_response_tokens] was filled in from the context size, rather than set by the user.
+    #[serde(default)]
+    pub(crate) response_tokens_filled_in: bool,
     /// Controls the randomness of the model's output.
     ///
     /// The temperature parameter adjusts the randomness in token selection for the model's
@@ -123,6 +127,33 @@ pub struct RequestConfig {
     ///
     /// Defaults to `None` (not used, falling back to temperature-based sampling).
     pub top_p: Option<f32>,
+    /// Limits sampling to the `k` most likely tokens.
+    ///
+    /// At each step only the top `k` tokens are considered, which removes the long tail of
+    /// low probability tokens. Lower values produce more predictable outputs.
+    ///
+    /// Supported LLMs: anthropic, llama_cpp, mistral_rs
+    ///
+    /// Defaults to `None` (not used).
+    pub top_k: Option<u32>,
+    /// The seed for the sampler's random number generator.
+    ///
+    /// With the same seed, prompt and settings, a backend will usually return the same response.
+    /// Setting a different seed for each request is a way to get varied responses at a low temperature.
+    ///
+    /// Supported LLMs: llama_cpp, openai
+    ///
+    /// Defaults to `None` (a random seed).
+    pub seed: Option<u64>,
+    /// An identifier for the end user of the request.
+    ///
+    /// Sent to the API provider for abuse detection. Should be an opaque id such as a hash or uuid,
+    /// and never contain personal information.
+    ///
+    /// Supported LLMs: anthropic (as `metadata.user_id`)
+    ///
+    /// Defaults to `None`.
+    pub user_id: Option<String>,
     /// Maximum number of retry attempts after a request failure.
     ///
     /// Specifies how many times the system should attempt to retry a failed request before giving up.
@@ -147,10 +178,22 @@ pub struct RequestConfig {
     /// When set to `true`, the system will cache the prompt and reuse it for the next request.
     /// This can potentially improve performance for repeated or similar queries.
     ///
-    /// Supported LLMs: llama_cpp
+    /// For Anthropic models the system prompt and the last user message are marked with
+    /// `cache_control` breakpoints, so long system prompts and supporting material are cached.
+    ///
+    /// Supported LLMs: llama_cpp, anthropic
     ///
     /// Defaults to `false`.
     pub cache_prompt: bool,
+    /// Return the log probability of each generated token in `CompletionResponse::completion_probabilities`.
+    ///
+    /// Backends without `BackendCapabilities::logprobs` return no probabilities.
+    ///
+    /// Supported LLMs: llama_cpp, openai
+    ///
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub logprobs: bool,
 }
 
 impl RequestConfig {
@@ -160,17 +203,45 @@ impl RequestConfig {
             inference_ctx_size,
             requested_response_tokens: None,
             actual_request_tokens: None,
+            response_tokens_filled_in: false,
             frequency_penalty: None,
             presence_penalty: 0.0,
             temperature: 1.0,
             top_p: None,
+            top_k: None,
+            seed: None,
+            user_id: None,
             safety_tokens: 10,
             retry_after_fail_n_times: 3,
             increase_limit_on_fail: false,
             cache_prompt: false,
+            logprobs: false,
+        }
+    }
+
+    /// A copy of these settings with the context sizes of `other`, for sending the same request to a different model.
+    pub fn with_ctx_size_of(&self, other: &RequestConfig) -> Self {
+        Self {
+            model_ctx_size: other.model_ctx_size,
+            inference_ctx_size: other.inference_ctx_size,
+            actual_request_tokens: None,
+            ..self.clone()
         }
     }
 
+    pub fn model_ctx_size(&self) -> u64 {
+        self.model_ctx_size
+    }
+
+    pub fn inference_ctx_size(&self) -> u64 {
+        self.inference_ctx_size
+    }
+
+    /// The response tokens of the last request, from [RequestConfig::set_max_tokens_for_request].
+    pub fn actual_request_tokens(&self) -> Option<u64> {
+        self.actual_request_tokens
+    }
+
     pub fn set_max_tokens_for_request(
         &mut self,
         total_prompt_tokens: u64,
@@ -185,6 +256,7 @@ impl RequestConfig {
         self.actual_request_tokens = Some(actual_request_tokens);
         if self.requested_response_tokens.is_none() {
             self.requested_response_tokens = Some(actual_request_tokens);
+            self.response_tokens_filled_in = true;
         }
         Ok(())
     }
@@ -254,6 +326,7 @@ pub trait RequestConfigTrait {
     /// Sets the value of [RequestConfig::requested_response_tokens].
     fn max_tokens(&mut self, max_tokens: u64) -> &mut Self {
         self.config().requested_response_tokens = Some(max_tokens);
+        self.config().response_tokens_filled_in = false;
         self
     }
 
@@ -287,6 +360,24 @@ pub trait RequestConfigTrait {
         self
     }
 
+    /// Sets the value of [RequestConfig::top_k].
+    fn top_k(&mut self, top_k: u32) -> &mut Self {
+        self.config().top_k = Some(top_k);
+        self
+    }
+
+    /// Sets the value of [RequestConfig::seed].
+    fn seed(&mut self, seed: u64) -> &mut Self {
+        self.config().seed = Some(seed);
+        self
+    }
+
+    /// Sets the value of [RequestConfig::user_id].
+    fn user_id<S: Into<String>>(&mut self, user_id: S) -> &mut Self {
+        self.config().user_id = Some(user_id.into());
+        self
+    }
+
     /// Sets the value of [RequestConfig::retry_after_fail_n_times].
     fn retry_after_fail_n_times(&mut self, retry_after_fail_n_times: u8) -> &mut Self {
         self.config().retry_after_fail_n_times = retry_after_fail_n_times;
@@ -304,6 +395,12 @@ pub trait RequestConfigTrait {
         self.config().cache_prompt = cache_prompt;
         self
     }
+
+    /// Sets the value of [RequestConfig::logprobs].
+    fn logprobs(&mut self, logprobs: bool) -> &mut Self {
+        self.config().logprobs = logprobs;
+        self
+    }
 }
 
 impl std::fmt::Display for RequestConfig {
@@ -325,6 +422,8 @@ impl std::fmt::Display for RequestConfig {
         writeln!(f, "    presence_penalty: {:?}", self.presence_penalty)?;
         writeln!(f, "    temperature: {:?}", self.temperature)?;
         writeln!(f, "    top_p: {:?}", self.top_p)?;
+        writeln!(f, "    top_k: {:?}", self.top_k)?;
+        writeln!(f, "    seed: {:?}", self.seed)?;
         writeln!(
             f,
             "    retry_after_fail_n_times: {:?}",
@@ -335,6 +434,7 @@ impl std::fmt::Display for RequestConfig {
             "    increase_limit_on_fail: {:?}",
             self.increase_limit_on_fail
         )?;
-        writeln!(f, "    cache_prompt: {:?}", self.cache_prompt)
+        writeln!(f, "    cache_prompt: {:?}", self.cache_prompt)?;
+        writeln!(f, "    logprobs: {:?}", self.logprobs)
     }
 }
@@ -252,6 +252,8 @@ impl std::fmt::Display for TimingUsage {
 pub struct TokenUsage {
     /// Number of tokens from the prompt which could be re-used from previous completion (n_past)
     pub tokens_cached: Option<u32>,
+    /// Number of tokens from the prompt written to the cache for re-use by later requests.
+    pub tokens_cache_created: Option<u32>,
     /// Number of tokens evaluated in total from the prompt. Same as tokens_evaluated.
     pub prompt_tokens: u32,
     /// Number of tokens in the generated completion. Same as predicted_n.
@@ -269,6 +271,7 @@ impl TokenUsage {
     pub fn new_from_llama(res: &LlamaCppCompletionResponse) -> Self {
         Self {
             tokens_cached: Some(res.tokens_cached as u32),
+            tokens_cache_created: None,
             prompt_tokens: res.tokens_evaluated as u32,
             completion_tokens: res.timings.predicted_n as u32,
             total_tokens: res.tokens_evaluated as u32 + res.timings.predicted_n as u32,
@@ -280,6 +283,7 @@ impl TokenUsage {
     pub fn new_from_mistral(res: &MistralCompletionResponse) -> Self {
         Self {
             tokens_cached: None,
+            tokens_cache_created: None,
             prompt_tokens: res.usage.prompt_tokens as u32,
             completion_tokens: res.usage.completion_tokens as u32,
             total_tokens: res.usage.prompt_tokens as u32 + res.usage.completion_tokens as u32,
@@ -292,6 +296,7 @@ impl TokenUsage {
         if let Some(usage) = &res.usage {
             Self {
                 tokens_cached: None,
+                tokens_cache_created: None,
                 prompt_tokens: usage.prompt_tokens,
                 completion_tokens: usage.completion_tokens,
                 total_tokens: usage.total_tokens,
@@ -301,6 +306,7 @@ impl TokenUsage {
         } else {
             Self {
                 tokens_cached: None,
+                tokens_cache_created: None,
                 prompt_tokens: 0,
                 completion_tokens: 0,
                 total_tokens: 0,
@@ -311,11 +317,16 @@ impl TokenUsage {
     }
 
     pub fn new_from_anthropic(res: &AnthropicCompletionResponse) -> Self {
+        // Anthropic's input_tokens excludes the tokens read from or written to the cache.
+        let prompt_tokens = res.usage.input_tokens
+            + res.usage.cache_creation_input_tokens.unwrap_or_default()
+            + res.usage.cache_read_input_tokens.unwrap_or_default();
         Self {
-            tokens_cached: None,
-            prompt_tokens: res.usage.input_tokens,
+            tokens_cached: res.usage.cache_read_input_tokens,
+            tokens_cache_created: res.usage.cache_creation_input_tokens,
+            prompt_tokens,
             completion_tokens: res.usage.output_tokens,
-            total_tokens: res.usage.input_tokens + res.usage.output_tokens,
+            total_tokens: prompt_tokens + res.usage.output_tokens,
             dollar_cost: None,
             cents_cost: None,
         }
@@ -326,6 +337,11 @@ impl std::fmt::Display for TokenUsage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f)?;
         writeln!(f, "    tokens_cached: {:?}", self.tokens_cached)?;
+        writeln!(
+            f,
+            "    tokens_cache_created: {:?}",
+            self.tokens_cache_created
+        )?;
         writeln!(f, "    prompt_tokens: {:?}", self.prompt_tokens)?;
         writeln!(f, "    completion_tokens: {:?}", self.completion_tokens)?;
         writeln!(f, "    total_tokens: {:?}", self.total_tokens)?;
@@ -2,7 +2,9 @@ use std::{fs::File, io::Read, path::PathBuf};
 
 use serde::Deserialize;
 
-use crate::{BooleanTests, ExactStringTests, ExtractUrlsTest, IntegerTests, TestItem};
+use crate::{
+    BooleanTests, ExactStringTests, ExtractEntitiesTest, ExtractUrlsTest, IntegerTests, TestItem,
+};
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -85,9 +87,9 @@ impl TestSetsLoader {
         })
     }
 
-    // pub fn extract_entities(&self) -> crate::Result<Vec<ExtractEntitiesTest>> {
-    //     self.load_tests(self.test_file_path("extract_entities.json"))
-    // }
+    pub fn extract_entities(&self) -> crate::Result<Vec<ExtractEntitiesTest>> {
+        self.load_tests(self.test_file_path("extract_entities.json"))
+    }
 
     pub fn extract_urls(&self) -> crate::Result<Vec<ExtractUrlsTest>> {
         self.load_tests(self.test_file_path("extract_urls.json"))
@@ -136,46 +136,70 @@ impl TestItem for ExactStringTest {
     }
 }
 
-// #[derive(Debug, Deserialize, Serialize)]
-// pub struct ExtractEntitiesTest {
-//     pub supporting_material: String,
-//     test_level: u8,
-// }
-
-// impl ExtractEntitiesTest {
-//     pub fn check_result(
-//         &self,
-//         res: llm_client::workflows::nlp::extract::_entities::ExtractEntitiesResult,
-//     ) {
-//         let outcome = if res.results.is_some() {
-//             format!("🟢\n{}", res)
-//         } else {
-//             format!("🔴\n{}", res)
-//         };
-
-//         // Trim supporting_material to first 25 chars and add ellipsis if longer
-//         let trimmed_material = if self.supporting_material.len() > 25 {
-//             format!("{}...", &self.supporting_material[..25])
-//         } else {
-//             self.supporting_material.clone()
-//         };
-
-//         println!(
-//             "\n\nExtractEntities Test\nsupporting_material: '{}'\n{}",
-//             trimmed_material, outcome
-//         );
-//     }
-// }
-
-// impl TestItem for ExtractEntitiesTest {
-//     fn test_level(&self) -> u8 {
-//         self.test_level
-//     }
-
-//     fn is_optional(&self) -> bool {
-//         false
-//     }
-// }
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExtractEntitiesTest {
+    pub supporting_material: String,
+    correct_answers: Option<Vec<String>>,
+    test_level: u8,
+}
+
+impl ExtractEntitiesTest {
+    pub fn check_result(
+        &self,
+        res: llm_client::workflows::nlp::extract::entities::ExtractEntitiesResult,
+    ) {
+        let found: HashSet<&str> = res
+            .results
+            .iter()
+            .flatten()
+            .map(|entity| entity.text.as_str())
+            .collect();
+        let outcome = match &self.correct_answers {
+            Some(correct_answers) => {
+                let matched = correct_answers
+                    .iter()
+                    .filter(|answer| found.contains(answer.as_str()))
+                    .count();
+                let icon = if matched == correct_answers.len() {
+                    "🟢"
+                } else if matched > 0 {
+                    "🟡"
+                } else {
+                    "🔴"
+                };
+                format!(
+                    "{icon} found {matched} of {} expected entities\n{}",
+                    correct_answers.len(),
+                    res
+                )
+            }
+            None if found.is_empty() => format!("🟢 correct response: no entities\n{}", res),
+            None => format!("🔴 incorrect response: expected no entities\n{}", res),
+        };
+
+        // Trim supporting_material to first 25 chars and add ellipsis if longer
+        let trimmed_material = if self.supporting_material.len() > 25 {
+            format!("{}...", &self.supporting_material[..25])
+        } else {
+            self.supporting_material.clone()
+        };
+
+        println!(
+            "\n\nExtractEntities Test\nsupporting_material: '{}'\n{}",
+            trimmed_material, outcome
+        );
+    }
+}
+
+impl TestItem for ExtractEntitiesTest {
+    fn test_level(&self) -> u8 {
+        self.test_level
+    }
+
+    fn result_can_be_none(&self) -> bool {
+        self.correct_answers.is_none()
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ExtractUrlsTest {